@@ -4,8 +4,9 @@ use serde::{Deserialize, Serialize};
 #[allow(unused_imports)]
 #[cfg(feature = "rt")]
 use serde_with::{serde_as, DurationSecondsWithFrac};
+use std::collections::BTreeMap;
 use std::num::NonZeroU32;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 // TODO: Have a separate builder
 #[doc(hidden)]
@@ -16,11 +17,64 @@ pub struct ScenarioConfig {
     pub name: String,
     #[cfg_attr(feature = "rt", serde_as(as = "Option<DurationSecondsWithFrac>"))]
     pub duration: Option<Duration>,
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub iterations: Option<u64>,
+    /// Maximum number of transactions to send over the whole run
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub request_budget: Option<u64>,
+    /// Arguments provided with `.args()`, serialized so that they can be handed off to peers
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub args: Option<Vec<u8>>,
+    /// Whether to skip the body of each transaction, as set with `.dry_run()`
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub dry_run: bool,
     pub max_tps: Option<NonZeroU32>,
+    /// Whether `max_tps` is the total across all peers, rather than for this server alone
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub global_tps: bool,
+    /// Period between transactions, for rates below 1 TPS set with `.tps_every()`
+    #[cfg_attr(feature = "rt", serde(default))]
+    #[cfg_attr(feature = "rt", serde_as(as = "Option<DurationSecondsWithFrac>"))]
+    pub tps_period: Option<Duration>,
+    /// Error rate above which `max_tps` is backed off, as set with `.aimd()`
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub aimd: Option<f64>,
+    /// Caps on the TPS of individual transactions, by transaction name, as set with
+    /// `.transaction_tps()`
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub transaction_tps: BTreeMap<String, NonZeroU32>,
     pub error_rate: Option<f64>,
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub search: SearchStrategy,
+    /// Whether to search for the onset of overload, as set with `.onset()`
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub onset: bool,
     pub latency: Option<LatencyConfig>,
     #[cfg_attr(feature = "rt", serde(default))]
+    pub ramp: Option<RampConfig>,
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub stages: Option<Vec<Stage>>,
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub retry: Option<RetryPolicy>,
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub abort_error_rate: Option<ErrorRateAbort>,
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub seed: Option<u64>,
+    /// Labels attached to every metric the Scenario emits, as set with `.label()`
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub labels: BTreeMap<String, String>,
+    #[cfg_attr(feature = "rt", serde(default))]
     pub hints: HintConfig,
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub sampler: SamplerConfig,
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub whole_run_statistics: bool,
+    /// Dedicated runtime to run the Scenario on, as set with `.runtime()`
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub runtime: Option<RuntimeConfig>,
+    /// Wall-clock time at which the config was handed off to a peer
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub dispatched_at: Option<SystemTime>,
 }
 
 impl ScenarioConfig {
@@ -28,10 +82,30 @@ impl ScenarioConfig {
         Self {
             name: name.to_string(),
             duration: None,
+            iterations: None,
+            request_budget: None,
+            args: None,
+            dry_run: false,
             max_tps: None,
+            global_tps: false,
+            tps_period: None,
+            aimd: None,
+            transaction_tps: BTreeMap::new(),
             error_rate: None,
+            search: SearchStrategy::default(),
+            onset: false,
             latency: None,
+            ramp: None,
+            stages: None,
+            retry: None,
+            abort_error_rate: None,
+            seed: None,
+            labels: BTreeMap::new(),
             hints: HintConfig::default(),
+            sampler: SamplerConfig::default(),
+            whole_run_statistics: false,
+            runtime: None,
+            dispatched_at: None,
         }
     }
 
@@ -39,12 +113,29 @@ impl ScenarioConfig {
         // NOTE: Technically just setting `duration` should do _something_,
         // but its realistically an edge-case.
         #[allow(clippy::match_like_matches_macro)]
-        match (self.max_tps, self.error_rate, self.latency) {
-            (None, None, None) => true,
+        match (
+            self.max_tps,
+            self.error_rate,
+            self.latency,
+            self.ramp,
+            &self.stages,
+            self.onset,
+        ) {
+            (None, None, None, None, None, false) => true,
             _ => false,
         }
     }
 
+    /// The method which requires a `.duration()` to be set, if there isn't one. Scenarios limited
+    /// by `.iterations()` are exempt, since they have a natural end.
+    pub fn missing_duration(&self) -> Option<&'static str> {
+        if self.duration.is_some() || self.iterations.is_some() {
+            return None;
+        }
+
+        self.ramp.map(|_| "ramp_tps")
+    }
+
     pub fn starting_tps(&self) -> Option<NonZeroU32> {
         match self {
             ScenarioConfig {
@@ -53,12 +144,23 @@ impl ScenarioConfig {
             }
             | ScenarioConfig {
                 latency: Some(_), ..
-            } => Some(BASE_TPS),
+            }
+            | ScenarioConfig { onset: true, .. } => Some(BASE_TPS),
 
             ScenarioConfig {
                 max_tps: Some(tps), ..
             } => Some(*tps),
 
+            ScenarioConfig {
+                ramp: Some(RampConfig { start, .. }),
+                ..
+            } => Some(*start),
+
+            ScenarioConfig {
+                stages: Some(stages),
+                ..
+            } => stages.first().and_then(|s| NonZeroU32::new(s.target_tps)),
+
             _ => None,
         }
     }
@@ -67,6 +169,42 @@ impl ScenarioConfig {
         self.hints.concurrency
     }
 
+    /// Index of the stage which is active at the given point in the run, if stages are
+    /// configured. Returns the final stage once all stages have elapsed.
+    pub fn stage_at(&self, elapsed: Duration) -> Option<usize> {
+        let stages = self.stages.as_ref()?;
+
+        let mut end = Duration::ZERO;
+        for (idx, stage) in stages.iter().enumerate() {
+            end += stage.duration;
+            if elapsed < end {
+                return Some(idx);
+            }
+        }
+
+        stages.len().checked_sub(1)
+    }
+
+    /// Stamp the config with the current wall-clock time, just before handing it off to a peer.
+    pub fn mark_dispatched(&mut self) {
+        self.dispatched_at = Some(SystemTime::now());
+    }
+
+    /// Subtract the time a config spent in transit from its duration, returning the measured
+    /// delay. Called by the peer receiving the config.
+    ///
+    /// NOTE: Peers do not share a monotonic clock, so this relies on their wall clocks being
+    /// roughly in sync (e.g. via NTP), and the delay includes any clock skew between them. A
+    /// receiving clock which is behind the sender's is treated as zero delay.
+    pub fn account_for_transit(&mut self) -> Option<Duration> {
+        let dispatched_at = self.dispatched_at.take()?;
+        let delay = SystemTime::now()
+            .duration_since(dispatched_at)
+            .unwrap_or(Duration::ZERO);
+        self.duration = self.duration.map(|d| d.saturating_sub(delay));
+        Some(delay)
+    }
+
     #[allow(unused)]
     pub fn set_max_tps(&mut self, max_tps: NonZeroU32) {
         self.max_tps = Some(max_tps);
@@ -89,6 +227,112 @@ impl LatencyConfig {
     }
 }
 
+#[doc(hidden)]
+#[derive(Clone, Debug, Copy)]
+#[cfg_attr(feature = "rt", derive(Serialize, Deserialize))]
+pub struct RampConfig {
+    pub start: NonZeroU32,
+    pub end: NonZeroU32,
+}
+
+impl RampConfig {
+    pub fn new(start: NonZeroU32, end: NonZeroU32) -> Self {
+        Self { start, end }
+    }
+}
+
+/// Strategy used to search for the TPS matching a goal error rate.
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "rt", derive(Serialize, Deserialize))]
+pub enum SearchStrategy {
+    /// Double the TPS until the error rate is exceeded, then bisect between the last good TPS
+    /// and the overloaded TPS.
+    #[default]
+    Bisect,
+    /// Double the TPS until the error rate is exceeded, then approach the goal with
+    /// progressively smaller steps.
+    Step,
+}
+
+/// A single stage of a multi-stage load profile.
+///
+/// The scenario linearly ramps from the previous stage's `target_tps` to this stage's
+/// `target_tps` over the given `duration`. The first stage holds its `target_tps` for the entire
+/// stage, so a "hold" is expressed as a stage with the same target as the one before it.
+#[derive(Clone, Debug, Copy, PartialEq)]
+#[cfg_attr(feature = "rt", cfg_eval::cfg_eval, serde_as)]
+#[cfg_attr(feature = "rt", derive(Serialize, Deserialize))]
+pub struct Stage {
+    pub target_tps: u32,
+    #[cfg_attr(feature = "rt", serde_as(as = "DurationSecondsWithFrac"))]
+    pub duration: Duration,
+}
+
+impl Stage {
+    pub fn new(target_tps: u32, duration: Duration) -> Self {
+        Self {
+            target_tps,
+            duration,
+        }
+    }
+}
+
+/// Dedicated multi-threaded tokio runtime for a Scenario to run on, rather than the runtime it
+/// is awaited from.
+#[derive(Clone, Debug, Copy, PartialEq)]
+#[cfg_attr(feature = "rt", derive(Serialize, Deserialize))]
+pub struct RuntimeConfig {
+    /// Number of worker threads of the runtime
+    pub worker_threads: usize,
+}
+
+impl RuntimeConfig {
+    pub fn new(worker_threads: usize) -> Self {
+        Self { worker_threads }
+    }
+}
+
+/// Policy for retrying failed transactions marked with `#[transaction(retry)]`.
+///
+/// A failed transaction is retried up to `max_attempts` total attempts, waiting `backoff` before
+/// the first retry and doubling the wait for each subsequent retry.
+#[derive(Clone, Debug, Copy, PartialEq)]
+#[cfg_attr(feature = "rt", cfg_eval::cfg_eval, serde_as)]
+#[cfg_attr(feature = "rt", derive(Serialize, Deserialize))]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    #[cfg_attr(feature = "rt", serde_as(as = "DurationSecondsWithFrac"))]
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32, backoff: Duration) -> Self {
+        Self {
+            max_attempts,
+            backoff,
+        }
+    }
+
+    /// Time to wait before the given attempt, where the first attempt is `1`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        if attempt <= 1 {
+            Duration::ZERO
+        } else {
+            self.backoff
+                .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(2)))
+        }
+    }
+}
+
+/// Abort a run once the error rate exceeds `error_rate` for `consecutive_samples` samples
+/// in a row.
+#[derive(Clone, Debug, Copy, PartialEq)]
+#[cfg_attr(feature = "rt", derive(Serialize, Deserialize))]
+pub struct ErrorRateAbort {
+    pub error_rate: f64,
+    pub consecutive_samples: usize,
+}
+
 #[doc(hidden)]
 #[derive(Clone, Debug, Copy)]
 #[cfg_attr(feature = "rt", cfg_eval::cfg_eval, serde_as)]
@@ -105,6 +349,176 @@ impl Default for HintConfig {
     }
 }
 
+#[doc(hidden)]
+#[derive(Clone, Debug, Copy)]
+#[cfg_attr(feature = "rt", cfg_eval::cfg_eval, serde_as)]
+#[cfg_attr(feature = "rt", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "rt", serde(default))]
+pub struct SamplerConfig {
+    #[cfg_attr(feature = "rt", serde_as(as = "DurationSecondsWithFrac"))]
+    pub shutdown_timeout: Duration,
+    pub window: usize,
+    #[cfg_attr(feature = "rt", serde(default = "default_error_window"))]
+    pub error_window: usize,
+    #[cfg_attr(feature = "rt", serde(default = "default_tps_tolerance"))]
+    pub tps_tolerance: f64,
+    pub skip: usize,
+    #[cfg_attr(feature = "rt", serde(default))]
+    #[cfg_attr(feature = "rt", serde_as(as = "Option<DurationSecondsWithFrac>"))]
+    pub interval: Option<Duration>,
+    #[cfg_attr(feature = "rt", serde(default))]
+    #[cfg_attr(
+        feature = "rt",
+        serde_as(as = "Option<(DurationSecondsWithFrac, DurationSecondsWithFrac)>")
+    )]
+    pub interval_bounds: Option<(Duration, Duration)>,
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub controller: ControllerKind,
+    pub burst: NonZeroU32,
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub arrival: Arrival,
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub max_panics: Option<u64>,
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub concurrency_step: ConcurrencyStep,
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub max_concurrency: Option<usize>,
+    /// Multiple of the baseline p99 latency beyond which the concurrency is cut back
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub latency_backoff: Option<f64>,
+    /// Start from the hinted concurrency, rather than jumping to an estimate of the concurrency
+    /// needed after the first sample
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub skip_warmup: bool,
+    #[cfg_attr(feature = "rt", serde(default))]
+    pub tps_target: TpsTarget,
+}
+
+impl Default for SamplerConfig {
+    fn default() -> Self {
+        Self {
+            shutdown_timeout: crate::BASE_SHUTDOWN_TIMEOUT,
+            window: crate::BASE_SAMPLE_WINDOW,
+            error_window: crate::BASE_ERROR_WINDOW,
+            tps_tolerance: crate::BASE_TPS_TOLERANCE,
+            skip: crate::BASE_SKIP_SAMPLES,
+            interval: None,
+            interval_bounds: None,
+            controller: ControllerKind::default(),
+            burst: NonZeroU32::MIN,
+            arrival: Arrival::default(),
+            max_panics: None,
+            concurrency_step: ConcurrencyStep::default(),
+            max_concurrency: None,
+            latency_backoff: None,
+            skip_warmup: false,
+            tps_target: TpsTarget::default(),
+        }
+    }
+}
+
+#[cfg(feature = "rt")]
+fn default_error_window() -> usize {
+    crate::BASE_ERROR_WINDOW
+}
+
+#[cfg(feature = "rt")]
+fn default_tps_tolerance() -> f64 {
+    crate::BASE_TPS_TOLERANCE
+}
+
+/// Controller used to adjust concurrency to reach the goal TPS.
+#[derive(Clone, Debug, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "rt", derive(Serialize, Deserialize))]
+pub enum ControllerKind {
+    /// Scale concurrency by the ratio of goal TPS to measured TPS. Reaches the goal quickly,
+    /// but can overshoot when TPS does not scale linearly with concurrency.
+    #[default]
+    Ratio,
+    /// PID controller over the TPS error, with the given gains.
+    Pid(PidGains),
+    /// Keep the concurrency fixed at the starting concurrency (see [HintConfig]). The Scenario
+    /// is still sampled and measured, but runs below the goal TPS if the concurrency is too low
+    /// to reach it.
+    Fixed,
+}
+
+/// Distribution of the gaps between transactions sent at the goal TPS.
+#[derive(Clone, Debug, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "rt", derive(Serialize, Deserialize))]
+pub enum Arrival {
+    /// Space transactions evenly, allowing up to `burst` through at once.
+    #[default]
+    Constant,
+    /// Space transactions with exponentially distributed gaps, as if sent by many independent
+    /// clients. The burst is ignored.
+    Poisson,
+}
+
+/// Throughput which the goal TPS is measured in, and which the rate limiter paces.
+#[derive(Clone, Debug, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "rt", derive(Serialize, Deserialize))]
+pub enum TpsTarget {
+    /// Count each transaction, and pace them as they are sent.
+    #[default]
+    Transactions,
+    /// Count each run of the Scenario, and pace runs as they start. Transactions within a run
+    /// are sent as fast as the Scenario makes them.
+    Scenarios,
+}
+
+/// Maximum change in concurrency per adjustment.
+#[derive(Clone, Debug, Copy, PartialEq)]
+#[cfg_attr(feature = "rt", derive(Serialize, Deserialize))]
+pub enum ConcurrencyStep {
+    /// Change concurrency by at most this many tasks
+    Absolute(usize),
+    /// Change concurrency by at most this fraction of the current concurrency
+    Proportional(f64),
+}
+
+impl ConcurrencyStep {
+    /// Limit the change from `current` to `new` concurrency to this step. The concurrency can
+    /// always change by at least one.
+    pub fn limit(&self, current: usize, new: usize) -> usize {
+        let max_change = match *self {
+            ConcurrencyStep::Absolute(step) => step,
+            ConcurrencyStep::Proportional(ratio) => (current as f64 * ratio).ceil() as usize,
+        }
+        .max(1);
+
+        new.clamp(
+            current.saturating_sub(max_change),
+            current.saturating_add(max_change),
+        )
+    }
+}
+
+impl Default for ConcurrencyStep {
+    fn default() -> Self {
+        Self::Absolute(crate::BASE_CONCURRENCY_STEP)
+    }
+}
+
+/// Gains for [ControllerKind::Pid].
+#[derive(Clone, Debug, Copy, PartialEq)]
+#[cfg_attr(feature = "rt", derive(Serialize, Deserialize))]
+pub struct PidGains {
+    pub kp: f64,
+    pub ki: f64,
+    pub kd: f64,
+}
+
+impl Default for PidGains {
+    fn default() -> Self {
+        Self {
+            kp: 0.3,
+            ki: 0.6,
+            kd: 0.05,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -114,10 +528,126 @@ mod tests {
         insta::assert_json_snapshot!(ScenarioConfig {
             name: "test_scenario".to_string(),
             duration: Some(Duration::from_secs(300)),
+            iterations: Some(1_000_000),
+            request_budget: Some(5_000_000),
+            args: None,
+            dry_run: false,
             max_tps: Some(NonZeroU32::new(2_000).unwrap()),
+            global_tps: false,
+            tps_period: Some(Duration::from_secs(5)),
+            aimd: None,
+            transaction_tps: BTreeMap::from([(
+                "write_user".to_string(),
+                NonZeroU32::new(100).unwrap(),
+            )]),
             error_rate: Some(0.03),
+            search: SearchStrategy::Bisect,
+            onset: false,
             latency: Some(LatencyConfig::new(Duration::from_millis(20), 0.99)),
+            ramp: Some(RampConfig::new(
+                NonZeroU32::new(100).unwrap(),
+                NonZeroU32::new(1_000).unwrap(),
+            )),
+            stages: Some(vec![
+                Stage::new(100, Duration::from_secs(120)),
+                Stage::new(1_000, Duration::from_secs(300)),
+            ]),
+            retry: Some(RetryPolicy::new(3, Duration::from_millis(10))),
+            abort_error_rate: Some(ErrorRateAbort {
+                error_rate: 0.5,
+                consecutive_samples: 3,
+            }),
+            seed: Some(42),
+            labels: BTreeMap::from([("region".to_string(), "us-east-1".to_string())]),
             hints: HintConfig::default(),
+            sampler: SamplerConfig::default(),
+            whole_run_statistics: false,
+            runtime: Some(RuntimeConfig::new(4)),
+            dispatched_at: None,
         });
     }
+
+    #[test]
+    fn test_account_for_transit() {
+        let mut config = ScenarioConfig::new("test_scenario");
+        config.duration = Some(Duration::from_secs(10));
+        assert_eq!(config.account_for_transit(), None);
+
+        config.dispatched_at = Some(SystemTime::now() - Duration::from_millis(300));
+        let delay = config.account_for_transit().unwrap();
+        assert!(delay >= Duration::from_millis(300) && delay < Duration::from_secs(1));
+        assert_eq!(config.duration, Some(Duration::from_secs(10) - delay));
+        assert_eq!(config.dispatched_at, None);
+
+        // Receiving clock behind the sender's
+        config.dispatched_at = Some(SystemTime::now() + Duration::from_secs(5));
+        assert_eq!(config.account_for_transit(), Some(Duration::ZERO));
+        assert_eq!(config.duration, Some(Duration::from_secs(10) - delay));
+    }
+
+    #[test]
+    fn test_concurrency_step() {
+        let step = ConcurrencyStep::Absolute(100);
+        assert_eq!(step.limit(1_000, 5_000), 1_100);
+        assert_eq!(step.limit(1_000, 1_050), 1_050);
+        assert_eq!(step.limit(50, 1), 1);
+
+        let step = ConcurrencyStep::Proportional(0.25);
+        assert_eq!(step.limit(1_000, 5_000), 1_250);
+        assert_eq!(step.limit(1_000, 500), 750);
+        assert_eq!(step.limit(1, 10), 2);
+
+        // Steps too large to add to the concurrency leave it unlimited
+        let step = ConcurrencyStep::Absolute(usize::MAX);
+        assert_eq!(step.limit(1_000, 5_000), 5_000);
+        assert_eq!(step.limit(1_000, 1), 1);
+        let step = ConcurrencyStep::Proportional(f64::MAX);
+        assert_eq!(step.limit(1_000, usize::MAX), usize::MAX);
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let policy = RetryPolicy::new(4, Duration::from_millis(10));
+        assert_eq!(policy.backoff(1), Duration::ZERO);
+        assert_eq!(policy.backoff(2), Duration::from_millis(10));
+        assert_eq!(policy.backoff(3), Duration::from_millis(20));
+        assert_eq!(policy.backoff(4), Duration::from_millis(40));
+    }
+
+    #[test]
+    fn test_stage_at() {
+        let mut config = ScenarioConfig::new("test_scenario");
+        assert_eq!(config.stage_at(Duration::ZERO), None);
+
+        config.stages = Some(vec![
+            Stage::new(100, Duration::from_secs(10)),
+            Stage::new(1_000, Duration::from_secs(20)),
+            Stage::new(1_000, Duration::from_secs(5)),
+        ]);
+        assert_eq!(config.stage_at(Duration::ZERO), Some(0));
+        assert_eq!(config.stage_at(Duration::from_secs(10)), Some(1));
+        assert_eq!(config.stage_at(Duration::from_secs(29)), Some(1));
+        assert_eq!(config.stage_at(Duration::from_secs(31)), Some(2));
+        assert_eq!(config.stage_at(Duration::from_secs(100)), Some(2));
+    }
+
+    #[test]
+    fn test_missing_duration() {
+        let mut config = ScenarioConfig::new("test_scenario");
+        config.max_tps = Some(NonZeroU32::new(100).unwrap());
+        assert_eq!(config.missing_duration(), None);
+
+        config.ramp = Some(RampConfig::new(
+            NonZeroU32::new(100).unwrap(),
+            NonZeroU32::new(1_000).unwrap(),
+        ));
+        assert_eq!(config.missing_duration(), Some("ramp_tps"));
+
+        config.iterations = Some(1_000);
+        assert_eq!(config.missing_duration(), None);
+
+        config.iterations = None;
+        config.duration = Some(Duration::from_secs(60));
+        assert_eq!(config.missing_duration(), None);
+    }
 }
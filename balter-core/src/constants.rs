@@ -1,7 +1,15 @@
 use std::num::NonZeroU32;
 use std::time::Duration;
 
-pub const BASE_TPS: NonZeroU32 = unsafe { NonZeroU32::new_unchecked(512) };
+pub const BASE_TPS: NonZeroU32 = NonZeroU32::new(512).unwrap();
 pub const BASE_CONCURRENCY: usize = 10;
 pub const BASE_INTERVAL: Duration = Duration::from_millis(1000);
 pub const BASE_INTERVAL_SLOW: Duration = Duration::from_millis(5000);
+pub const MIN_INTERVAL: Duration = Duration::from_millis(10);
+pub const MAX_INTERVAL: Duration = Duration::from_millis(10_000);
+pub const BASE_SHUTDOWN_TIMEOUT: Duration = Duration::from_millis(1000);
+pub const BASE_SAMPLE_WINDOW: usize = 5;
+pub const BASE_ERROR_WINDOW: usize = 1;
+pub const BASE_TPS_TOLERANCE: f64 = 0.02;
+pub const BASE_SKIP_SAMPLES: usize = 0;
+pub const BASE_CONCURRENCY_STEP: usize = 100;
@@ -0,0 +1,127 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Relative accuracy of the quantiles of a [LatencyHistogram]
+const RELATIVE_ACCURACY: f64 = 0.01;
+/// Ratio between the bounds of consecutive buckets
+const GAMMA: f64 = (1. + RELATIVE_ACCURACY) / (1. - RELATIVE_ACCURACY);
+
+/// Histogram of transaction latencies, with logarithmically sized buckets.
+///
+/// Unlike pre-computed quantiles, histograms can be merged (e.g. those of each peer in a
+/// distributed run) without losing accuracy: quantiles of the merged histogram are within 1% of
+/// the true quantiles across all the latencies recorded.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LatencyHistogram {
+    /// Number of latencies in each bucket, by bucket index
+    buckets: BTreeMap<i32, u64>,
+    /// Number of latencies under a nanosecond
+    zero: u64,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        let nanos = latency.as_nanos() as f64;
+        if nanos < 1. {
+            self.zero += 1;
+            return;
+        }
+        let index = (nanos.ln() / GAMMA.ln()).ceil() as i32;
+        *self.buckets.entry(index).or_default() += 1;
+    }
+
+    /// Add the latencies recorded by another histogram to this one
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        self.zero += other.zero;
+        for (index, count) in &other.buckets {
+            *self.buckets.entry(*index).or_default() += count;
+        }
+    }
+
+    /// Number of latencies recorded
+    pub fn count(&self) -> u64 {
+        self.zero + self.buckets.values().sum::<u64>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count() == 0
+    }
+
+    /// Latency at the given quantile (between 0 and 1), or zero if the histogram is empty
+    pub fn quantile(&self, quantile: f64) -> Duration {
+        let count = self.count();
+        if count == 0 {
+            return Duration::ZERO;
+        }
+
+        let rank = (quantile.clamp(0., 1.) * (count - 1) as f64).round() as u64;
+        let mut seen = self.zero;
+        if rank < seen {
+            return Duration::ZERO;
+        }
+        for (index, bucket) in &self.buckets {
+            seen += bucket;
+            if rank < seen {
+                // NOTE: The midpoint of the bucket (relative to its bounds), which is within the
+                // relative accuracy of every latency in it.
+                let nanos = 2. * GAMMA.powi(*index) / (GAMMA + 1.);
+                return Duration::from_nanos(nanos.round() as u64);
+            }
+        }
+        unreachable!("Rank is always below the total count")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Duration, expected: Duration) {
+        let error = (actual.as_secs_f64() - expected.as_secs_f64()).abs();
+        assert!(
+            error <= expected.as_secs_f64() * RELATIVE_ACCURACY,
+            "{actual:?} vs {expected:?}"
+        );
+    }
+
+    #[test]
+    fn test_quantiles() {
+        let mut histogram = LatencyHistogram::new();
+        assert_eq!(histogram.quantile(0.5), Duration::ZERO);
+
+        for ms in 1..=1_000 {
+            histogram.record(Duration::from_millis(ms));
+        }
+        assert_eq!(histogram.count(), 1_000);
+        assert_close(histogram.quantile(0.), Duration::from_millis(1));
+        assert_close(histogram.quantile(0.5), Duration::from_micros(500_500));
+        assert_close(histogram.quantile(0.99), Duration::from_millis(990));
+        assert_close(histogram.quantile(1.), Duration::from_millis(1_000));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut fast = LatencyHistogram::new();
+        let mut slow = LatencyHistogram::new();
+        for _ in 0..900 {
+            fast.record(Duration::from_millis(10));
+        }
+        for _ in 0..100 {
+            slow.record(Duration::from_millis(200));
+        }
+        fast.record(Duration::ZERO);
+
+        fast.merge(&slow);
+        assert_eq!(fast.count(), 1_001);
+        assert_eq!(fast.quantile(0.), Duration::ZERO);
+        assert_close(fast.quantile(0.5), Duration::from_millis(10));
+        assert_close(fast.quantile(0.95), Duration::from_millis(200));
+    }
+}
@@ -1,9 +1,11 @@
 mod config;
 mod constants;
+mod histogram;
 mod metrics;
 mod stats;
 
 pub use config::*;
 pub use constants::*;
+pub use histogram::*;
 pub use metrics::*;
 pub use stats::*;
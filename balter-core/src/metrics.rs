@@ -1,17 +1,21 @@
 #[derive(Copy, Clone)]
 pub struct TransactionLabels {
+    pub name: &'static str,
     pub success: &'static str,
     pub error: &'static str,
     pub latency: &'static str,
+    pub retries: &'static str,
 }
 
 #[macro_export]
 macro_rules! generate_labels {
     ($base_name:expr) => {
         ::balter::core::TransactionLabels {
-            success: concat!(stringify!($base_name), "_success"),
-            error: concat!(stringify!($base_name), "_error"),
-            latency: concat!(stringify!($base_name), "_latency"),
+            name: stringify!($base_name),
+            success: concat!("balter_transaction_", stringify!($base_name), "_success"),
+            error: concat!("balter_transaction_", stringify!($base_name), "_error"),
+            latency: concat!("balter_transaction_", stringify!($base_name), "_latency"),
+            retries: concat!("balter_transaction_", stringify!($base_name), "_retries"),
         }
     };
 }
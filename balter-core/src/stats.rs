@@ -1,15 +1,567 @@
+use crate::LatencyHistogram;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[allow(unused_imports)]
+#[cfg(feature = "serde")]
+use serde_with::{serde_as, DurationSecondsWithFrac};
+use std::collections::BTreeMap;
+use std::ops::AddAssign;
 use std::time::Duration;
 
 /// Run Statistics for a given Scenario
+///
+/// With the `serde` feature enabled this can be serialized, e.g. to JSON, to compare runs
+/// against a baseline. Durations are serialized as (fractional) seconds.
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", cfg_eval::cfg_eval, serde_as)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RunStatistics {
     pub concurrency: usize,
     pub goal_tps: u32,
+    /// The TPS set with `.tps()`, if any. With `.aimd()`, `goal_tps` is the TPS it was backed
+    /// off to by the end of the run, which may fall short of this.
+    pub requested_tps: Option<u32>,
+    /// Measured throughput in the units of `goal_tps`: `transaction_tps` by default, or
+    /// `scenario_tps` with `.tps_target(TpsTarget::Scenarios)`
     pub actual_tps: f64,
+    /// Successful transactions per second
+    pub transaction_tps: f64,
+    /// Completed runs of the Scenario per second, whether or not they succeeded
+    pub scenario_tps: f64,
+    #[cfg_attr(feature = "serde", serde_as(as = "DurationSecondsWithFrac"))]
     pub latency_p50: Duration,
+    #[cfg_attr(feature = "serde", serde_as(as = "DurationSecondsWithFrac"))]
     pub latency_p90: Duration,
+    #[cfg_attr(feature = "serde", serde_as(as = "DurationSecondsWithFrac"))]
     pub latency_p95: Duration,
+    #[cfg_attr(feature = "serde", serde_as(as = "DurationSecondsWithFrac"))]
     pub latency_p99: Duration,
+    #[cfg_attr(feature = "serde", serde_as(as = "DurationSecondsWithFrac"))]
+    pub latency_max: Duration,
+    /// For Scenarios run with `.latency()`, the latency measured at the configured quantile.
+    /// `goal_tps` is then the highest TPS found to keep within the latency threshold.
+    #[cfg_attr(feature = "serde", serde_as(as = "Option<DurationSecondsWithFrac>"))]
+    pub latency_at_quantile: Option<Duration>,
+    /// Histogram of the latencies the quantiles above are read from, so that runs can be
+    /// combined with [RunStatistics::merge]
+    pub latency_histogram: LatencyHistogram,
     pub error_rate: f64,
+    /// For Scenarios run with `.onset()`, the highest TPS found before the error rate started
+    /// to climb. `None` if errors never climbed within the run.
+    pub onset_tps: Option<u32>,
     pub tps_limited: bool,
+    /// Whether the goal TPS could not be reached within the concurrency set by
+    /// `.max_concurrency()`. Implies `tps_limited`.
+    pub concurrency_limited: bool,
+    pub stable: bool,
+    /// The stage active during the final sample, for scenarios run with `.stages()`
+    pub stage: Option<usize>,
+    /// Why the Scenario stopped running
+    pub stop_reason: StopReason,
+    /// Whether the Scenario was aborted early because it was misbehaving, rather than
+    /// completing or being stopped. See [StopReason::is_abort].
+    pub aborted: bool,
+    /// Total time the Scenario ran for. With a `.duration()`, the final sample is cut short at
+    /// the end of the duration, so this only overruns it by the time taken to shut down.
+    #[cfg_attr(feature = "serde", serde_as(as = "DurationSecondsWithFrac"))]
+    pub elapsed: Duration,
+    /// Number of times the Scenario panicked. Each panic is also counted as a failed
+    /// transaction of [ErrorKind::Panic].
+    pub panics: u64,
+    /// Number of times the Scenario returned an error. These are counted separately from
+    /// transaction errors, and do not contribute to the error rate.
+    pub scenario_errors: u64,
+    /// Transaction errors over the whole run, by kind
+    pub errors: ErrorCounts,
+    /// Number of responses with each HTTP status code over the whole run, for requests sent with
+    /// `balter::http::request`. Includes successful responses.
+    pub status_codes: BTreeMap<u16, u64>,
+    /// Distribution of the concurrency over the run
+    pub concurrency_summary: ConcurrencySummary,
+}
+
+impl RunStatistics {
+    /// Combine the statistics of another run of the same Scenario, such as that of a peer in a
+    /// distributed run, into a single view across both.
+    ///
+    /// Throughput, concurrency and counts are summed, and the latency quantiles are read from
+    /// the merged [LatencyHistogram]s, as they are for a single run. The error rate is weighted by the number of
+    /// transactions measured in each run, and `latency_at_quantile` is the higher of the two.
+    /// The merged run is TPS limited or aborted if either run was, and stable only if both
+    /// were; it keeps the stop reason of an aborted run over that of one which wasn't. The
+    /// elapsed time is the longer of the two, and the concurrency summary is summed field by
+    /// field, which is the fleet-wide concurrency if both runs scaled up and down together.
+    ///
+    /// # Example
+    /// ```
+    /// use balter_core::RunStatistics;
+    ///
+    /// fn fleet_wide(runs: Vec<RunStatistics>) -> RunStatistics {
+    ///     runs.into_iter().fold(RunStatistics::default(), |mut merged, run| {
+    ///         merged.merge(&run);
+    ///         merged
+    ///     })
+    /// }
+    /// ```
+    pub fn merge(&mut self, other: &RunStatistics) {
+        let (count, other_count) = (
+            self.latency_histogram.count(),
+            other.latency_histogram.count(),
+        );
+        if count + other_count > 0 {
+            self.error_rate = (self.error_rate * count as f64
+                + other.error_rate * other_count as f64)
+                / (count + other_count) as f64;
+        }
+
+        self.latency_histogram.merge(&other.latency_histogram);
+        if !self.latency_histogram.is_empty() {
+            self.latency_p50 = self.latency_histogram.quantile(0.5);
+            self.latency_p90 = self.latency_histogram.quantile(0.9);
+            self.latency_p95 = self.latency_histogram.quantile(0.95);
+            self.latency_p99 = self.latency_histogram.quantile(0.99);
+            self.latency_max = self.latency_histogram.quantile(1.);
+        }
+        self.latency_at_quantile = self.latency_at_quantile.max(other.latency_at_quantile);
+
+        self.concurrency += other.concurrency;
+        self.goal_tps = self.goal_tps.saturating_add(other.goal_tps);
+        self.requested_tps = add_options(self.requested_tps, other.requested_tps);
+        self.actual_tps += other.actual_tps;
+        self.transaction_tps += other.transaction_tps;
+        self.scenario_tps += other.scenario_tps;
+        self.onset_tps = add_options(self.onset_tps, other.onset_tps);
+
+        self.tps_limited |= other.tps_limited;
+        self.concurrency_limited |= other.concurrency_limited;
+        self.stable &= other.stable;
+        self.stage = self.stage.or(other.stage);
+        if other.aborted && !self.aborted {
+            self.stop_reason = other.stop_reason;
+        }
+        self.aborted |= other.aborted;
+        self.elapsed = self.elapsed.max(other.elapsed);
+
+        self.panics += other.panics;
+        self.scenario_errors += other.scenario_errors;
+        self.errors += other.errors;
+        for (status, count) in &other.status_codes {
+            *self.status_codes.entry(*status).or_default() += count;
+        }
+
+        let summary = &mut self.concurrency_summary;
+        summary.min += other.concurrency_summary.min;
+        summary.max += other.concurrency_summary.max;
+        summary.median += other.concurrency_summary.median;
+        summary.mode += other.concurrency_summary.mode;
+        summary.changes += other.concurrency_summary.changes;
+    }
+}
+
+fn add_options(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.saturating_add(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Category of a failed transaction, used to tell apart e.g. timeouts from server errors.
+///
+/// A transaction fails if it returns an error (of any kind, including timeouts), or if the
+/// Scenario panics while running it. Every failure is counted exactly once, and failures are
+/// all the error rate is computed from: the `error_rate` of [RunStatistics] and of each sample
+/// the controllers see is the fraction of transactions which failed, and [ErrorCounts::total]
+/// is the number which failed over the run. Each failure is also counted in the
+/// `balter_transaction_error` metric, except for panics, which are counted in
+/// `balter_scenario_panic_count` since the transaction they interrupted is unknown.
+///
+/// See `TransactionError` for how transactions report the kind of their errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ErrorKind {
+    /// The request timed out
+    Timeout,
+    /// The connection could not be established, or was dropped
+    Connection,
+    /// The service responded with a server error (e.g. HTTP 5xx)
+    Server,
+    /// The service rejected the request (e.g. HTTP 4xx)
+    Client,
+    /// Any other error
+    Other,
+    /// The Scenario panicked
+    Panic,
+}
+
+impl ErrorKind {
+    pub const ALL: [ErrorKind; 6] = [
+        ErrorKind::Timeout,
+        ErrorKind::Connection,
+        ErrorKind::Server,
+        ErrorKind::Client,
+        ErrorKind::Other,
+        ErrorKind::Panic,
+    ];
+
+    /// Label used for the `kind` label on error metrics
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Connection => "connection",
+            ErrorKind::Server => "server",
+            ErrorKind::Client => "client",
+            ErrorKind::Other => "other",
+            ErrorKind::Panic => "panic",
+        }
+    }
+
+    /// Index of the kind within [ErrorKind::ALL]
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Number of transaction errors of each [ErrorKind]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ErrorCounts {
+    pub timeout: u64,
+    pub connection: u64,
+    pub server: u64,
+    pub client: u64,
+    pub other: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub panic: u64,
+}
+
+impl ErrorCounts {
+    pub fn get(&self, kind: ErrorKind) -> u64 {
+        match kind {
+            ErrorKind::Timeout => self.timeout,
+            ErrorKind::Connection => self.connection,
+            ErrorKind::Server => self.server,
+            ErrorKind::Client => self.client,
+            ErrorKind::Other => self.other,
+            ErrorKind::Panic => self.panic,
+        }
+    }
+
+    /// Total number of failed transactions
+    pub fn total(&self) -> u64 {
+        self.timeout + self.connection + self.server + self.client + self.other + self.panic
+    }
+}
+
+impl AddAssign for ErrorCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.timeout += other.timeout;
+        self.connection += other.connection;
+        self.server += other.server;
+        self.client += other.client;
+        self.other += other.other;
+        self.panic += other.panic;
+    }
+}
+
+impl From<[u64; 6]> for ErrorCounts {
+    fn from(counts: [u64; 6]) -> Self {
+        let [timeout, connection, server, client, other, panic] = counts;
+        Self {
+            timeout,
+            connection,
+            server,
+            client,
+            other,
+            panic,
+        }
+    }
+}
+
+/// Distribution of the concurrency over a run, weighted by the time spent at each concurrency.
+///
+/// A Scenario which settled spends most of its run at a single concurrency, with few changes,
+/// whereas one which thrashed has a wide spread between `min` and `max` and many changes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ConcurrencySummary {
+    /// Lowest concurrency sampled
+    pub min: usize,
+    /// Highest concurrency sampled
+    pub max: usize,
+    /// Concurrency at or below which the Scenario spent half of its run
+    pub median: usize,
+    /// Concurrency the Scenario spent the most time at
+    pub mode: usize,
+    /// Number of times the concurrency changed between samples
+    pub changes: usize,
+}
+
+impl ConcurrencySummary {
+    /// Summarize the time spent at each concurrency, given in order of concurrency.
+    pub fn from_dwell(dwell: &[(usize, Duration)], changes: usize) -> Self {
+        let (Some((min, _)), Some((max, _))) = (dwell.first(), dwell.last()) else {
+            return Self::default();
+        };
+
+        let total: Duration = dwell.iter().map(|(_, d)| *d).sum();
+        let mut cumulative = Duration::ZERO;
+        let median = dwell
+            .iter()
+            .find(|(_, d)| {
+                cumulative += *d;
+                cumulative * 2 >= total
+            })
+            .map_or(*max, |(c, _)| *c);
+        let mode = dwell
+            .iter()
+            .rev()
+            .max_by_key(|(_, d)| *d)
+            .map_or(*max, |(c, _)| *c);
+
+        Self {
+            min: *min,
+            max: *max,
+            median,
+            mode,
+            changes,
+        }
+    }
+}
+
+/// Snapshot of a running Scenario, taken each time Balter samples it
+#[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", cfg_eval::cfg_eval, serde_as)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LiveStats {
+    /// Time elapsed since the Scenario started
+    #[cfg_attr(feature = "serde", serde_as(as = "DurationSecondsWithFrac"))]
+    pub elapsed: Duration,
+    pub concurrency: usize,
+    pub goal_tps: u32,
+    /// TPS measured over the most recent sample window
+    pub actual_tps: f64,
+    /// Error rate measured over the most recent sample window
+    pub error_rate: f64,
+    pub stable: bool,
+}
+
+/// Reason a Scenario stopped running
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum StopReason {
+    /// The configured duration elapsed
+    #[default]
+    Duration,
+    /// The condition provided to `.until()` was met
+    Until,
+    /// The number of iterations provided to `.iterations()` completed
+    Iterations,
+    /// The request budget provided to `.request_budget()` was used up
+    Budget,
+    /// The Scenario panicked more times than allowed by `.max_panics()`
+    Panics,
+    /// The Scenario was stopped with `ScenarioHandle::stop()`
+    Stopped,
+    /// The error rate exceeded the threshold set by `.abort_on_error_rate()`
+    ErrorRate,
+    /// A Controller provided to `.tps_controller()` requested that the Scenario stop
+    Controller,
+    /// The Scenario was not run, because it was configured with a mode which requires a
+    /// `.duration()` (such as `.ramp_tps()`) without one
+    MissingDuration,
+    /// The Scenario completed runs without recording any transactions, which usually means it
+    /// never calls a `#[transaction]`. The goal TPS counts transactions, so it could never be
+    /// measured.
+    NoTransactions,
+}
+
+impl StopReason {
+    /// Whether the Scenario was cut short because it was misbehaving or misconfigured
+    pub fn is_abort(&self) -> bool {
+        matches!(
+            self,
+            StopReason::Panics
+                | StopReason::ErrorRate
+                | StopReason::MissingDuration
+                | StopReason::NoTransactions
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_counts() {
+        let counts = ErrorCounts::from([3, 0, 2, 0, 1, 0]);
+        for (i, kind) in ErrorKind::ALL.iter().enumerate() {
+            assert_eq!(kind.index(), i);
+        }
+        assert_eq!(counts.get(ErrorKind::Timeout), 3);
+        assert_eq!(counts.get(ErrorKind::Server), 2);
+        assert_eq!(counts.total(), 6);
+    }
+
+    #[test]
+    fn test_run_statistics_serialization() {
+        insta::assert_json_snapshot!(RunStatistics {
+            concurrency: 12,
+            goal_tps: 1_000,
+            requested_tps: Some(1_000),
+            actual_tps: 998.5,
+            transaction_tps: 998.5,
+            scenario_tps: 499.25,
+            latency_p50: Duration::from_millis(2),
+            latency_p90: Duration::from_millis(5),
+            latency_p95: Duration::from_millis(8),
+            latency_p99: Duration::from_millis(20),
+            latency_max: Duration::from_millis(150),
+            latency_at_quantile: None,
+            latency_histogram: {
+                let mut histogram = LatencyHistogram::new();
+                histogram.record(Duration::from_millis(2));
+                histogram.record(Duration::from_millis(150));
+                histogram
+            },
+            error_rate: 0.001,
+            onset_tps: None,
+            tps_limited: false,
+            concurrency_limited: false,
+            stable: true,
+            stage: None,
+            stop_reason: StopReason::Duration,
+            aborted: false,
+            elapsed: Duration::from_secs(300),
+            panics: 0,
+            scenario_errors: 0,
+            errors: ErrorCounts {
+                timeout: 2,
+                server: 1,
+                ..Default::default()
+            },
+            status_codes: BTreeMap::from([(200, 299_400), (500, 1)]),
+            concurrency_summary: ConcurrencySummary {
+                min: 1,
+                max: 16,
+                median: 12,
+                mode: 12,
+                changes: 5,
+            },
+        });
+    }
+
+    #[test]
+    fn test_merge() {
+        let run = |latency, count, error_rate, stop_reason: StopReason| {
+            let mut latency_histogram = LatencyHistogram::new();
+            for _ in 0..count {
+                latency_histogram.record(latency);
+            }
+            RunStatistics {
+                concurrency: 10,
+                goal_tps: 500,
+                requested_tps: Some(500),
+                actual_tps: 490.,
+                latency_histogram,
+                error_rate,
+                stop_reason,
+                aborted: stop_reason.is_abort(),
+                stable: !stop_reason.is_abort(),
+                elapsed: Duration::from_secs(count),
+                errors: ErrorCounts {
+                    timeout: 1,
+                    ..Default::default()
+                },
+                status_codes: BTreeMap::from([(200, count)]),
+                ..Default::default()
+            }
+        };
+
+        let mut merged = run(Duration::from_millis(10), 900, 0., StopReason::Duration);
+        merged.merge(&run(
+            Duration::from_millis(100),
+            100,
+            0.5,
+            StopReason::ErrorRate,
+        ));
+
+        assert_eq!(merged.concurrency, 20);
+        assert_eq!(merged.goal_tps, 1_000);
+        assert_eq!(merged.requested_tps, Some(1_000));
+        assert_eq!(merged.actual_tps, 980.);
+        assert_eq!(merged.error_rate, 0.05);
+        assert!((merged.latency_p50.as_secs_f64() - 0.01).abs() < 0.001);
+        assert!((merged.latency_p99.as_secs_f64() - 0.1).abs() < 0.001);
+        assert_eq!(merged.stop_reason, StopReason::ErrorRate);
+        assert!(merged.aborted);
+        assert!(!merged.stable);
+        assert_eq!(merged.elapsed, Duration::from_secs(900));
+        assert_eq!(merged.errors.timeout, 2);
+        assert_eq!(merged.status_codes[&200], 1_000);
+    }
+
+    #[test]
+    fn test_merge_empty() {
+        let mut latency_histogram = LatencyHistogram::new();
+        for ms in 1..=1_000 {
+            latency_histogram.record(Duration::from_millis(ms));
+        }
+        let run = RunStatistics {
+            latency_p50: latency_histogram.quantile(0.5),
+            latency_p90: latency_histogram.quantile(0.9),
+            latency_p95: latency_histogram.quantile(0.95),
+            latency_p99: latency_histogram.quantile(0.99),
+            latency_max: latency_histogram.quantile(1.),
+            latency_histogram,
+            ..Default::default()
+        };
+        let quantiles = |run: &RunStatistics| {
+            [
+                run.latency_p50,
+                run.latency_p90,
+                run.latency_p95,
+                run.latency_p99,
+                run.latency_max,
+            ]
+        };
+
+        let mut merged = run.clone();
+        merged.merge(&RunStatistics::default());
+        assert_eq!(quantiles(&merged), quantiles(&run));
+
+        let mut merged = RunStatistics::default();
+        merged.merge(&run);
+        assert_eq!(quantiles(&merged), quantiles(&run));
+    }
+
+    #[test]
+    fn test_concurrency_summary() {
+        assert_eq!(
+            ConcurrencySummary::from_dwell(&[], 0),
+            ConcurrencySummary::default()
+        );
+
+        let summary = ConcurrencySummary::from_dwell(
+            &[
+                (2, Duration::from_secs(1)),
+                (4, Duration::from_secs(2)),
+                (8, Duration::from_secs(10)),
+                (16, Duration::from_secs(3)),
+            ],
+            6,
+        );
+        assert_eq!(summary.min, 2);
+        assert_eq!(summary.max, 16);
+        assert_eq!(summary.median, 8);
+        assert_eq!(summary.mode, 8);
+        assert_eq!(summary.changes, 6);
+
+        let summary = ConcurrencySummary::from_dwell(
+            &[(4, Duration::from_secs(10)), (8, Duration::from_secs(1))],
+            1,
+        );
+        assert_eq!(summary.median, 4);
+        assert_eq!(summary.mode, 4);
+    }
 }
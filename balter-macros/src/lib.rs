@@ -1,12 +1,25 @@
 use proc_macro::TokenStream;
 use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::quote;
-use syn::{Ident, ItemFn};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Expr, ExprLit, Ident, ItemFn, Lit, LitStr, Meta, MetaNameValue, Token, Type};
 
 /// Proc macro to denote a Transaction
 ///
-/// NOTE: Currently this macro only works on functions with a `Result<T, E>` return value. This is a
-/// restriction which will be lifted soon.
+/// Transactions returning a `Result<T, E>` are counted as an error when they return `Err`.
+/// Transactions returning any other type are infallible: they are timed and always counted as a
+/// success, and never increment the error count. The return type is detected by name, so a
+/// `Result` alias with a different name (e.g. `type Response = Result<..>`) is treated as
+/// infallible.
+///
+/// Errors are counted by `ErrorKind` if the error type implements `TransactionError`, and as
+/// `ErrorKind::Other` otherwise.
+///
+/// Failed transactions can be retried according to the Scenario's retry policy (see
+/// `ConfigurableScenario::retry`) by using `#[transaction(retry)]`. Retried transactions run their
+/// body multiple times, so they cannot consume owned arguments. Only fallible transactions can be
+/// retried.
 ///
 /// # Example
 /// ```ignore
@@ -16,13 +29,34 @@ use syn::{Ident, ItemFn};
 /// fn my_transaction(arg_1: u32, arg_2: &str) -> Result<String, MyError> {
 ///     ...
 /// }
+///
+/// #[transaction]
+/// fn my_infallible_transaction(arg_1: u32) -> u64 {
+///     ...
+/// }
+///
+/// #[transaction(retry)]
+/// fn my_flaky_transaction(arg_1: u32, arg_2: &str) -> Result<String, MyError> {
+///     ...
+/// }
 /// ```
 #[proc_macro_attribute]
 pub fn transaction(attr: TokenStream, item: TokenStream) -> TokenStream {
     transaction_internal(attr, item).into()
 }
 
-fn transaction_internal(_attr: TokenStream, item: TokenStream) -> TokenStream2 {
+fn transaction_internal(attr: TokenStream, item: TokenStream) -> TokenStream2 {
+    let retry = if attr.is_empty() {
+        false
+    } else {
+        let attr = syn::parse::<Ident>(attr).expect("Unsupported transaction attribute");
+        if attr != "retry" {
+            return syn::Error::new(attr.span(), "Unsupported transaction attribute")
+                .to_compile_error();
+        }
+        true
+    };
+
     let input = syn::parse::<ItemFn>(item).unwrap();
 
     let ItemFn {
@@ -34,19 +68,111 @@ fn transaction_internal(_attr: TokenStream, item: TokenStream) -> TokenStream2 {
     let stmts = &block.stmts;
 
     let ident = &sig.ident;
-    quote! {
-        #(#attrs)* #vis #sig {
-            ::balter::transaction::transaction_hook(::balter::core::generate_labels!(#ident), async move {
-                #(#stmts)*
-            }).await
+    let output = match &sig.output {
+        syn::ReturnType::Type(_, ty) if is_result(ty) => ty,
+        _ if retry => {
+            return syn::Error::new_spanned(
+                &sig,
+                "Only transactions returning a Result can be retried",
+            )
+            .to_compile_error()
+        }
+        _ => {
+            let output = match &sig.output {
+                syn::ReturnType::Type(_, ty) => quote! { #ty },
+                syn::ReturnType::Default => quote! { () },
+            };
+            // NOTE: An `impl Trait` output can't be named, so it never has a dry run output.
+            let dry_run = match &sig.output {
+                syn::ReturnType::Type(_, ty) if matches!(**ty, Type::ImplTrait(_)) => {
+                    quote! { || None }
+                }
+                _ => quote! {
+                    || {
+                        #[allow(unused_imports)]
+                        use ::balter::transaction::{DryRunDefault as _, DryRunFallback as _};
+                        (&&::balter::transaction::DryRun::<#output>::new()).dry_run_output()
+                    }
+                },
+            };
+            return quote! {
+                #(#attrs)* #vis #sig {
+                    ::balter::transaction::transaction_infallible_hook(::balter::core::generate_labels!(#ident), async move {
+                        #(#stmts)*
+                    }, #dry_run).await
+                }
+            };
+        }
+    };
+    let classify = quote! {
+        |__balter_err| {
+            #[allow(unused_imports)]
+            use ::balter::transaction::{ClassifyFallback as _, ClassifySpecific as _};
+            (&&::balter::transaction::Classify(__balter_err)).error_kind()
+        }
+    };
+    let dry_run = quote! {
+        || {
+            #[allow(unused_imports)]
+            use ::balter::transaction::{DryRunFallback as _, DryRunResult as _};
+            (&&::balter::transaction::DryRun::<#output>::new()).dry_run_output()
         }
+    };
+    if retry {
+        quote! {
+            #(#attrs)* #vis #sig {
+                let mut __balter_attempt = 0;
+                loop {
+                    __balter_attempt += 1;
+                    if let Some(res) = ::balter::transaction::transaction_retry_hook(::balter::core::generate_labels!(#ident), __balter_attempt, ::balter::transaction::with_output::<#output, _>(async {
+                        #(#stmts)*
+                    }), #classify, #dry_run).await {
+                        break res;
+                    }
+                }
+            }
+        }
+    } else {
+        quote! {
+            #(#attrs)* #vis #sig {
+                ::balter::transaction::transaction_hook(::balter::core::generate_labels!(#ident), ::balter::transaction::with_output::<#output, _>(async move {
+                    #(#stmts)*
+                }), #classify, #dry_run).await
+            }
+        }
+    }
+}
+
+/// Whether the return type of a transaction is a `Result`, going by the name of the type. This
+/// covers `Result` aliases such as `anyhow::Result`, but not aliases with a different name.
+fn is_result(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Result"),
+        Type::Group(group) => is_result(&group.elem),
+        Type::Paren(paren) => is_result(&paren.elem),
+        _ => false,
     }
 }
 
 /// Proc macro to denote a Scenario
 ///
-/// NOTE: Currently this macro only works on functions which take no arguments and with no return value.
-/// (void functions). This is a restriction which will be lifted soon.
+/// NOTE: Currently this macro only works on functions which take no arguments. This is a
+/// restriction which will be lifted soon.
+///
+/// Scenarios may either return nothing, or a `Result<(), E>` where `E: Display`. Errors are logged
+/// and counted in `RunStatistics::scenario_errors`, separately from transaction errors.
+///
+/// The Scenario is named after the function by default, which is used in metric labels and to
+/// look it up in distributed runs. Use `#[scenario(name = "...")]` to give it a different name.
+///
+/// Scenarios are run on a multi-threaded runtime by default, which requires them to be `Send`.
+/// Use `#[scenario(single_threaded)]` for scenarios which hold `!Send` values (such as `Rc` or
+/// FFI handles) across an await. These run on a dedicated thread, with their tasks spawned onto
+/// a tokio `LocalSet`, so their throughput is limited to what a single thread can drive.
 ///
 /// See the `Scenario` struct for more information on the methods this macro provides on functions.
 ///
@@ -57,6 +183,22 @@ fn transaction_internal(_attr: TokenStream, item: TokenStream) -> TokenStream2 {
 /// #[scenario]
 /// fn my_scenario() {
 /// }
+///
+/// #[scenario]
+/// fn my_fallible_scenario() -> Result<(), MyError> {
+///     let client = setup()?;
+///     ...
+/// }
+///
+/// #[scenario(name = "checkout_flow")]
+/// fn my_named_scenario() {
+/// }
+///
+/// #[scenario(single_threaded)]
+/// fn my_local_scenario() {
+///     let client = Rc::new(Client::new());
+///     ...
+/// }
 /// ```
 #[proc_macro_attribute]
 pub fn scenario(attr: TokenStream, item: TokenStream) -> TokenStream {
@@ -65,8 +207,19 @@ pub fn scenario(attr: TokenStream, item: TokenStream) -> TokenStream {
 
 /// Proc macro to denote a Scenario
 ///
-/// NOTE: Currently this macro only works on functions which take no arguments and with no return value.
-/// (void functions). This is a restriction which will be lifted soon.
+/// NOTE: Currently this macro only works on functions which take no arguments. This is a
+/// restriction which will be lifted soon.
+///
+/// Scenarios may either return nothing, or a `Result<(), E>` where `E: Display`. Errors are logged
+/// and counted in `RunStatistics::scenario_errors`, separately from transaction errors.
+///
+/// The Scenario is named after the function by default, which is used in metric labels and to
+/// look it up in distributed runs. Use `#[scenario(name = "...")]` to give it a different name.
+///
+/// Scenarios are run on a multi-threaded runtime by default, which requires them to be `Send`.
+/// Use `#[scenario(single_threaded)]` for scenarios which hold `!Send` values (such as `Rc` or
+/// FFI handles) across an await. These run on a dedicated thread, with their tasks spawned onto
+/// a tokio `LocalSet`, so their throughput is limited to what a single thread can drive.
 ///
 /// See the `Scenario` struct for more information on the methods this macro provides on functions.
 ///
@@ -77,13 +230,37 @@ pub fn scenario(attr: TokenStream, item: TokenStream) -> TokenStream {
 /// #[scenario]
 /// fn my_scenario() {
 /// }
+///
+/// #[scenario]
+/// fn my_fallible_scenario() -> Result<(), MyError> {
+///     let client = setup()?;
+///     ...
+/// }
+///
+/// #[scenario(name = "checkout_flow")]
+/// fn my_named_scenario() {
+/// }
+///
+/// #[scenario(single_threaded)]
+/// fn my_local_scenario() {
+///     let client = Rc::new(Client::new());
+///     ...
+/// }
 /// ```
 #[proc_macro_attribute]
 pub fn scenario_linkme(attr: TokenStream, item: TokenStream) -> TokenStream {
     scenario_internal(attr, item, true).into()
 }
 
-fn scenario_internal(_attr: TokenStream, item: TokenStream, linkme: bool) -> TokenStream2 {
+fn scenario_internal(attr: TokenStream, item: TokenStream, linkme: bool) -> TokenStream2 {
+    let ScenarioAttrs {
+        name,
+        single_threaded,
+    } = match parse_scenario_attrs(attr) {
+        Ok(attrs) => attrs,
+        Err(err) => return err.to_compile_error(),
+    };
+
     let input = syn::parse::<ItemFn>(item).expect("Macro only works on fn() items");
 
     let ItemFn {
@@ -99,7 +276,7 @@ fn scenario_internal(_attr: TokenStream, item: TokenStream, linkme: bool) -> Tok
     new_sig.ident = new_name.clone();
 
     let mut scen_sig = sig.clone();
-    let scen_name = sig.ident.clone();
+    let scen_name = name.unwrap_or_else(|| LitStr::new(&sig.ident.to_string(), sig.ident.span()));
     scen_sig.asyncness = None;
     scen_sig.output = syn::parse(
         quote! {
@@ -109,9 +286,17 @@ fn scenario_internal(_attr: TokenStream, item: TokenStream, linkme: bool) -> Tok
     )
     .expect("Scenario signature is invalid");
 
+    let new_scenario = if single_threaded {
+        quote! { ::balter::scenario::Scenario::new_single_threaded }
+    } else {
+        quote! { ::balter::scenario::Scenario::new }
+    };
+
     let res = quote! {
         #(#attrs)* #vis #scen_sig {
-            ::balter::scenario::Scenario::new(stringify!(#scen_name), #new_name)
+            #new_scenario(#scen_name, || async {
+                ::balter::transaction::scenario_hook(#new_name().await)
+            })
         }
 
         #(#attrs)* #vis #new_sig {
@@ -139,11 +324,13 @@ fn scenario_internal(_attr: TokenStream, item: TokenStream, linkme: bool) -> Tok
 
         let mut linkme = quote! {
             #[::balter::runtime::distributed_slice(::balter::runtime::BALTER_SCENARIOS)]
-            static #static_name: (&'static str, fn() -> ::core::pin::Pin<Box<dyn ::balter::prelude::DistributedScenario<Output=::balter::prelude::RunStatistics>>>) = (stringify!(#scen_name), #linkme_name);
+            static #static_name: (&'static str, fn() -> ::core::pin::Pin<Box<dyn ::balter::prelude::DistributedScenario<Output=::balter::prelude::RunStatistics>>>) = (#scen_name, #linkme_name);
 
             // TODO: This definition can almost certainly merge with the #scen_sig definition
             #(#attrs)* #vis #linkme_sig {
-                Box::pin(::balter::scenario::Scenario::new(stringify!(#scen_name), #new_name))
+                Box::pin(#new_scenario(#scen_name, || async {
+                    ::balter::transaction::scenario_hook(#new_name().await)
+                }))
             }
         };
 
@@ -153,3 +340,44 @@ fn scenario_internal(_attr: TokenStream, item: TokenStream, linkme: bool) -> Tok
         res
     }
 }
+
+#[derive(Default)]
+struct ScenarioAttrs {
+    name: Option<LitStr>,
+    single_threaded: bool,
+}
+
+fn parse_scenario_attrs(attr: TokenStream) -> syn::Result<ScenarioAttrs> {
+    let mut attrs = ScenarioAttrs::default();
+    let metas = Punctuated::<Meta, Token![,]>::parse_terminated.parse(attr)?;
+    for meta in metas {
+        match meta {
+            Meta::NameValue(attr) if attr.path.is_ident("name") => {
+                attrs.name = Some(parse_scenario_name(attr)?);
+            }
+            Meta::Path(path) if path.is_ident("single_threaded") => {
+                attrs.single_threaded = true;
+            }
+            meta => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "Unsupported scenario attribute",
+                ))
+            }
+        }
+    }
+    Ok(attrs)
+}
+
+fn parse_scenario_name(attr: MetaNameValue) -> syn::Result<LitStr> {
+    match attr.value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(name),
+            ..
+        }) if !name.value().is_empty() => Ok(name),
+        value => Err(syn::Error::new_spanned(
+            value,
+            "Scenario name must be a non-empty string literal",
+        )),
+    }
+}
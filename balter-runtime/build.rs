@@ -0,0 +1,11 @@
+fn main() {
+    println!("cargo:rerun-if-changed=proto/balter.proto");
+
+    #[cfg(feature = "grpc")]
+    {
+        // NOTE: Use a vendored protoc so that building with gRPC support doesn't require
+        // installing one.
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
+        tonic_build::compile_protos("proto/balter.proto").unwrap();
+    }
+}
@@ -14,6 +14,10 @@ pub(crate) enum RuntimeError {
 
     #[error("Gossip protocol had an error: {0}")]
     GossipProtocol(#[from] crate::gossip::GossipError),
+
+    #[cfg(feature = "grpc")]
+    #[error("gRPC transport had an error: {0}")]
+    Grpc(#[from] crate::grpc::GrpcError),
 }
 
 impl<T> From<PoisonError<T>> for RuntimeError {
@@ -16,8 +16,9 @@ mod interchange;
 pub(crate) mod message;
 mod protocol;
 
-pub(crate) use data::{GossipData, PeerInfo};
+pub(crate) use data::{GossipData, PeerInfo, PeerState};
 pub(crate) use error::GossipError;
+pub(crate) use protocol::heartbeat::Heartbeat;
 
 pub(crate) async fn gossip_task(gossip: Gossip) -> Result<(), GossipError> {
     // TODO: This gossip interval rate is arbitrary at this point. It would be nice to either
@@ -30,7 +31,7 @@ pub(crate) async fn gossip_task(gossip: Gossip) -> Result<(), GossipError> {
 
         let peer = { gossip.data.lock()?.select_random_peer() };
         if let Some(peer) = peer {
-            let mut stream = peer_stream(&peer).await?;
+            let mut stream = peer_stream(peer.addr).await?;
             gossip.request_sync(&mut stream, peer.addr).await?;
         } else {
             debug!("No peers to gossip with.");
@@ -45,6 +46,7 @@ pub(crate) struct Gossip {
     server_id: Uuid,
     pub data: Arc<Mutex<GossipData>>,
     scenario_spawn_hook: SpawnHook,
+    accept_help: bool,
 }
 
 impl Gossip {
@@ -53,9 +55,26 @@ impl Gossip {
             data: Arc::new(Mutex::new(GossipData::new(server_id, port))),
             server_id,
             scenario_spawn_hook,
+            accept_help: true,
         }
     }
 
+    /// Refuse work handed off over gossip, which is unauthenticated, so that work can only be
+    /// handed off over a secured gRPC transport.
+    #[cfg(feature = "grpc")]
+    pub fn refuse_help(mut self) -> Self {
+        self.accept_help = false;
+        self
+    }
+
+    pub fn server_id(&self) -> Uuid {
+        self.server_id
+    }
+
+    pub fn spawn_scenario(&self, config: ScenarioConfig) -> Result<(), RuntimeError> {
+        (self.scenario_spawn_hook)(config)
+    }
+
     pub async fn receive_request(
         &self,
         stream: &mut impl GossipStream,
@@ -65,12 +84,13 @@ impl Gossip {
         match msg.inner() {
             Handshake::Sync => self.receive_sync_request(stream, peer_addr).await,
             Handshake::Help => self.receive_help_request(stream, peer_addr).await,
+            Handshake::Heartbeat => self.receive_heartbeat_request(stream).await,
         }
     }
 }
 
-pub async fn peer_stream(peer: &PeerInfo) -> Result<impl GossipStream, GossipError> {
-    let url = Url::parse(&format!("ws://{}/ws", peer.addr))?;
+pub async fn peer_stream(addr: SocketAddr) -> Result<impl GossipStream, GossipError> {
+    let url = Url::parse(&format!("ws://{addr}/ws"))?;
     let (stream, _) = connect_async(url).await?;
     Ok(stream)
 }
@@ -79,6 +79,15 @@ impl GossipData {
             .choose(&mut rng)
     }
 
+    /// Addresses of the peers which have not been marked unreachable, not including this server
+    pub fn reachable_peers(&self) -> Vec<SocketAddr> {
+        self.peers
+            .iter()
+            .filter(|(id, info)| **id != self.server_id && info.state != PeerState::Unreachable)
+            .map(|(_, info)| info.addr)
+            .collect()
+    }
+
     pub fn set_state_free(&mut self) {
         if let Some(info) = self.peers.get_mut(&self.server_id) {
             info.state = PeerState::Free;
@@ -95,6 +104,13 @@ impl GossipData {
         }
     }
 
+    pub fn set_peer_state(&mut self, server_id: &Uuid, state: PeerState) {
+        if let Some(info) = self.peers.get_mut(server_id) {
+            info.state = state;
+            info.version += 1;
+        }
+    }
+
     pub fn is_busy(&self) -> Option<bool> {
         match self.peers.get(&self.server_id) {
             Some(info) if info.state == PeerState::Busy => Some(true),
@@ -143,3 +159,39 @@ enum MyAddress {
     Known,
     Unknown { port: u16 },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reachable_peers() {
+        let server_id = Uuid::new_v4();
+        let mut data = GossipData::new(server_id, 7621);
+        data.learn_address("10.0.0.1:7000".parse().unwrap());
+
+        let peer = |addr: &str, state| PeerInfoPartial {
+            version: 1,
+            addr: addr.parse().unwrap(),
+            state,
+        };
+        data.peers
+            .insert(Uuid::new_v4(), peer("10.0.0.2:7621", PeerState::Free));
+        data.peers
+            .insert(Uuid::new_v4(), peer("10.0.0.3:7621", PeerState::Busy));
+        data.peers.insert(
+            Uuid::new_v4(),
+            peer("10.0.0.4:7621", PeerState::Unreachable),
+        );
+
+        let mut peers = data.reachable_peers();
+        peers.sort();
+        assert_eq!(
+            peers,
+            vec![
+                "10.0.0.2:7621".parse::<SocketAddr>().unwrap(),
+                "10.0.0.3:7621".parse().unwrap()
+            ]
+        );
+    }
+}
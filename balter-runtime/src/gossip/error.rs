@@ -10,7 +10,7 @@ pub enum GossipError {
     Axum(#[from] axum::Error),
 
     #[error("Error in Tungstenite: {0}")]
-    Tungstenite(#[from] tungstenite::Error),
+    Tungstenite(Box<tungstenite::Error>),
 
     #[error("Error deserializing with Bincode: {0}")]
     Bincode(#[from] Box<bincode::ErrorKind>),
@@ -26,6 +26,15 @@ pub enum GossipError {
 
     #[error("Peer to share work with is busy. Retries not implemented yet.")]
     PeerBusy,
+
+    #[error("Peer did not respond in time")]
+    Timeout,
+}
+
+impl From<tungstenite::Error> for GossipError {
+    fn from(err: tungstenite::Error) -> Self {
+        Self::Tungstenite(Box::new(err))
+    }
 }
 
 impl<T> From<PoisonError<T>> for GossipError {
@@ -6,7 +6,7 @@ use tokio::io::{AsyncRead, AsyncWrite};
 
 #[allow(dead_code)]
 #[trait_variant::make(GossipStream: Send)]
-pub trait LocalGossipStream {
+pub(crate) trait LocalGossipStream {
     async fn recv_bytes(&mut self) -> Option<Result<Vec<u8>, GossipError>>;
     async fn send_bytes(&mut self, bytes: Vec<u8>) -> Result<(), GossipError>;
 
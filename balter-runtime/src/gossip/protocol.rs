@@ -1,2 +1,3 @@
+pub(crate) mod heartbeat;
 pub(crate) mod help;
 pub(crate) mod sync;
@@ -0,0 +1,66 @@
+use super::super::{message::Message, Gossip, GossipError, GossipStream};
+use crate::runtime::running_scenarios;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+impl Gossip {
+    /// Check that a peer is still alive, returning its server ID and how much work it is running.
+    ///
+    /// NOTE: A peer which restarted responds with a new server ID, and has lost any work it was
+    /// running.
+    pub(crate) async fn request_heartbeat(
+        &self,
+        stream: &mut impl GossipStream,
+    ) -> Result<Heartbeat, GossipError> {
+        stream.send(Message::heartbeat()).await?;
+
+        let msg: Message<Heartbeat> = stream.recv().await?;
+        Ok(msg.inner)
+    }
+
+    pub(crate) async fn receive_heartbeat_request(
+        &self,
+        stream: &mut impl GossipStream,
+    ) -> Result<(), GossipError> {
+        stream
+            .send(Message::new(Heartbeat {
+                server_id: self.server_id(),
+                running: running_scenarios(),
+            }))
+            .await
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Heartbeat {
+    pub server_id: Uuid,
+    /// Number of scenarios the peer is running on behalf of other servers
+    pub running: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gossip::tests::FakeStream;
+    use crate::gossip::Gossip;
+    use crate::runtime::spawn_scenario;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn heartbeat_test() {
+        let server_id = Uuid::new_v4();
+        let gossip_0 = Gossip::new(Uuid::new_v4(), 1234, spawn_scenario);
+        let gossip_1 = Gossip::new(server_id, 4321, spawn_scenario);
+
+        let (mut stream_0, mut stream_1) = FakeStream::duplex();
+
+        let (res0, res1) = tokio::join! {
+            gossip_0.request_heartbeat(&mut stream_0),
+            gossip_1.receive_request(&mut stream_1, "0.0.0.0:1111".parse().unwrap()),
+        };
+
+        let heartbeat = res0.unwrap();
+        assert_eq!(heartbeat.server_id, server_id);
+        assert_eq!(heartbeat.running, 0);
+        assert!(res1.is_ok());
+    }
+}
@@ -2,7 +2,7 @@ use super::super::{message::Message, Gossip, GossipError, GossipStream};
 use balter_core::ScenarioConfig;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
-use tracing::error;
+use tracing::{debug, error, warn};
 
 impl Gossip {
     #[allow(unused)]
@@ -10,10 +10,11 @@ impl Gossip {
         &self,
         mut stream: &mut impl GossipStream,
         peer_addr: SocketAddr,
-        config: ScenarioConfig,
+        mut config: ScenarioConfig,
     ) -> Result<(), GossipError> {
         stream.send(Message::help()).await?;
 
+        config.mark_dispatched();
         stream.send(Message::run_config(config)).await?;
 
         let status: Message<Status> = stream.recv().await?;
@@ -33,6 +34,14 @@ impl Gossip {
     ) -> Result<(), GossipError> {
         let msg: Message<RunConfig> = stream.recv().await?;
 
+        if !self.accept_help {
+            warn!(
+                "Rejected {} from {peer_addr}, since work may only be handed off over the secured gRPC transport.",
+                msg.inner().config.name
+            );
+            return stream.send(Message::new(Status::Busy)).await;
+        }
+
         // TODO: Be far more clever about whether this server can accept work
         let is_busy = self.data.lock()?.is_busy();
 
@@ -41,9 +50,15 @@ impl Gossip {
                 stream.send(Message::new(Status::Busy)).await?;
             }
             Some(false) => {
-                stream.send(Message::new(Status::Accepted)).await?;
+                let mut config = msg.config();
+                if let Some(delay) = config.account_for_transit() {
+                    debug!("Received {} after {delay:?} in transit", config.name);
+                }
+                // NOTE: The scenario is spawned before accepting, so that it is already counted
+                // as running by the time the peer checks on it.
                 // TODO: Handle error
-                let _ = (self.scenario_spawn_hook)(msg.config());
+                let _ = self.spawn_scenario(config);
+                stream.send(Message::new(Status::Accepted)).await?;
             }
             None => {
                 error!("Could not find own info.");
@@ -122,4 +137,30 @@ mod tests {
         SPAWNED.store(true, Ordering::Relaxed);
         Ok(())
     }
+
+    #[cfg(feature = "grpc")]
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn refused_help_test() {
+        static SPAWNED_REFUSED: AtomicBool = AtomicBool::new(false);
+        let gossip_0 = Gossip::new(Uuid::new_v4(), 1234, fake_spawn_scenario);
+        let gossip_1 = Gossip::new(Uuid::new_v4(), 4321, |_| {
+            SPAWNED_REFUSED.store(true, Ordering::Relaxed);
+            Ok(())
+        })
+        .refuse_help();
+
+        let (mut stream_0, mut stream_1) = FakeStream::duplex();
+
+        let config = ScenarioConfig::new("test_config");
+        let (res0, res1) = tokio::join! {
+            gossip_0.request_help(&mut stream_0, "0.0.0.0:1111".parse().unwrap(), config),
+            gossip_1.receive_request(&mut stream_1, "0.0.0.0:1111".parse().unwrap()),
+        };
+
+        assert!(matches!(res0, Err(GossipError::PeerBusy)));
+        assert!(res1.is_ok());
+        assert!(!SPAWNED_REFUSED.load(Ordering::Relaxed));
+        assert!(logs_contain("Rejected test_config"));
+    }
 }
@@ -0,0 +1,706 @@
+//! gRPC transport for handing off work between peers (requires `grpc` feature)
+//!
+//! The wire protocol is defined in `proto/balter.proto`. Peers serve the `Balter` service on
+//! their gRPC port (see [BalterRuntime::grpc_port](crate::BalterRuntime::grpc_port)), and hand
+//! off work to the peers provided with
+//! [BalterRuntime::grpc_peers](crate::BalterRuntime::grpc_peers). The transport can be secured
+//! with mutual TLS and a shared token, with a [TransportConfig].
+use crate::runtime::running_scenarios;
+use crate::{
+    error::RuntimeError,
+    gossip::{Gossip, Heartbeat},
+};
+use balter_core::{LatencyConfig, ScenarioConfig};
+use proto::balter_client::BalterClient;
+use proto::balter_server::{Balter, BalterServer};
+use proto::{HandoffRequest, HandoffResponse, HeartbeatRequest, HeartbeatResponse, ScenarioKind};
+use std::net::SocketAddr;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::transport::{
+    Certificate, Channel, ClientTlsConfig, Endpoint, Identity, ServerTlsConfig,
+};
+use tonic::{Request, Response, Status};
+#[allow(unused)]
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+#[allow(clippy::all)]
+pub(crate) mod proto {
+    tonic::include_proto!("balter");
+}
+
+#[derive(Debug, Error)]
+pub enum GrpcError {
+    #[error("Error in gRPC transport: {0}")]
+    Transport(#[from] tonic::transport::Error),
+
+    #[error("gRPC request failed: {0}")]
+    Status(Box<Status>),
+
+    #[error("Invalid scenario config: {0}")]
+    InvalidConfig(&'static str),
+
+    #[error("Error serializing scenario config with Bincode: {0}")]
+    Bincode(#[from] Box<bincode::ErrorKind>),
+
+    #[error("Peer responded with an invalid server ID")]
+    InvalidServerId(#[from] uuid::Error),
+
+    #[error("No peers accepted the work")]
+    NoPeers,
+
+    #[error("Unable to bind the gRPC port: {0}")]
+    Bind(#[source] std::io::Error),
+
+    #[error("Unable to read {}: {1}", .0.display())]
+    ReadFile(PathBuf, #[source] std::io::Error),
+
+    #[error("Token must be printable ASCII")]
+    InvalidToken,
+}
+
+impl From<Status> for GrpcError {
+    fn from(status: Status) -> Self {
+        Self::Status(Box::new(status))
+    }
+}
+
+/// Name of the metadata key carrying the token
+const AUTHORIZATION: &str = "authorization";
+
+/// Security settings for the gRPC transport (requires `grpc` feature)
+///
+/// By default the transport is neither encrypted nor authenticated, so any host which can reach
+/// the gRPC port can hand off work to it. With [TransportConfig::tls], peers only connect to
+/// (and accept connections from) peers presenting a certificate signed by the given CA. With
+/// [TransportConfig::token], handoffs which don't carry the same token are rejected and logged
+/// before their config is accepted.
+///
+/// Once either is set, work is only accepted over authenticated requests: the HTTP `/run`
+/// endpoint requires the token as an `Authorization: Bearer` header (and refuses every request
+/// without a token), and work handed off over gossip is refused.
+///
+/// Every peer must be given the same settings.
+///
+/// # Example
+/// ```ignore
+/// use balter::prelude::*;
+/// use balter::runtime::TransportConfig;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     BalterRuntime::new()
+///         .grpc_port(7622)
+///         .grpc_transport(
+///             TransportConfig::new()
+///                 .tls("certs/peer.pem", "certs/peer.key", "certs/ca.pem")
+///                 .token(&std::env::var("BALTER_AUTH_TOKEN").unwrap()),
+///         )
+///         .with_args()
+///         .run()
+///         .await;
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct TransportConfig {
+    tls: Option<TlsPaths>,
+    domain_name: Option<String>,
+    token: Option<String>,
+}
+
+#[derive(Clone)]
+struct TlsPaths {
+    cert: PathBuf,
+    key: PathBuf,
+    ca: PathBuf,
+}
+
+impl TransportConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use mutual TLS, with the PEM-encoded certificate and private key of this peer, and the
+    /// certificate of the CA which signed those of every peer.
+    ///
+    /// NOTE: Peers are addressed by IP, so their certificates must include their IP addresses
+    /// unless [TransportConfig::domain_name] is set.
+    pub fn tls(
+        mut self,
+        cert: impl Into<PathBuf>,
+        key: impl Into<PathBuf>,
+        ca: impl Into<PathBuf>,
+    ) -> Self {
+        self.tls = Some(TlsPaths {
+            cert: cert.into(),
+            key: key.into(),
+            ca: ca.into(),
+        });
+        self
+    }
+
+    /// Verify the certificates of peers against this domain name, rather than their IP addresses
+    pub fn domain_name(mut self, domain_name: &str) -> Self {
+        self.domain_name = Some(domain_name.to_string());
+        self
+    }
+
+    /// Send the token with each request as a bearer token, and reject requests without it
+    pub fn token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    /// Read the TLS certificates and check the token
+    pub(crate) fn load(&self) -> Result<Credentials, GrpcError> {
+        let tls = match &self.tls {
+            Some(paths) => {
+                let identity = Identity::from_pem(read(&paths.cert)?, read(&paths.key)?);
+                Some((identity, Certificate::from_pem(read(&paths.ca)?)))
+            }
+            None => None,
+        };
+        let token = match &self.token {
+            Some(token) => Some(
+                format!("Bearer {token}")
+                    .parse()
+                    .map_err(|_| GrpcError::InvalidToken)?,
+            ),
+            None => None,
+        };
+
+        Ok(Credentials {
+            tls,
+            domain_name: self.domain_name.clone(),
+            token,
+        })
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, GrpcError> {
+    std::fs::read(path).map_err(|err| GrpcError::ReadFile(path.to_path_buf(), err))
+}
+
+/// A [TransportConfig] with its certificates loaded
+#[derive(Clone, Default)]
+pub(crate) struct Credentials {
+    tls: Option<(Identity, Certificate)>,
+    domain_name: Option<String>,
+    token: Option<MetadataValue<Ascii>>,
+}
+
+impl Credentials {
+    async fn connect(&self, peer: SocketAddr) -> Result<BalterClient<Channel>, GrpcError> {
+        let channel = match &self.tls {
+            Some((identity, ca)) => {
+                let mut tls = ClientTlsConfig::new()
+                    .ca_certificate(ca.clone())
+                    .identity(identity.clone());
+                if let Some(domain_name) = &self.domain_name {
+                    tls = tls.domain_name(domain_name);
+                }
+                Endpoint::from_shared(format!("https://{peer}"))?
+                    .tls_config(tls)?
+                    .connect()
+                    .await?
+            }
+            None => {
+                Endpoint::from_shared(format!("http://{peer}"))?
+                    .connect()
+                    .await?
+            }
+        };
+        Ok(BalterClient::new(channel))
+    }
+
+    fn request<T>(&self, message: T) -> Request<T> {
+        let mut request = Request::new(message);
+        if let Some(token) = &self.token {
+            request.metadata_mut().insert(AUTHORIZATION, token.clone());
+        }
+        request
+    }
+
+    /// Whether the transport is secured with TLS or a token
+    pub(crate) fn is_secured(&self) -> bool {
+        self.tls.is_some() || self.token.is_some()
+    }
+
+    /// Whether an HTTP request with the given `authorization` header may dispatch work. Once the
+    /// transport is secured this requires the token, since plain HTTP can't be authenticated with
+    /// TLS.
+    pub(crate) fn authorize_http(&self, authorization: Option<&[u8]>) -> bool {
+        if !self.is_secured() {
+            return true;
+        }
+        match (&self.token, authorization) {
+            (Some(token), Some(value)) => constant_time_eq(value, token.as_bytes()),
+            _ => false,
+        }
+    }
+
+    /// Reject requests which don't carry the token, if there is one
+    // NOTE: The signature is that of a tonic interceptor.
+    #[allow(clippy::result_large_err)]
+    fn authenticate(&self, request: Request<()>) -> Result<Request<()>, Status> {
+        let Some(token) = &self.token else {
+            return Ok(request);
+        };
+
+        let authenticated = request
+            .metadata()
+            .get(AUTHORIZATION)
+            .is_some_and(|value| constant_time_eq(value.as_bytes(), token.as_bytes()));
+        if authenticated {
+            Ok(request)
+        } else {
+            let addr = request
+                .remote_addr()
+                .map_or_else(|| "an unknown address".to_string(), |addr| addr.to_string());
+            warn!("Rejected an unauthenticated request from {addr}.");
+            Err(Status::unauthenticated("Missing or invalid token"))
+        }
+    }
+}
+
+/// Compare without short-circuiting, so that the time taken doesn't reveal how much of the token
+/// was guessed correctly.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+pub(crate) async fn server_task(
+    port: u16,
+    gossip: Gossip,
+    credentials: Credentials,
+) -> Result<(), GrpcError> {
+    let addr: SocketAddr = ([0, 0, 0, 0], port).into();
+    let listener = TcpListener::bind(addr).await.map_err(GrpcError::Bind)?;
+    serve(listener, gossip, credentials).await
+}
+
+async fn serve(
+    listener: TcpListener,
+    gossip: Gossip,
+    credentials: Credentials,
+) -> Result<(), GrpcError> {
+    debug!("gRPC server starting up...");
+
+    let mut server = tonic::transport::Server::builder();
+    if let Some((identity, ca)) = &credentials.tls {
+        server = server.tls_config(
+            ServerTlsConfig::new()
+                .identity(identity.clone())
+                .client_ca_root(ca.clone()),
+        )?;
+    }
+    #[allow(clippy::result_large_err)]
+    let service = BalterServer::with_interceptor(BalterService { gossip }, move |request| {
+        credentials.authenticate(request)
+    });
+    server
+        .add_service(service)
+        .serve_with_incoming(TcpListenerStream::new(listener))
+        .await?;
+    Ok(())
+}
+
+struct BalterService {
+    gossip: Gossip,
+}
+
+#[tonic::async_trait]
+impl Balter for BalterService {
+    async fn handoff(
+        &self,
+        request: Request<HandoffRequest>,
+    ) -> Result<Response<HandoffResponse>, Status> {
+        let config = request
+            .into_inner()
+            .config
+            .ok_or_else(|| Status::invalid_argument("Missing scenario config"))?;
+        let mut config = ScenarioConfig::try_from(config)
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+
+        // TODO: Be far more clever about whether this server can accept work
+        let is_busy = self
+            .gossip
+            .data
+            .lock()
+            .map_err(|_| Status::internal("GossipData Mutex is poisoned"))?
+            .is_busy();
+
+        let accepted = is_busy == Some(false);
+        if accepted {
+            if let Some(delay) = config.account_for_transit() {
+                debug!("Received {} after {delay:?} in transit", config.name);
+            }
+            match self.gossip.spawn_scenario(config) {
+                Ok(()) => {}
+                Err(RuntimeError::NoScenario) => {
+                    return Err(Status::not_found("Scenario not found"));
+                }
+                Err(err) => return Err(Status::internal(err.to_string())),
+            }
+        }
+
+        Ok(Response::new(HandoffResponse {
+            accepted,
+            server_id: self.gossip.server_id().to_string(),
+        }))
+    }
+
+    async fn heartbeat(
+        &self,
+        _request: Request<HeartbeatRequest>,
+    ) -> Result<Response<HeartbeatResponse>, Status> {
+        Ok(Response::new(HeartbeatResponse {
+            server_id: self.gossip.server_id().to_string(),
+            running: running_scenarios(),
+        }))
+    }
+}
+
+/// Hand off the config to the first of the peers which accepts it, returning the ID and address
+/// of that peer.
+pub(crate) async fn handoff(
+    peers: &[SocketAddr],
+    config: &ScenarioConfig,
+    credentials: &Credentials,
+) -> Result<(Uuid, SocketAddr), GrpcError> {
+    for peer in peers {
+        match handoff_to(*peer, config.clone(), credentials).await {
+            Ok(Some(server_id)) => return Ok((server_id, *peer)),
+            Ok(None) => debug!("Peer {peer} is busy."),
+            Err(err) => warn!("Unable to hand off work to {peer}: {err}"),
+        }
+    }
+    Err(GrpcError::NoPeers)
+}
+
+async fn handoff_to(
+    peer: SocketAddr,
+    mut config: ScenarioConfig,
+    credentials: &Credentials,
+) -> Result<Option<Uuid>, GrpcError> {
+    let mut client = credentials.connect(peer).await?;
+
+    config.mark_dispatched();
+    let res = client
+        .handoff(credentials.request(HandoffRequest {
+            config: Some((&config).try_into()?),
+        }))
+        .await?
+        .into_inner();
+
+    if res.accepted {
+        Ok(Some(Uuid::parse_str(&res.server_id)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Check that a peer is alive, returning its server ID and how much work it is running
+pub(crate) async fn heartbeat(
+    peer: SocketAddr,
+    credentials: &Credentials,
+) -> Result<Heartbeat, GrpcError> {
+    let mut client = credentials.connect(peer).await?;
+    let res = client
+        .heartbeat(credentials.request(HeartbeatRequest {}))
+        .await?
+        .into_inner();
+    Ok(Heartbeat {
+        server_id: Uuid::parse_str(&res.server_id)?,
+        running: res.running,
+    })
+}
+
+impl TryFrom<&ScenarioConfig> for proto::ScenarioConfig {
+    type Error = GrpcError;
+
+    fn try_from(config: &ScenarioConfig) -> Result<Self, Self::Error> {
+        let kind = if config.latency.is_some() {
+            ScenarioKind::Latency
+        } else if config.error_rate.is_some() {
+            ScenarioKind::ErrorRate
+        } else {
+            ScenarioKind::Tps
+        };
+
+        Ok(Self {
+            name: config.name.clone(),
+            kind: kind.into(),
+            duration_secs: config.duration.map(|d| d.as_secs_f64()),
+            goal_tps: config.max_tps.map(NonZeroU32::get),
+            error_rate: config.error_rate,
+            latency_secs: config.latency.map(|l| l.latency.as_secs_f64()),
+            latency_quantile: config.latency.map(|l| l.quantile),
+            dispatched_at_unix_nanos: config
+                .dispatched_at
+                .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map(|d| d.as_nanos() as u64),
+            args: config.args.clone(),
+            labels: config.labels.clone().into_iter().collect(),
+            config: bincode::serialize(config)?,
+        })
+    }
+}
+
+impl TryFrom<proto::ScenarioConfig> for ScenarioConfig {
+    type Error = GrpcError;
+
+    fn try_from(proto: proto::ScenarioConfig) -> Result<Self, Self::Error> {
+        // NOTE: Peers which send the full config are trusted to have sent a valid one
+        if !proto.config.is_empty() {
+            return Ok(bincode::deserialize(&proto.config)?);
+        }
+
+        let secs = |secs: f64| {
+            Duration::try_from_secs_f64(secs).map_err(|_| GrpcError::InvalidConfig("duration"))
+        };
+
+        let mut config = ScenarioConfig::new(&proto.name);
+        config.duration = proto.duration_secs.map(secs).transpose()?;
+        config.max_tps = proto.goal_tps.and_then(NonZeroU32::new);
+        config.dispatched_at = proto
+            .dispatched_at_unix_nanos
+            .map(|nanos| SystemTime::UNIX_EPOCH + Duration::from_nanos(nanos));
+
+        match proto.kind() {
+            ScenarioKind::Unspecified => return Err(GrpcError::InvalidConfig("kind")),
+            ScenarioKind::Tps => {
+                if config.max_tps.is_none() {
+                    return Err(GrpcError::InvalidConfig("goal_tps"));
+                }
+            }
+            ScenarioKind::ErrorRate => {
+                let error_rate = proto
+                    .error_rate
+                    .ok_or(GrpcError::InvalidConfig("error_rate"))?;
+                config.error_rate = Some(error_rate);
+            }
+            ScenarioKind::Latency => {
+                let (Some(latency), Some(quantile)) = (proto.latency_secs, proto.latency_quantile)
+                else {
+                    return Err(GrpcError::InvalidConfig("latency"));
+                };
+                config.latency = Some(LatencyConfig::new(secs(latency)?, quantile));
+            }
+        }
+        config.args = proto.args;
+        config.labels = proto.labels.into_iter().collect();
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use balter_core::RampConfig;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn test_config_conversion() {
+        let mut config = ScenarioConfig::new("test_scenario");
+        config.duration = Some(Duration::from_millis(1_500));
+        config.max_tps = NonZeroU32::new(2_000);
+        config.latency = Some(LatencyConfig::new(Duration::from_millis(20), 0.99));
+        config.args = Some(vec![1, 2, 3]);
+        config
+            .labels
+            .insert("region".to_string(), "eu-west-1".to_string());
+        config.ramp = Some(RampConfig::new(
+            NonZeroU32::new(100).unwrap(),
+            NonZeroU32::new(2_000).unwrap(),
+        ));
+        config
+            .transaction_tps
+            .insert("write_user".to_string(), NonZeroU32::new(50).unwrap());
+        config.mark_dispatched();
+
+        let full = proto::ScenarioConfig::try_from(&config).unwrap();
+        assert_eq!(full.kind(), ScenarioKind::Latency);
+
+        // Peers which don't read the full config still get the goal of the Scenario
+        let goal_only = proto::ScenarioConfig {
+            config: vec![],
+            ..full.clone()
+        };
+        for proto in [full.clone(), goal_only] {
+            let received = ScenarioConfig::try_from(proto).unwrap();
+            assert_eq!(received.name, config.name);
+            assert_eq!(received.duration, config.duration);
+            assert_eq!(received.max_tps, config.max_tps);
+            let latency = received.latency.unwrap();
+            assert_eq!(latency.latency, Duration::from_millis(20));
+            assert_eq!(latency.quantile, 0.99);
+            assert_eq!(received.dispatched_at, config.dispatched_at);
+            assert_eq!(received.args, config.args);
+            assert_eq!(received.labels, config.labels);
+        }
+
+        let received = ScenarioConfig::try_from(full).unwrap();
+        let ramp = received.ramp.unwrap();
+        assert_eq!((ramp.start.get(), ramp.end.get()), (100, 2_000));
+        assert_eq!(received.transaction_tps, config.transaction_tps);
+
+        let proto = proto::ScenarioConfig {
+            name: "test_scenario".to_string(),
+            kind: ScenarioKind::ErrorRate.into(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ScenarioConfig::try_from(proto),
+            Err(GrpcError::InvalidConfig("error_rate"))
+        ));
+    }
+
+    static SPAWNED: AtomicBool = AtomicBool::new(false);
+
+    fn fake_spawn_scenario(_config: ScenarioConfig) -> Result<(), RuntimeError> {
+        SPAWNED.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Serve the transport on a free port, returning the server ID and address of the peer
+    async fn spawn_peer(
+        spawn_hook: fn(ScenarioConfig) -> Result<(), RuntimeError>,
+        credentials: Credentials,
+    ) -> (Uuid, SocketAddr) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server_id = Uuid::new_v4();
+        let gossip = Gossip::new(server_id, 0, spawn_hook);
+        gossip.data.lock().unwrap().learn_address(addr);
+        tokio::spawn(serve(listener, gossip, credentials));
+        (server_id, addr)
+    }
+
+    #[tokio::test]
+    async fn test_handoff() {
+        let (server_id, peer) = spawn_peer(fake_spawn_scenario, Credentials::default()).await;
+        let credentials = Credentials::default();
+        assert_eq!(
+            heartbeat(peer, &credentials).await.unwrap().server_id,
+            server_id
+        );
+
+        // Unreachable peers are skipped
+        let mut config = ScenarioConfig::new("test_scenario");
+        config.max_tps = NonZeroU32::new(100);
+        let unreachable = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap()
+        };
+        let (id, addr) = handoff(&[unreachable, peer], &config, &credentials)
+            .await
+            .unwrap();
+        assert_eq!((id, addr), (server_id, peer));
+        assert!(SPAWNED.load(Ordering::Relaxed));
+    }
+
+    static SPAWNED_UNAUTHENTICATED: AtomicBool = AtomicBool::new(false);
+
+    fn unauthenticated_spawn_scenario(_config: ScenarioConfig) -> Result<(), RuntimeError> {
+        SPAWNED_UNAUTHENTICATED.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_token() {
+        let credentials = TransportConfig::new().token("secret").load().unwrap();
+        let (server_id, peer) =
+            spawn_peer(unauthenticated_spawn_scenario, credentials.clone()).await;
+        assert_eq!(
+            heartbeat(peer, &credentials).await.unwrap().server_id,
+            server_id
+        );
+
+        let mut config = ScenarioConfig::new("test_scenario");
+        config.max_tps = NonZeroU32::new(100);
+        let wrong_token = TransportConfig::new().token("guess").load().unwrap();
+        for credentials in [Credentials::default(), wrong_token] {
+            let err = heartbeat(peer, &credentials).await.unwrap_err();
+            assert!(
+                matches!(&err, GrpcError::Status(status) if status.code() == tonic::Code::Unauthenticated),
+                "{err:?}"
+            );
+            assert!(matches!(
+                handoff(&[peer], &config, &credentials).await,
+                Err(GrpcError::NoPeers)
+            ));
+        }
+        assert!(!SPAWNED_UNAUTHENTICATED.load(Ordering::Relaxed));
+        assert!(logs_contain("Rejected an unauthenticated request"));
+
+        assert!(matches!(
+            TransportConfig::new().token("new\nline").load(),
+            Err(GrpcError::InvalidToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls() {
+        use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+
+        let dir = std::env::temp_dir().join(format!("balter-tls-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, contents: String| {
+            let path = dir.join(name);
+            std::fs::write(&path, contents).unwrap();
+            path
+        };
+
+        let new_ca = || {
+            let key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(vec![]).unwrap();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            (params.self_signed(&key).unwrap(), key)
+        };
+        let ca = new_ca();
+        let ca_path = write("ca.pem", ca.0.pem());
+
+        // Peers trust the CA, but only those with a certificate signed by it are trusted back
+        let peer = |name: &str, (signer, signer_key): &(rcgen::Certificate, KeyPair)| {
+            let key = KeyPair::generate().unwrap();
+            let cert = CertificateParams::new(vec!["127.0.0.1".to_string()])
+                .unwrap()
+                .signed_by(&key, signer, signer_key)
+                .unwrap();
+            TransportConfig::new()
+                .tls(
+                    write(&format!("{name}.pem"), cert.pem()),
+                    write(&format!("{name}.key"), key.serialize_pem()),
+                    &ca_path,
+                )
+                .load()
+                .unwrap()
+        };
+        let server = peer("server", &ca);
+        let client = peer("client", &ca);
+        let rogue = peer("rogue", &new_ca());
+
+        let (server_id, addr) = spawn_peer(unauthenticated_spawn_scenario, server).await;
+        assert_eq!(heartbeat(addr, &client).await.unwrap().server_id, server_id);
+        assert!(heartbeat(addr, &rogue).await.is_err());
+        assert!(heartbeat(addr, &Credentials::default()).await.is_err());
+
+        assert!(matches!(
+            TransportConfig::new()
+                .tls(dir.join("missing.pem"), dir.join("missing.key"), &ca_path)
+                .load(),
+            Err(GrpcError::ReadFile(..))
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -2,6 +2,8 @@ pub mod runtime;
 
 mod error;
 mod gossip;
+#[cfg(feature = "grpc")]
+mod grpc;
 mod server;
 pub mod traits;
 
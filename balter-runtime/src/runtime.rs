@@ -2,9 +2,11 @@
 //!
 //! This Runtime handles running scenarios and distributing workloads to peers. Currently this
 //! involves spinning up an API server and a gossip protocol task.
+#[cfg(feature = "grpc")]
+use crate::grpc::Credentials;
 use crate::{
     error::RuntimeError,
-    gossip::{gossip_task, peer_stream, Gossip},
+    gossip::{gossip_task, peer_stream, Gossip, GossipError, Heartbeat, PeerInfo, PeerState},
     server::server_task,
     DistributedScenario,
 };
@@ -12,16 +14,26 @@ use async_channel::{bounded, Receiver, Sender};
 use balter_core::{RunStatistics, ScenarioConfig};
 use clap::Parser;
 use lazy_static::lazy_static;
+use ledger::{PeerLedger, Transport};
 #[doc(hidden)]
 pub use linkme::distributed_slice;
 use std::future::Future;
+#[cfg(feature = "grpc")]
+use std::path::PathBuf;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
 use std::{collections::HashMap, net::SocketAddr};
 #[allow(unused)]
-use tracing::{debug, error, info, instrument, Instrument};
+use tracing::{debug, error, info, instrument, warn, Instrument};
+use uuid::Uuid;
 
+mod ledger;
 mod message;
 
+#[cfg(feature = "grpc")]
+pub use crate::grpc::TransportConfig;
 pub use message::RuntimeMessage;
 
 // TODO: This doesn't need to be a global, and can be threaded into each Scenario via task_local.
@@ -31,6 +43,33 @@ lazy_static! {
         bounded(10);
 }
 
+static SERVER_ID: OnceLock<Uuid> = OnceLock::new();
+
+static GOSSIP: OnceLock<Gossip> = OnceLock::new();
+
+/// Number of scenarios this server is running on behalf of other servers
+static RUNNING: AtomicU32 = AtomicU32::new(0);
+
+/// ID of this server, or `None` if the runtime has not been started.
+pub fn server_id() -> Option<Uuid> {
+    SERVER_ID.get().copied()
+}
+
+/// Addresses of the peers this server currently knows of through gossip, not including itself
+/// or peers which have stopped responding. Empty if the runtime has not been started.
+pub fn active_peers() -> Vec<SocketAddr> {
+    GOSSIP
+        .get()
+        .and_then(|gossip| Some(gossip.data.lock().ok()?.reachable_peers()))
+        .unwrap_or_default()
+}
+
+/// Number of scenarios this server is running on behalf of other servers, which peers use to tell
+/// when the work they handed off has finished.
+pub(crate) fn running_scenarios() -> u32 {
+    RUNNING.load(Ordering::Relaxed)
+}
+
 /// An array created at link-time which stores the names of each scenario and their respective
 /// function pointer.
 #[doc(hidden)]
@@ -40,8 +79,20 @@ pub static BALTER_SCENARIOS: [(
     fn() -> Pin<Box<dyn DistributedScenario<Output = RunStatistics>>>,
 )];
 
+type RegisteredScenarios = HashMap<String, Box<dyn DistributedScenario<Output = RunStatistics>>>;
+
+/// Scenarios registered with [BalterRuntime::scenario], which are built at runtime rather than
+/// with the `#[scenario]` macro.
+static REGISTERED_SCENARIOS: OnceLock<Mutex<RegisteredScenarios>> = OnceLock::new();
+
 const DEFAULT_PORT: u16 = 7621;
 
+/// How often peers which have taken on our work are checked on
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a peer can go without responding before its work is redistributed
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Parser, Debug)]
 #[command(version = "0.1")]
 struct BalterCli {
@@ -50,6 +101,30 @@ struct BalterCli {
 
     #[arg(short('n'), long)]
     peers: Vec<SocketAddr>,
+
+    #[cfg(feature = "grpc")]
+    #[arg(long)]
+    grpc_port: Option<u16>,
+
+    #[cfg(feature = "grpc")]
+    #[arg(long("grpc-peer"))]
+    grpc_peers: Vec<SocketAddr>,
+
+    #[cfg(feature = "grpc")]
+    #[arg(long, requires_all = ["tls_key", "tls_ca"])]
+    tls_cert: Option<PathBuf>,
+
+    #[cfg(feature = "grpc")]
+    #[arg(long, requires_all = ["tls_cert", "tls_ca"])]
+    tls_key: Option<PathBuf>,
+
+    #[cfg(feature = "grpc")]
+    #[arg(long, requires_all = ["tls_cert", "tls_key"])]
+    tls_ca: Option<PathBuf>,
+
+    #[cfg(feature = "grpc")]
+    #[arg(long, env = "BALTER_AUTH_TOKEN", hide_env_values = true)]
+    auth_token: Option<String>,
 }
 
 /// Default Balter distributed runtime. (requires `rt` feature)
@@ -73,6 +148,12 @@ struct BalterCli {
 pub struct BalterRuntime {
     port: u16,
     peers: Vec<SocketAddr>,
+    #[cfg(feature = "grpc")]
+    grpc_port: Option<u16>,
+    #[cfg(feature = "grpc")]
+    grpc_peers: Vec<SocketAddr>,
+    #[cfg(feature = "grpc")]
+    grpc_transport: TransportConfig,
 }
 
 impl Default for BalterRuntime {
@@ -86,6 +167,12 @@ impl BalterRuntime {
         BalterRuntime {
             port: DEFAULT_PORT,
             peers: vec![],
+            #[cfg(feature = "grpc")]
+            grpc_port: None,
+            #[cfg(feature = "grpc")]
+            grpc_peers: vec![],
+            #[cfg(feature = "grpc")]
+            grpc_transport: TransportConfig::new(),
         }
     }
 
@@ -95,6 +182,11 @@ impl BalterRuntime {
     ///
     /// `-n`, `--peers` to provide addresses to peer servers to enable gossiping.
     ///
+    /// With the `grpc` feature, `--grpc-port` and `--grpc-peer` set the options of the same
+    /// name. `--tls-cert`, `--tls-key` and `--tls-ca` set up mutual TLS, and `--auth-token` (or
+    /// the `BALTER_AUTH_TOKEN` environment variable) the token, of the
+    /// [TransportConfig](crate::runtime::TransportConfig).
+    ///
     /// # Example
     /// ```ignore
     /// $ ./my_load_test -p 2742
@@ -104,6 +196,17 @@ impl BalterRuntime {
         let args = BalterCli::parse();
         self.port = args.port;
         self.peers = args.peers;
+        #[cfg(feature = "grpc")]
+        {
+            self.grpc_port = args.grpc_port;
+            self.grpc_peers = args.grpc_peers;
+            if let (Some(cert), Some(key), Some(ca)) = (args.tls_cert, args.tls_key, args.tls_ca) {
+                self.grpc_transport = self.grpc_transport.tls(cert, key, ca);
+            }
+            if let Some(token) = args.auth_token {
+                self.grpc_transport = self.grpc_transport.token(&token);
+            }
+        }
         self
     }
 
@@ -117,13 +220,135 @@ impl BalterRuntime {
         self
     }
 
+    /// Serve the gRPC transport on the given port, so that peers on other hosts can hand off
+    /// work to this server. (requires `grpc` feature)
+    #[cfg(feature = "grpc")]
+    pub fn grpc_port(mut self, port: u16) -> Self {
+        self.grpc_port = Some(port);
+        self
+    }
+
+    /// Hand off work to the given peers over gRPC, rather than to peers found via gossip. Peers
+    /// are tried in order until one accepts. (requires `grpc` feature)
+    ///
+    /// # Example
+    /// ```ignore
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     BalterRuntime::new()
+    ///         .grpc_port(7622)
+    ///         .grpc_peers(&["10.0.0.2:7622".parse().unwrap(), "10.0.0.3:7622".parse().unwrap()])
+    ///         .run()
+    ///         .await;
+    /// }
+    /// ```
+    #[cfg(feature = "grpc")]
+    pub fn grpc_peers(mut self, peers: &[SocketAddr]) -> Self {
+        self.grpc_peers = peers.to_vec();
+        self
+    }
+
+    /// Secure the gRPC transport with mutual TLS and/or a shared token. (requires `grpc`
+    /// feature)
+    ///
+    /// Once secured, the HTTP `/run` endpoint requires the token as well, and work handed off over
+    /// gossip is refused. See [TransportConfig](crate::runtime::TransportConfig) for details.
+    #[cfg(feature = "grpc")]
+    pub fn grpc_transport(mut self, transport: TransportConfig) -> Self {
+        self.grpc_transport = transport;
+        self
+    }
+
+    /// Make a Scenario built at runtime (e.g. with `Scenario::from_fn`) available to be run by
+    /// name, as those created with the `#[scenario]` macro are. Each server which may run the
+    /// Scenario must register it under the same name.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use balter::prelude::*;
+    /// use balter::Scenario;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     BalterRuntime::new()
+    ///         .scenario("health_check", Scenario::from_fn("health_check", || async {}))
+    ///         .with_args()
+    ///         .run()
+    ///         .await;
+    /// }
+    /// ```
+    pub fn scenario(
+        self,
+        name: &str,
+        scenario: impl DistributedScenario<Output = RunStatistics> + 'static,
+    ) -> Self {
+        REGISTERED_SCENARIOS
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(name.to_string(), Box::new(scenario));
+        self
+    }
+
     #[instrument(name="balter", skip_all, fields(port=self.port))]
     pub async fn run(self) {
-        let gossip = Gossip::new(uuid::Uuid::new_v4(), self.port, spawn_scenario);
+        #[cfg(feature = "grpc")]
+        let credentials = match self.grpc_transport.load() {
+            Ok(credentials) => credentials,
+            Err(err) => {
+                error!("Unable to set up the gRPC transport: {err}");
+                std::process::exit(1);
+            }
+        };
+
+        let server_id = *SERVER_ID.get_or_init(Uuid::new_v4);
+        let gossip = Gossip::new(server_id, self.port, spawn_scenario);
+        // NOTE: Gossip is unauthenticated, so once the transport is secured work may only be
+        // handed off over it.
+        #[cfg(feature = "grpc")]
+        let gossip = if credentials.is_secured() {
+            gossip.refuse_help()
+        } else {
+            gossip
+        };
+        let _ = GOSSIP.set(gossip.clone());
+        let ledger = Arc::new(Mutex::new(PeerLedger::default()));
 
-        spawn_or_halt(server_task(self.port, gossip.clone())).await;
+        spawn_or_halt(server_task(
+            self.port,
+            gossip.clone(),
+            #[cfg(feature = "grpc")]
+            credentials.clone(),
+        ))
+        .await;
         spawn_or_halt(gossip_task(gossip.clone())).await;
-        spawn_or_halt(helper_task(gossip.clone())).await;
+        #[cfg(feature = "grpc")]
+        if let Some(port) = self.grpc_port {
+            spawn_or_halt(crate::grpc::server_task(
+                port,
+                gossip.clone(),
+                credentials.clone(),
+            ))
+            .await;
+        }
+        spawn_or_halt(helper_task(
+            gossip.clone(),
+            ledger.clone(),
+            #[cfg(feature = "grpc")]
+            self.grpc_peers,
+            #[cfg(feature = "grpc")]
+            credentials.clone(),
+        ))
+        .await;
+        spawn_or_halt(health_task(
+            gossip.clone(),
+            ledger,
+            #[cfg(feature = "grpc")]
+            credentials,
+        ))
+        .await;
     }
 }
 
@@ -135,27 +360,60 @@ pub(crate) fn spawn_scenario(config: ScenarioConfig) -> Result<(), RuntimeError>
         .map(|(idx, (name, _))| (*name, idx))
         .collect();
 
-    let idx = scenarios
-        .get(config.name.as_str())
-        .ok_or(RuntimeError::NoScenario)?;
-    info!("Running scenario {}.", &config.name);
-    let scenario = BALTER_SCENARIOS[*idx];
-    let fut = scenario.1().set_config(config);
+    let fut = match scenarios.get(config.name.as_str()) {
+        Some(idx) => {
+            info!("Running scenario {}.", &config.name);
+            BALTER_SCENARIOS[*idx].1().set_config(config)
+        }
+        None => {
+            let registered = REGISTERED_SCENARIOS
+                .get()
+                .ok_or(RuntimeError::NoScenario)?
+                .lock()?;
+            let scenario = registered
+                .get(&config.name)
+                .ok_or(RuntimeError::NoScenario)?;
+            info!("Running scenario {}.", &config.name);
+            scenario.set_config(config)
+        }
+    };
+    // NOTE: The count is incremented before returning, so that a peer which has just handed off
+    // work never sees it as finished.
+    RUNNING.fetch_add(1, Ordering::Relaxed);
     tokio::spawn(
         async move {
             fut.await;
+            RUNNING.fetch_sub(1, Ordering::Relaxed);
         }
         .in_current_span(),
     );
     Ok(())
 }
 
-async fn helper_task(gossip: Gossip) -> Result<(), RuntimeError> {
+async fn helper_task(
+    gossip: Gossip,
+    ledger: Arc<Mutex<PeerLedger>>,
+    #[cfg(feature = "grpc")] grpc_peers: Vec<SocketAddr>,
+    #[cfg(feature = "grpc")] credentials: Credentials,
+) -> Result<(), RuntimeError> {
     let (_, ref rx) = *BALTER_OUT;
     let rx = rx.clone();
     loop {
         if let Ok(msg) = rx.recv().await {
             match msg {
+                #[cfg(feature = "grpc")]
+                RuntimeMessage::Help(config) if !grpc_peers.is_empty() => {
+                    gossip.data.lock()?.set_state_busy();
+                    match crate::grpc::handoff(&grpc_peers, &config, &credentials).await {
+                        Ok((server_id, addr)) => ledger.lock()?.assign(
+                            busy_peer(server_id, addr),
+                            Transport::Grpc,
+                            *config,
+                            Instant::now(),
+                        ),
+                        Err(error) => error!("Unable to hand off work over gRPC: {error}"),
+                    }
+                }
                 RuntimeMessage::Help(config) => {
                     // TODO: The internal `data` probably shouldn't be exposed like this.
                     let peer = {
@@ -164,16 +422,43 @@ async fn helper_task(gossip: Gossip) -> Result<(), RuntimeError> {
                         data.select_free_peer()
                     };
                     if let Some(peer) = peer {
-                        let mut stream = peer_stream(&peer).await?;
-                        let res = gossip.request_help(&mut stream, peer.addr, config).await;
-                        if let Err(error) = res {
-                            error!("Error in gossip protocol: {error:?}");
+                        let mut stream = peer_stream(peer.addr).await?;
+                        let res = gossip
+                            .request_help(&mut stream, peer.addr, (*config).clone())
+                            .await;
+                        match res {
+                            Ok(()) => ledger.lock()?.assign(
+                                peer,
+                                Transport::Gossip,
+                                *config,
+                                Instant::now(),
+                            ),
+                            Err(error) => error!("Error in gossip protocol: {error:?}"),
                         }
                     } else {
                         error!("No Peers available to help.");
                         // TODO: Implement some form of retry/auto-scaling
                     }
                 }
+                RuntimeMessage::Shard(addr, config) => {
+                    gossip.data.lock()?.set_state_busy();
+                    let res = shard_to(
+                        &gossip,
+                        addr,
+                        &config,
+                        #[cfg(feature = "grpc")]
+                        (!grpc_peers.is_empty()).then_some(&credentials),
+                    )
+                    .await;
+                    match res {
+                        Ok((peer, transport)) => {
+                            ledger
+                                .lock()?
+                                .assign(peer, transport, *config, Instant::now())
+                        }
+                        Err(error) => error!("Unable to hand off work to {addr}: {error}"),
+                    }
+                }
                 RuntimeMessage::Finished => {
                     gossip.data.lock()?.set_state_free();
                 }
@@ -184,6 +469,110 @@ async fn helper_task(gossip: Gossip) -> Result<(), RuntimeError> {
     }
 }
 
+/// Hand off work to a specific peer
+async fn shard_to(
+    gossip: &Gossip,
+    addr: SocketAddr,
+    config: &ScenarioConfig,
+    #[cfg(feature = "grpc")] grpc: Option<&Credentials>,
+) -> Result<(PeerInfo, Transport), RuntimeError> {
+    #[cfg(feature = "grpc")]
+    if let Some(credentials) = grpc {
+        let (server_id, addr) = crate::grpc::handoff(&[addr], config, credentials).await?;
+        return Ok((busy_peer(server_id, addr), Transport::Grpc));
+    }
+
+    // NOTE: The help protocol doesn't tell us who accepted the work, so we learn the peer's ID
+    // first in order to track the work in the ledger.
+    let mut stream = peer_stream(addr).await?;
+    let server_id = gossip.request_heartbeat(&mut stream).await?.server_id;
+
+    let mut stream = peer_stream(addr).await?;
+    gossip
+        .request_help(&mut stream, addr, config.clone())
+        .await?;
+    Ok((busy_peer(server_id, addr), Transport::Gossip))
+}
+
+fn busy_peer(server_id: Uuid, addr: SocketAddr) -> PeerInfo {
+    PeerInfo {
+        server_id,
+        version: 0,
+        addr,
+        state: PeerState::Busy,
+    }
+}
+
+/// Check on the peers which have taken on our work, and redistribute the work of any which stop
+/// responding.
+async fn health_task(
+    gossip: Gossip,
+    ledger: Arc<Mutex<PeerLedger>>,
+    #[cfg(feature = "grpc")] credentials: Credentials,
+) -> Result<(), RuntimeError> {
+    let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let peers = { ledger.lock()?.peers() };
+        for (peer, transport) in peers {
+            let heartbeat = check_peer(
+                &gossip,
+                &peer,
+                transport,
+                #[cfg(feature = "grpc")]
+                &credentials,
+            );
+            match heartbeat.await {
+                Ok(heartbeat) if heartbeat.server_id == peer.server_id => {
+                    ledger.lock()?.heartbeat(&heartbeat, Instant::now());
+                }
+                Ok(_) => warn!("Peer {} restarted and lost its work.", peer.addr),
+                Err(err) => debug!("Heartbeat to {} failed: {err:?}", peer.addr),
+            }
+        }
+
+        let reclaimed = { ledger.lock()?.reclaim(Instant::now(), HEARTBEAT_TIMEOUT) };
+        for (peer, config) in reclaimed {
+            warn!(
+                "Peer {} stopped responding; redistributing {}.",
+                peer.addr, config.name
+            );
+            gossip
+                .data
+                .lock()?
+                .set_peer_state(&peer.server_id, PeerState::Unreachable);
+
+            let (ref tx, _) = *BALTER_OUT;
+            tx.send(RuntimeMessage::Help(Box::new(config)))
+                .await
+                .map_err(|_| RuntimeError::ChannelClosed)?;
+        }
+    }
+}
+
+async fn check_peer(
+    gossip: &Gossip,
+    peer: &PeerInfo,
+    transport: Transport,
+    #[cfg(feature = "grpc")] credentials: &Credentials,
+) -> Result<Heartbeat, RuntimeError> {
+    let heartbeat = async {
+        match transport {
+            Transport::Gossip => {
+                let mut stream = peer_stream(peer.addr).await?;
+                Ok(gossip.request_heartbeat(&mut stream).await?)
+            }
+            #[cfg(feature = "grpc")]
+            Transport::Grpc => Ok(crate::grpc::heartbeat(peer.addr, credentials).await?),
+        }
+    };
+    tokio::time::timeout(HEARTBEAT_INTERVAL, heartbeat)
+        .await
+        .unwrap_or(Err(GossipError::Timeout.into()))
+}
+
 async fn spawn_or_halt<F, R, E>(fut: F)
 where
     F: Future<Output = Result<R, E>> + Send + 'static,
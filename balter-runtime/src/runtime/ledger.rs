@@ -0,0 +1,206 @@
+use crate::gossip::{Heartbeat, PeerInfo};
+use balter_core::ScenarioConfig;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// How work was handed off to a peer, and therefore how to check on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Transport {
+    Gossip,
+    #[cfg(feature = "grpc")]
+    Grpc,
+}
+
+/// Tracks the work this server has handed off to peers, so that it can be reclaimed if a peer
+/// stops responding.
+#[derive(Default)]
+pub(crate) struct PeerLedger {
+    assignments: Vec<Assignment>,
+}
+
+struct Assignment {
+    peer: PeerInfo,
+    transport: Transport,
+    config: ScenarioConfig,
+    started: Instant,
+    last_seen: Instant,
+}
+
+impl PeerLedger {
+    pub fn assign(
+        &mut self,
+        peer: PeerInfo,
+        transport: Transport,
+        config: ScenarioConfig,
+        now: Instant,
+    ) {
+        self.assignments.push(Assignment {
+            peer,
+            transport,
+            config,
+            started: now,
+            last_seen: now,
+        });
+    }
+
+    /// Peers which currently own some of our work
+    pub fn peers(&self) -> Vec<(PeerInfo, Transport)> {
+        let mut seen = HashSet::new();
+        self.assignments
+            .iter()
+            .filter(|a| seen.insert(a.peer.server_id))
+            .map(|a| (a.peer, a.transport))
+            .collect()
+    }
+
+    /// Record a response from a peer. Once the peer is no longer running any work on behalf of
+    /// other servers our work on it has finished, and is retired rather than left to be reclaimed
+    /// (which work without a duration otherwise would be).
+    pub fn heartbeat(&mut self, heartbeat: &Heartbeat, now: Instant) {
+        if heartbeat.running == 0 {
+            self.assignments
+                .retain(|a| a.peer.server_id != heartbeat.server_id);
+            return;
+        }
+
+        for assignment in &mut self.assignments {
+            if assignment.peer.server_id == heartbeat.server_id {
+                assignment.last_seen = now;
+            }
+        }
+    }
+
+    /// Drop work which has run to completion, and take back work from peers which have not
+    /// responded within the `timeout`. Reclaimed configs have their duration reduced by the time
+    /// already spent running.
+    pub fn reclaim(&mut self, now: Instant, timeout: Duration) -> Vec<(PeerInfo, ScenarioConfig)> {
+        self.assignments.retain(|a| match a.config.duration {
+            Some(duration) => now.saturating_duration_since(a.started) < duration,
+            None => true,
+        });
+
+        let mut reclaimed = vec![];
+        self.assignments.retain(|a| {
+            if now.saturating_duration_since(a.last_seen) < timeout {
+                return true;
+            }
+
+            let mut config = a.config.clone();
+            config.duration = config
+                .duration
+                .map(|d| d.saturating_sub(now.saturating_duration_since(a.started)));
+            reclaimed.push((a.peer, config));
+            false
+        });
+        reclaimed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gossip::PeerState;
+    use uuid::Uuid;
+
+    fn peer(port: u16) -> PeerInfo {
+        PeerInfo {
+            server_id: Uuid::new_v4(),
+            version: 1,
+            addr: format!("0.0.0.0:{port}").parse().unwrap(),
+            state: PeerState::Free,
+        }
+    }
+
+    fn running(peer: &PeerInfo, running: u32) -> Heartbeat {
+        Heartbeat {
+            server_id: peer.server_id,
+            running,
+        }
+    }
+
+    fn config(duration: u64) -> ScenarioConfig {
+        let mut config = ScenarioConfig::new("test");
+        config.duration = Some(Duration::from_secs(duration));
+        config
+    }
+
+    #[test]
+    fn test_peer_drops_out() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let timeout = Duration::from_secs(10);
+
+        let (alive, dead) = (peer(1111), peer(2222));
+        let mut ledger = PeerLedger::default();
+        ledger.assign(alive, Transport::Gossip, config(300), start);
+        ledger.assign(dead, Transport::Gossip, config(300), start);
+        assert_eq!(ledger.peers().len(), 2);
+
+        // Both peers respond for a while, then one drops out
+        for secs in (0..=20).step_by(5) {
+            ledger.heartbeat(&running(&alive, 1), at(secs));
+            if secs <= 15 {
+                ledger.heartbeat(&running(&dead, 1), at(secs));
+            }
+            assert!(ledger.reclaim(at(secs), timeout).is_empty());
+        }
+
+        let reclaimed = ledger.reclaim(at(28), timeout);
+        assert_eq!(reclaimed.len(), 1);
+        assert_eq!(reclaimed[0].0.server_id, dead.server_id);
+        assert_eq!(reclaimed[0].1.duration, Some(Duration::from_secs(272)));
+        assert_eq!(ledger.peers(), vec![(alive, Transport::Gossip)]);
+    }
+
+    #[test]
+    fn test_completed_work() {
+        let start = Instant::now();
+        let mut ledger = PeerLedger::default();
+        ledger.assign(peer(1111), Transport::Gossip, config(10), start);
+
+        // Work which has run its course is dropped rather than reclaimed
+        let reclaimed = ledger.reclaim(start + Duration::from_secs(60), Duration::from_secs(5));
+        assert!(reclaimed.is_empty());
+        assert!(ledger.peers().is_empty());
+    }
+
+    #[test]
+    fn test_interleaved_assignments() {
+        let start = Instant::now();
+        let (first, second) = (peer(1111), peer(2222));
+        let mut ledger = PeerLedger::default();
+        for peer in [first, second, first, second] {
+            ledger.assign(peer, Transport::Gossip, config(300), start);
+        }
+
+        // Each peer is only checked on once, however much of our work it holds
+        assert_eq!(
+            ledger.peers(),
+            vec![(first, Transport::Gossip), (second, Transport::Gossip)]
+        );
+    }
+
+    #[test]
+    fn test_finished_work() {
+        let start = Instant::now();
+        let timeout = Duration::from_secs(10);
+        let mut config = config(0);
+        config.duration = None;
+
+        let (busy, done) = (peer(1111), peer(2222));
+        let mut ledger = PeerLedger::default();
+        ledger.assign(busy, Transport::Gossip, config.clone(), start);
+        ledger.assign(done, Transport::Gossip, config, start);
+
+        // Work without a duration is retired once the peer reports it is no longer running any,
+        // rather than being reclaimed and run again
+        ledger.heartbeat(&running(&busy, 1), start + Duration::from_secs(5));
+        ledger.heartbeat(&running(&done, 0), start + Duration::from_secs(5));
+        assert_eq!(ledger.peers(), vec![(busy, Transport::Gossip)]);
+
+        let reclaimed = ledger.reclaim(start + Duration::from_secs(60), timeout);
+        assert_eq!(reclaimed.len(), 1);
+        assert_eq!(reclaimed[0].0.server_id, busy.server_id);
+        assert_eq!(reclaimed[0].1.duration, None);
+    }
+}
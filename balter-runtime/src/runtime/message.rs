@@ -1,6 +1,10 @@
 use balter_core::ScenarioConfig;
+use std::net::SocketAddr;
 
 pub enum RuntimeMessage {
-    Help(ScenarioConfig),
+    /// Hand off the work to any free peer
+    Help(Box<ScenarioConfig>),
+    /// Hand off the work to the given peer
+    Shard(SocketAddr, Box<ScenarioConfig>),
     Finished,
 }
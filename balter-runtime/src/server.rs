@@ -1,11 +1,13 @@
-use crate::{error::RuntimeError, gossip::Gossip, runtime::spawn_scenario};
+#[cfg(feature = "grpc")]
+use crate::grpc::Credentials;
+use crate::{error::RuntimeError, gossip::Gossip};
 use axum::{
     extract::{
         connect_info::ConnectInfo,
         ws::{WebSocket, WebSocketUpgrade},
         Json, State,
     },
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     routing::{get, post},
     Router,
@@ -13,9 +15,11 @@ use axum::{
 use balter_core::ScenarioConfig;
 use std::{net::SocketAddr, sync::Arc};
 use thiserror::Error;
+use tokio::net::TcpListener;
 use tower::ServiceBuilder;
 use tower_http::trace::TraceLayer;
-use tracing::{debug, error, instrument};
+#[allow(unused)]
+use tracing::{debug, error, instrument, warn};
 
 #[derive(Error, Debug)]
 pub(crate) enum ServerError {
@@ -26,8 +30,32 @@ pub(crate) enum ServerError {
     IoError(#[from] std::io::Error),
 }
 
-pub(crate) async fn server_task(port: u16, gossip: Gossip) -> Result<(), ServerError> {
-    let state = ServerState { gossip };
+pub(crate) async fn server_task(
+    port: u16,
+    gossip: Gossip,
+    #[cfg(feature = "grpc")] credentials: Credentials,
+) -> Result<(), ServerError> {
+    let socket_addr: SocketAddr = format!("0.0.0.0:{port}").parse()?;
+    let listener = TcpListener::bind(socket_addr).await?;
+    serve(
+        listener,
+        gossip,
+        #[cfg(feature = "grpc")]
+        credentials,
+    )
+    .await
+}
+
+async fn serve(
+    listener: TcpListener,
+    gossip: Gossip,
+    #[cfg(feature = "grpc")] credentials: Credentials,
+) -> Result<(), ServerError> {
+    let state = ServerState {
+        gossip,
+        #[cfg(feature = "grpc")]
+        credentials,
+    };
 
     let app = Router::new()
         .route("/run", post(run))
@@ -36,9 +64,6 @@ pub(crate) async fn server_task(port: u16, gossip: Gossip) -> Result<(), ServerE
         .layer(ServiceBuilder::new().layer(TraceLayer::new_for_http()))
         .into_make_service_with_connect_info::<SocketAddr>();
 
-    let socket_addr: SocketAddr = format!("0.0.0.0:{port}").parse()?;
-    let listener = tokio::net::TcpListener::bind(socket_addr).await?;
-
     debug!("Axum server starting up...");
     axum::serve(listener, app).await?;
 
@@ -47,15 +72,39 @@ pub(crate) async fn server_task(port: u16, gossip: Gossip) -> Result<(), ServerE
 
 struct ServerState {
     gossip: Gossip,
+    #[cfg(feature = "grpc")]
+    credentials: Credentials,
+}
+
+impl ServerState {
+    /// Reject requests which don't carry the token, once the transport is secured (see
+    /// [TransportConfig](crate::grpc::TransportConfig))
+    #[cfg_attr(not(feature = "grpc"), allow(unused_variables))]
+    fn authorize(&self, headers: &HeaderMap, addr: SocketAddr) -> Result<(), HandlerError> {
+        #[cfg(feature = "grpc")]
+        if !self.credentials.authorize_http(
+            headers
+                .get(axum::http::header::AUTHORIZATION)
+                .map(|value| value.as_bytes()),
+        ) {
+            warn!("Rejected an unauthenticated request from {addr}.");
+            return Err(HandlerError::Unauthenticated);
+        }
+        Ok(())
+    }
 }
 
 #[derive(Error, Debug)]
 enum HandlerError {
     #[error("Channel send error (Balter runtime has likely fallen over): {0}")]
-    Send(#[from] async_channel::SendError<ScenarioConfig>),
+    Send(#[from] Box<async_channel::SendError<ScenarioConfig>>),
 
     #[error("Runtime error: {0}")]
     Runtime(#[from] RuntimeError),
+
+    #[cfg(feature = "grpc")]
+    #[error("Missing or invalid token")]
+    Unauthenticated,
 }
 
 impl IntoResponse for HandlerError {
@@ -73,19 +122,27 @@ impl IntoResponse for HandlerError {
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Runtime error: {err:?}"),
             ),
+            #[cfg(feature = "grpc")]
+            Unauthenticated => (
+                StatusCode::UNAUTHORIZED,
+                "Missing or invalid token".to_string(),
+            ),
         }
         .into_response()
     }
 }
 
-#[instrument(skip(_state))]
+#[instrument(skip(state, headers))]
 async fn run(
-    State(_state): State<Arc<ServerState>>,
+    State(state): State<Arc<ServerState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(scenario): Json<ScenarioConfig>,
 ) -> Result<String, HandlerError> {
-    let output = format!("Running scenario {}", &scenario.name);
+    state.authorize(&headers, addr)?;
+    let output = format!("Running scenario {}", scenario.name);
 
-    spawn_scenario(scenario)?;
+    state.gossip.spawn_scenario(scenario)?;
 
     Ok(output)
 }
@@ -104,3 +161,50 @@ async fn handle_ws(mut socket: WebSocket, state: Arc<ServerState>, addr: SocketA
         error!("Error in gossip protocol: {err:?}");
     }
 }
+
+#[cfg(all(test, feature = "grpc"))]
+mod tests {
+    use super::*;
+    use crate::grpc::TransportConfig;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use uuid::Uuid;
+
+    static SPAWNED: AtomicBool = AtomicBool::new(false);
+
+    fn fake_spawn_scenario(_config: ScenarioConfig) -> Result<(), RuntimeError> {
+        SPAWNED.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_unauthenticated_run() {
+        let credentials = TransportConfig::new().token("secret").load().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let gossip = Gossip::new(Uuid::new_v4(), 0, fake_spawn_scenario);
+        tokio::spawn(serve(listener, gossip, credentials));
+
+        let client = reqwest::Client::new();
+        let run = |token: Option<&str>| {
+            let mut request = client
+                .post(format!("http://{addr}/run"))
+                .json(&ScenarioConfig::new("test_scenario"));
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            request.send()
+        };
+
+        for token in [None, Some("guess")] {
+            let res = run(token).await.unwrap();
+            assert_eq!(res.status().as_u16(), 401);
+        }
+        assert!(!SPAWNED.load(Ordering::Relaxed));
+        assert!(logs_contain("Rejected an unauthenticated request"));
+
+        let res = run(Some("secret")).await.unwrap();
+        assert_eq!(res.status().as_u16(), 200);
+        assert!(SPAWNED.load(Ordering::Relaxed));
+    }
+}
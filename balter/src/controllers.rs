@@ -1,34 +1,75 @@
+mod aimd;
 mod constant;
 mod error_rate;
 mod latency;
+mod onset;
+mod ramp;
+mod staged;
 
+pub(crate) use aimd::AimdController;
 pub(crate) use constant::ConstantController;
 pub(crate) use error_rate::ErrorRateController;
 pub(crate) use latency::LatencyController;
+pub(crate) use onset::OnsetController;
+pub(crate) use ramp::RampController;
+pub(crate) use staged::StagedController;
 
-use crate::measurement::Measurement;
-use balter_core::{LatencyConfig, ScenarioConfig};
+use crate::sample_set::SampleSet;
+use balter_core::{LatencyConfig, RampConfig, ScenarioConfig};
 use std::num::NonZeroU32;
 
-pub(crate) trait Controller: Send {
+/// Controller over the goal TPS of a Scenario.
+///
+/// Each load testing mode (`.tps()`, `.error_rate()`, `.latency()`, etc.) is implemented as a
+/// Controller, and custom control strategies can be provided with
+/// [tps_controller](crate::scenario::ConfigurableScenario::tps_controller). When several
+/// Controllers are in use, the Scenario runs at the lowest TPS any of them allows.
+///
+/// NOTE: Controllers are called from Balter's sampling loop, which is time-sensitive. `analyze`
+/// should return quickly.
+pub trait Controller: Send {
+    /// TPS to start the Scenario at
     fn initial_tps(&self) -> NonZeroU32;
-    fn limit(&mut self, sample: &Measurement, stable: bool) -> NonZeroU32;
+
+    /// Called each time Balter samples the Scenario, with the most recent samples. `stable` is
+    /// true once the sampler has reached the current goal TPS and the measurements have
+    /// settled; controllers should generally only increase the TPS when it is set.
+    fn analyze(&mut self, samples: &SampleSet, stable: bool) -> ControllerOutcome;
+}
+
+/// Outcome of [Controller::analyze]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControllerOutcome {
+    /// Run the Scenario at (up to) the given TPS
+    Limit(NonZeroU32),
+    /// Stop the Scenario
+    Stop,
 }
 
 pub(crate) struct CompositeController {
     controllers: Vec<Box<dyn Controller>>,
+    /// Kept apart from the other controllers, so that the onset it finds can be reported
+    onset: Option<OnsetController>,
 }
 
 impl CompositeController {
-    pub fn new(config: &ScenarioConfig) -> Self {
-        let mut controllers = vec![];
+    pub fn new(config: &ScenarioConfig, custom: Option<Box<dyn Controller>>) -> Self {
+        let mut controllers: Vec<Box<dyn Controller>> = custom.into_iter().collect();
 
-        if let Some(tps) = config.max_tps {
-            controllers.push(Box::new(ConstantController::new(tps)) as Box<dyn Controller>);
+        match (config.max_tps, config.aimd) {
+            (Some(tps), Some(error_rate)) => {
+                controllers.push(Box::new(AimdController::new(&config.name, tps, error_rate)))
+            }
+            (Some(tps), None) => controllers.push(Box::new(ConstantController::new(tps))),
+            (None, _) => {}
         }
 
         if let Some(error_rate) = config.error_rate {
-            controllers.push(Box::new(ErrorRateController::new(&config.name, error_rate)));
+            controllers.push(Box::new(ErrorRateController::new(
+                &config.name,
+                error_rate,
+                config.search,
+            )));
         }
 
         if let Some(LatencyConfig { latency, quantile }) = config.latency {
@@ -39,7 +80,34 @@ impl CompositeController {
             )));
         }
 
-        Self { controllers }
+        if let Some(RampConfig { start, end }) = config.ramp {
+            controllers.push(Box::new(RampController::new(
+                &config.name,
+                start,
+                end,
+                config.duration,
+            )));
+        }
+
+        if let Some(stages) = &config.stages {
+            controllers.push(Box::new(StagedController::new(&config.name, stages)));
+        }
+
+        let onset = config.onset.then(|| OnsetController::new(&config.name));
+
+        Self { controllers, onset }
+    }
+
+    /// TPS at which the error rate started climbing, for scenarios run with `.onset()`
+    pub fn onset_tps(&self) -> Option<u32> {
+        self.onset.as_ref().and_then(OnsetController::onset_tps)
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = &mut dyn Controller> {
+        self.controllers
+            .iter_mut()
+            .map(|c| &mut **c as &mut dyn Controller)
+            .chain(self.onset.iter_mut().map(|c| c as &mut dyn Controller))
     }
 }
 
@@ -48,15 +116,54 @@ impl Controller for CompositeController {
         self.controllers
             .iter()
             .map(|c| c.initial_tps())
+            .chain(self.onset.iter().map(|c| c.initial_tps()))
             .min()
             .expect("No controllers present.")
     }
 
-    fn limit(&mut self, sample: &Measurement, stable: bool) -> NonZeroU32 {
-        self.controllers
-            .iter_mut()
-            .map(|c| c.limit(sample, stable))
-            .min()
-            .expect("No controllers present.")
+    fn analyze(&mut self, samples: &SampleSet, stable: bool) -> ControllerOutcome {
+        let mut limit = None;
+        for controller in self.iter_mut() {
+            match controller.analyze(samples, stable) {
+                ControllerOutcome::Stop => return ControllerOutcome::Stop,
+                ControllerOutcome::Limit(tps) => {
+                    limit = Some(limit.map_or(tps, |limit: NonZeroU32| limit.min(tps)));
+                }
+            }
+        }
+        ControllerOutcome::Limit(limit.expect("No controllers present."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(ControllerOutcome);
+
+    impl Controller for Fixed {
+        fn initial_tps(&self) -> NonZeroU32 {
+            NonZeroU32::new(500).unwrap()
+        }
+
+        fn analyze(&mut self, _samples: &SampleSet, _stable: bool) -> ControllerOutcome {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_composite_controller() {
+        let mut config = ScenarioConfig::new("test");
+        config.max_tps = NonZeroU32::new(1_000);
+        let samples = SampleSet::new(1);
+
+        let limit = ControllerOutcome::Limit(NonZeroU32::new(200).unwrap());
+        let mut controllers = CompositeController::new(&config, Some(Box::new(Fixed(limit))));
+        assert_eq!(controllers.initial_tps().get(), 500);
+        assert_eq!(controllers.analyze(&samples, true), limit);
+
+        let stop = Box::new(Fixed(ControllerOutcome::Stop));
+        let mut controllers = CompositeController::new(&config, Some(stop));
+        assert_eq!(controllers.analyze(&samples, true), ControllerOutcome::Stop);
     }
 }
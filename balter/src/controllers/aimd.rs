@@ -0,0 +1,130 @@
+use crate::controllers::{Controller, ControllerOutcome};
+use crate::metric_keys::scenario_metric;
+use crate::sample_set::SampleSet;
+use std::num::NonZeroU32;
+#[allow(unused)]
+use tracing::{debug, error, info, trace};
+
+/// Factor the goal TPS is cut by when the error rate exceeds the threshold
+const AIMD_DECREASE: f64 = 0.5;
+/// Fraction of the requested TPS the goal recovers by with each stable sample
+const AIMD_INCREASE: f64 = 0.05;
+
+/// Guards a fixed TPS with additive-increase/multiplicative-decrease: the goal TPS is halved
+/// whenever the error rate exceeds the threshold, and recovers gradually towards the requested
+/// TPS while it stays below.
+pub(crate) struct AimdController {
+    name: String,
+    requested_tps: NonZeroU32,
+    goal_tps: NonZeroU32,
+    error_rate: f64,
+}
+
+impl AimdController {
+    pub fn new(name: &str, requested_tps: NonZeroU32, error_rate: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            requested_tps,
+            goal_tps: requested_tps,
+            error_rate,
+        }
+    }
+}
+
+impl Controller for AimdController {
+    fn initial_tps(&self) -> NonZeroU32 {
+        self.requested_tps
+    }
+
+    fn analyze(&mut self, samples: &SampleSet, stable: bool) -> ControllerOutcome {
+        if samples.latest().is_none() {
+            return ControllerOutcome::Limit(self.goal_tps);
+        }
+
+        let goal = self.goal_tps.get() as f64;
+        if samples.recent_error_rate() > self.error_rate {
+            let backoff = (goal * AIMD_DECREASE) as u32;
+            self.goal_tps = NonZeroU32::new(backoff).unwrap_or(NonZeroU32::MIN);
+            debug!(
+                "Error rate over threshold; backing off to {}",
+                self.goal_tps
+            );
+        } else if stable && self.goal_tps < self.requested_tps {
+            let step = (self.requested_tps.get() as f64 * AIMD_INCREASE).max(1.);
+            self.goal_tps = self
+                .goal_tps
+                .saturating_add(step as u32)
+                .min(self.requested_tps);
+            trace!("Recovering goal TPS to {}", self.goal_tps);
+        }
+
+        if cfg!(feature = "metrics") {
+            let (name, labels) = scenario_metric("balter_scenario_aimd_goal_tps", &self.name);
+            metrics::gauge!(name, labels).set(self.goal_tps.get());
+        }
+
+        ControllerOutcome::Limit(self.goal_tps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::Measurement;
+    use std::time::Duration;
+
+    /// Mock service which starts erroring above 1,000 TPS
+    fn sample(tps: NonZeroU32) -> SampleSet {
+        let tps = tps.get() as u64;
+        let error = tps.saturating_sub(1_000);
+        let mut samples = SampleSet::new(1);
+        samples.push(
+            Measurement::new(tps - error, error, Duration::from_secs(1)),
+            Duration::ZERO,
+        );
+        samples
+    }
+
+    #[test]
+    fn test_aimd() {
+        let requested = NonZeroU32::new(4_000).unwrap();
+        let mut controller = AimdController::new("test", requested, 0.05);
+        let mut goal_tps = controller.initial_tps();
+        assert_eq!(goal_tps, requested);
+
+        let mut max_tps = 0;
+        for i in 0..100 {
+            match controller.analyze(&sample(goal_tps), true) {
+                ControllerOutcome::Limit(tps) => goal_tps = tps,
+                ControllerOutcome::Stop => panic!("AIMD stopped"),
+            }
+            if i >= 10 {
+                max_tps = max_tps.max(goal_tps.get());
+            }
+        }
+
+        // NOTE: Once backed off, the goal probes just past the capacity of the service before
+        // backing off again.
+        assert!(max_tps <= 1_200, "{max_tps}");
+        assert!(max_tps >= 1_000, "{max_tps}");
+    }
+
+    #[test]
+    fn test_aimd_recovers() {
+        let requested = NonZeroU32::new(1_000).unwrap();
+        let mut controller = AimdController::new("test", requested, 0.05);
+        controller.goal_tps = NonZeroU32::new(100).unwrap();
+
+        // NOTE: The goal only recovers once the sampler has stabilized at it
+        let outcome = controller.analyze(&sample(controller.goal_tps), false);
+        assert_eq!(
+            outcome,
+            ControllerOutcome::Limit(NonZeroU32::new(100).unwrap())
+        );
+
+        for _ in 0..30 {
+            controller.analyze(&sample(controller.goal_tps), true);
+        }
+        assert_eq!(controller.goal_tps, requested);
+    }
+}
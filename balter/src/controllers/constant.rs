@@ -1,5 +1,5 @@
-use crate::controllers::Controller;
-use crate::measurement::Measurement;
+use crate::controllers::{Controller, ControllerOutcome};
+use crate::sample_set::SampleSet;
 use std::num::NonZeroU32;
 
 pub(crate) struct ConstantController {
@@ -17,7 +17,7 @@ impl Controller for ConstantController {
         self.goal_tps
     }
 
-    fn limit(&mut self, _sample: &Measurement, _stable: bool) -> NonZeroU32 {
-        self.goal_tps
+    fn analyze(&mut self, _samples: &SampleSet, _stable: bool) -> ControllerOutcome {
+        ControllerOutcome::Limit(self.goal_tps)
     }
 }
@@ -1,27 +1,41 @@
-use crate::controllers::Controller;
+use crate::controllers::{Controller, ControllerOutcome};
 use crate::measurement::Measurement;
-use balter_core::BASE_TPS;
+use crate::metric_keys::scenario_metric;
+use crate::sample_set::SampleSet;
+use balter_core::{SearchStrategy, BASE_TPS};
 use std::num::NonZeroU32;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, trace, warn, Instrument};
 
 const ERROR_RATE_TOLERANCE: f64 = 0.03;
 const DEFAULT_SMALL_STEP_SIZE: f64 = 0.5;
+/// Bisection stops once the bracket is within this fraction of the upper bound
+const BISECT_RESOLUTION: f64 = 0.02;
+/// When the error rate drifts out of bounds after stabilizing, the search restarts within this
+/// fraction of the current goal
+const BISECT_REOPEN_RATIO: f64 = 0.25;
+/// Consecutive out-of-bounds samples required before restarting the search, so that a single
+/// noisy sample does not throw away a stable goal
+const BISECT_REOPEN_SAMPLES: usize = 3;
 
 pub(crate) struct ErrorRateController {
-    base_label: String,
+    name: String,
     goal_tps: NonZeroU32,
     error_rate: f64,
+    search: SearchStrategy,
     state: State,
+    out_of_bounds: usize,
 }
 
 impl ErrorRateController {
-    pub fn new(name: &str, error_rate: f64) -> Self {
+    pub fn new(name: &str, error_rate: f64, search: SearchStrategy) -> Self {
         Self {
-            base_label: format!("balter_{name}"),
+            name: name.to_string(),
             goal_tps: BASE_TPS,
             error_rate,
+            search,
             state: State::BigStep,
+            out_of_bounds: 0,
         }
     }
 
@@ -34,84 +48,168 @@ impl ErrorRateController {
 
         match sample_error_rate {
             // NOTE: Special case for 0. error rate since that is the inflection point
-            x if x == 0. => Bounds::Under,
+            0. => Bounds::Under,
             x if x >= bounds.0 && x <= bounds.1 => Bounds::At,
             x if x > bounds.1 => Bounds::Over,
             _ => Bounds::Under,
         }
     }
-}
-
-impl Controller for ErrorRateController {
-    fn initial_tps(&self) -> NonZeroU32 {
-        BASE_TPS
-    }
-
-    fn limit(&mut self, sample: &Measurement, stable: bool) -> NonZeroU32 {
-        // TODO: Remove panic; this can be a type-safe check
-        let sample_error_rate = sample.error_rate;
 
-        let (new_goal_tps, new_state) = match self.check_bounds(sample_error_rate) {
+    fn step(&self, bounds: Bounds, sample: &Measurement) -> (NonZeroU32, State) {
+        match bounds {
             Bounds::Under => match self.state {
                 s @ State::BigStep => {
                     trace!("Under bounds w/ BigStep");
-                    (NonZeroU32::new(self.goal_tps.get() * 2).unwrap(), s)
+                    (
+                        NonZeroU32::new(self.goal_tps.get().saturating_mul(2)).unwrap(),
+                        s,
+                    )
                 }
                 s @ State::SmallStep(step_ratio) => {
                     trace!("Under bounds w/ SmallStep.");
-                    // TODO: Better handling of conversions
                     let step = (self.goal_tps.get() as f64 * step_ratio).max(1.);
-                    (
-                        NonZeroU32::new(self.goal_tps.get() + step as u32).unwrap(),
-                        s,
-                    )
+                    (self.goal_tps.saturating_add(step as u32), s)
                 }
-                State::Stable => {
+                State::Stable | State::Bisect { .. } => {
                     trace!("Under bounds w/ Stable.");
                     (self.goal_tps, State::SmallStep(DEFAULT_SMALL_STEP_SIZE))
                 }
             },
-            Bounds::At => {
-                match self.state {
-                    State::BigStep | State::SmallStep(_) => {
-                        trace!("At bounds w/ BigStep|SmallStep.");
-                        // TODO: Remove unwraps
-                        (convert_to_nonzerou32(sample.tps).unwrap(), State::Stable)
-                    }
-                    s @ State::Stable => {
-                        trace!("At bounds w/ Stable.");
-                        (self.goal_tps, s)
-                    }
+            Bounds::At => match self.state {
+                State::BigStep | State::SmallStep(_) | State::Bisect { .. } => {
+                    trace!("At bounds w/ BigStep|SmallStep.");
+                    (convert_to_nonzerou32(sample.tps), State::Stable)
                 }
-            }
-            Bounds::Over => {
-                match self.state {
-                    State::BigStep => {
-                        trace!("Over bounds w/ BigStep.");
-                        // TODO: Remove unwrap
-                        (
-                            NonZeroU32::new(self.goal_tps.get() / 2).unwrap(),
-                            State::SmallStep(DEFAULT_SMALL_STEP_SIZE),
-                        )
-                    }
-                    State::SmallStep(step_ratio) => {
-                        trace!("Over bounds w/ SmallStep({step_ratio}).");
-
-                        let rev_goal = (self.goal_tps.get() as f64 / (step_ratio + 1.)).max(1.);
-                        (
-                            NonZeroU32::new(rev_goal as u32).unwrap(),
-                            State::SmallStep(step_ratio / 2.),
-                        )
-                    }
-                    State::Stable => {
-                        trace!("Over bounds w/ Stable.");
-                        (self.goal_tps, State::SmallStep(DEFAULT_SMALL_STEP_SIZE))
-                    }
+                s @ State::Stable => {
+                    trace!("At bounds w/ Stable.");
+                    (self.goal_tps, s)
+                }
+            },
+            Bounds::Over => match self.state {
+                State::BigStep => {
+                    trace!("Over bounds w/ BigStep.");
+                    (
+                        NonZeroU32::new(self.goal_tps.get() / 2).unwrap_or(NonZeroU32::MIN),
+                        State::SmallStep(DEFAULT_SMALL_STEP_SIZE),
+                    )
+                }
+                State::SmallStep(step_ratio) => {
+                    trace!("Over bounds w/ SmallStep({step_ratio}).");
+
+                    let rev_goal = self.goal_tps.get() as f64 / (step_ratio + 1.);
+                    (
+                        convert_to_nonzerou32(rev_goal),
+                        State::SmallStep(step_ratio / 2.),
+                    )
                 }
+                State::Stable | State::Bisect { .. } => {
+                    trace!("Over bounds w/ Stable.");
+                    (self.goal_tps, State::SmallStep(DEFAULT_SMALL_STEP_SIZE))
+                }
+            },
+        }
+    }
+
+    fn bisect(&mut self, bounds: Bounds, sample: &Measurement) -> (NonZeroU32, State) {
+        let goal = self.goal_tps.get();
+        // NOTE: The service can lose capacity partway through the search, after which the lower
+        // bound no longer holds. The successful TPS while over bounds is an estimate of the
+        // current capacity, so the search never stays above it.
+        let capacity = sample.tps as u32;
+
+        if let (State::Stable, Bounds::Under | Bounds::Over) = (self.state, bounds) {
+            self.out_of_bounds += 1;
+            if self.out_of_bounds < BISECT_REOPEN_SAMPLES {
+                trace!("Out of bounds w/ Stable; holding.");
+                return (self.goal_tps, State::Stable);
+            }
+        }
+        self.out_of_bounds = 0;
+
+        let (low, high) = match (bounds, self.state) {
+            (Bounds::At, _) => {
+                trace!("At bounds.");
+                return (self.goal_tps, State::Stable);
+            }
+            (Bounds::Under, State::BigStep) => {
+                trace!("Under bounds w/ BigStep");
+                return (
+                    NonZeroU32::new(goal.saturating_mul(2)).unwrap(),
+                    State::BigStep,
+                );
+            }
+            (Bounds::Over, State::BigStep) => {
+                trace!("Over bounds w/ BigStep.");
+                (goal / 2, goal)
+            }
+            (Bounds::Under, State::Bisect { high, .. }) => {
+                trace!("Under bounds w/ Bisect.");
+                (goal, high)
+            }
+            (Bounds::Over, State::Bisect { low, .. }) => {
+                trace!("Over bounds w/ Bisect.");
+                (low.min(capacity), goal)
+            }
+            (Bounds::Under, _) => {
+                trace!("Under bounds w/ Stable.");
+                (goal, scale(goal, 1. + BISECT_REOPEN_RATIO))
+            }
+            (Bounds::Over, _) => {
+                trace!("Over bounds w/ Stable.");
+                (scale(goal, 1. - BISECT_REOPEN_RATIO).min(capacity), goal)
             }
         };
+        trace!("Bisecting between {low} and {high}");
+
+        if (high - low) as f64 <= (high as f64 * BISECT_RESOLUTION).max(1.) {
+            debug!("Bisection converged on {low} TPS");
+            return (
+                NonZeroU32::new(low).unwrap_or(NonZeroU32::MIN),
+                State::Stable,
+            );
+        }
+
+        let mid = NonZeroU32::new(low + (high - low) / 2).unwrap_or(NonZeroU32::MIN);
+        (mid, State::Bisect { low, high })
+    }
+
+    /// Log each trial TPS of the search along with its outcome, so that the trajectory towards
+    /// the goal error rate can be followed.
+    fn log_trial(&self, error_rate: f64, new_goal_tps: NonZeroU32, new_state: State) {
+        let error_rate = error_rate * 100.;
+        match (self.state, new_state) {
+            (State::Stable, State::Stable) => {}
+            (_, State::Stable) => info!(
+                "{}: {error_rate:.1}% errors at {} TPS; settled on {new_goal_tps} TPS",
+                self.name, self.goal_tps
+            ),
+            _ if new_goal_tps != self.goal_tps => info!(
+                "{}: {error_rate:.1}% errors at {} TPS; trying {new_goal_tps} TPS",
+                self.name, self.goal_tps
+            ),
+            _ => {}
+        }
+    }
+}
+
+impl Controller for ErrorRateController {
+    fn initial_tps(&self) -> NonZeroU32 {
+        BASE_TPS
+    }
+
+    fn analyze(&mut self, samples: &SampleSet, stable: bool) -> ControllerOutcome {
+        let Some(sample) = samples.latest() else {
+            return ControllerOutcome::Limit(self.goal_tps);
+        };
+        let error_rate = samples.recent_error_rate();
+        let bounds = self.check_bounds(error_rate);
+        let (new_goal_tps, new_state) = match self.search {
+            SearchStrategy::Bisect => self.bisect(bounds, sample),
+            SearchStrategy::Step => self.step(bounds, sample),
+        };
 
         if new_goal_tps < self.goal_tps || stable {
+            self.log_trial(error_rate, new_goal_tps, new_state);
             self.goal_tps = new_goal_tps;
             self.state = new_state;
         } else {
@@ -119,15 +217,18 @@ impl Controller for ErrorRateController {
         }
 
         if cfg!(feature = "metrics") {
-            metrics::gauge!(format!("{}_erc_goal_tps", &self.base_label)).set(self.goal_tps.get());
-            metrics::gauge!(format!("{}_erc_state", &self.base_label)).set(match self.state {
+            let (name, labels) = scenario_metric("balter_scenario_erc_goal_tps", &self.name);
+            metrics::gauge!(name, labels).set(self.goal_tps.get());
+            let (name, labels) = scenario_metric("balter_scenario_erc_state", &self.name);
+            metrics::gauge!(name, labels).set(match self.state {
+                State::Bisect { .. } => 3,
                 State::BigStep => 2,
                 State::SmallStep(_) => 1,
                 State::Stable => 0,
             });
         }
 
-        self.goal_tps
+        ControllerOutcome::Limit(self.goal_tps)
     }
 }
 
@@ -135,16 +236,173 @@ impl Controller for ErrorRateController {
 enum State {
     BigStep,
     SmallStep(f64),
+    Bisect { low: u32, high: u32 },
     Stable,
 }
 
+#[derive(Debug, Clone, Copy)]
 enum Bounds {
     Under,
     At,
     Over,
 }
 
-fn convert_to_nonzerou32(val: f64) -> Option<NonZeroU32> {
+fn scale(val: u32, ratio: f64) -> u32 {
+    (val as f64 * ratio) as u32
+}
+
+/// Convert a measured or computed TPS, clamping to at least 1 TPS. Measured TPS can fall below
+/// 1 on heavily constrained machines.
+fn convert_to_nonzerou32(val: f64) -> NonZeroU32 {
     let val = val as u32;
-    NonZeroU32::new(val)
+    NonZeroU32::new(val).unwrap_or(NonZeroU32::MIN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Mock service which starts erroring above 1,000 TPS
+    fn sample(tps: NonZeroU32, noise: f64) -> SampleSet {
+        sample_with_capacity(tps, 1_000, noise)
+    }
+
+    /// Mock service which starts erroring above `capacity` TPS
+    fn sample_with_capacity(tps: NonZeroU32, capacity: u64, noise: f64) -> SampleSet {
+        let tps = tps.get() as u64;
+        let error = tps.saturating_sub(capacity) as f64 / tps as f64;
+        let error = ((error + noise).clamp(0., 1.) * tps as f64) as u64;
+        let mut samples = SampleSet::new(1);
+        samples.push(
+            Measurement::new(tps - error, error, std::time::Duration::from_secs(1)),
+            std::time::Duration::ZERO,
+        );
+        samples
+    }
+
+    fn limit(controller: &mut ErrorRateController, samples: &SampleSet) -> NonZeroU32 {
+        match controller.analyze(samples, true) {
+            ControllerOutcome::Limit(tps) => tps,
+            ControllerOutcome::Stop => panic!("ErrorRateController should never stop"),
+        }
+    }
+
+    fn samples_to_converge(search: SearchStrategy) -> usize {
+        let mut controller = ErrorRateController::new("", 0.1, search);
+        let mut goal_tps = controller.initial_tps();
+        for i in 0..100 {
+            goal_tps = limit(&mut controller, &sample(goal_tps, 0.));
+            if matches!(controller.state, State::Stable) {
+                assert!(goal_tps.get() >= 1_000 && goal_tps.get() < 1_250);
+                return i;
+            }
+        }
+        panic!("{search:?} did not converge");
+    }
+
+    #[test]
+    fn test_bisect_converges_faster() {
+        let bisect = samples_to_converge(SearchStrategy::Bisect);
+        let step = samples_to_converge(SearchStrategy::Step);
+        assert!(bisect < step, "bisect: {bisect}, step: {step}");
+    }
+
+    #[test]
+    fn test_bisect_noise() {
+        let mut controller = ErrorRateController::new("", 0.1, SearchStrategy::Bisect);
+        let mut goal_tps = controller.initial_tps();
+        for i in 0..100 {
+            let noise = if i % 2 == 0 { 0.02 } else { -0.02 };
+            goal_tps = limit(&mut controller, &sample(goal_tps, noise));
+            if i > 20 {
+                assert!(goal_tps.get() >= 1_000 && goal_tps.get() < 1_250);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bisect_capacity_drop() {
+        let mut controller = ErrorRateController::new("", 0.1, SearchStrategy::Bisect);
+        let mut goal_tps = controller.initial_tps();
+        for _ in 0..30 {
+            goal_tps = limit(&mut controller, &sample(goal_tps, 0.));
+        }
+        assert!(goal_tps.get() >= 1_000 && goal_tps.get() < 1_250);
+
+        // The service degrades to half its capacity, both once the search has stabilized, and
+        // partway through a search.
+        for _ in 0..8 {
+            goal_tps = limit(&mut controller, &sample_with_capacity(goal_tps, 500, 0.));
+        }
+        assert!(goal_tps.get() >= 500 && goal_tps.get() < 625, "{goal_tps}");
+
+        let mut controller = ErrorRateController::new("", 0.1, SearchStrategy::Bisect);
+        let mut goal_tps = controller.initial_tps();
+        while !matches!(controller.state, State::Bisect { .. }) {
+            goal_tps = limit(&mut controller, &sample(goal_tps, 0.));
+        }
+        for _ in 0..8 {
+            goal_tps = limit(&mut controller, &sample_with_capacity(goal_tps, 500, 0.));
+        }
+        assert!(goal_tps.get() >= 500 && goal_tps.get() < 625, "{goal_tps}");
+    }
+
+    #[test]
+    fn test_error_window() {
+        let samples = |error_window| {
+            let mut samples = SampleSet::new(1).with_error_window(error_window);
+            for error in [0, 0, 200] {
+                samples.push(
+                    Measurement::new(1_000 - error, error, std::time::Duration::from_secs(1)),
+                    std::time::Duration::ZERO,
+                );
+            }
+            samples
+        };
+
+        // A single sample at a 20% error rate backs off...
+        let mut controller = ErrorRateController::new("", 0.1, SearchStrategy::Step);
+        assert_eq!(limit(&mut controller, &samples(1)).get(), 256);
+
+        // ...unless it is smoothed out over the error window
+        let mut controller = ErrorRateController::new("", 0.1, SearchStrategy::Step);
+        assert_eq!(limit(&mut controller, &samples(3)).get(), 1_024);
+    }
+
+    #[test]
+    fn test_step_clamps_to_one_tps() {
+        let measurement = |success, error, secs| {
+            let mut samples = SampleSet::new(1);
+            samples.push(
+                Measurement::new(success, error, std::time::Duration::from_secs(secs)),
+                std::time::Duration::ZERO,
+            );
+            samples
+        };
+
+        // Measured TPS below 1 at the goal error rate
+        let mut controller = ErrorRateController::new("", 0.1, SearchStrategy::Step);
+        assert_eq!(limit(&mut controller, &measurement(9, 1, 20)).get(), 1);
+
+        // Over the goal error rate at 1 TPS, halving the TPS
+        let mut controller = ErrorRateController::new("", 0.1, SearchStrategy::Step);
+        controller.goal_tps = NonZeroU32::MIN;
+        assert_eq!(limit(&mut controller, &measurement(0, 10, 1)).get(), 1);
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_search_trajectory() {
+        let mut controller = ErrorRateController::new("search", 0.1, SearchStrategy::Bisect);
+        let mut goal_tps = controller.initial_tps();
+        for _ in 0..30 {
+            goal_tps = limit(&mut controller, &sample(goal_tps, 0.));
+        }
+
+        assert!(logs_contain(
+            "search: 0.0% errors at 512 TPS; trying 1024 TPS"
+        ));
+        assert!(logs_contain("errors at 2048 TPS; trying 1536 TPS"));
+        assert!(logs_contain(&format!("settled on {goal_tps} TPS")));
+    }
 }
@@ -1,5 +1,6 @@
-use crate::controllers::Controller;
-use crate::measurement::Measurement;
+use crate::controllers::{Controller, ControllerOutcome};
+use crate::metric_keys::scenario_metric;
+use crate::sample_set::SampleSet;
 use balter_core::BASE_TPS;
 use std::num::NonZeroU32;
 use std::time::Duration;
@@ -10,7 +11,7 @@ const KP: f64 = 0.9;
 
 #[allow(unused)]
 pub(crate) struct LatencyController {
-    base_label: String,
+    name: String,
     latency: Duration,
     quantile: f64,
     goal_tps: NonZeroU32,
@@ -19,7 +20,7 @@ pub(crate) struct LatencyController {
 impl LatencyController {
     pub fn new(name: &str, latency: Duration, quantile: f64) -> Self {
         let s = Self {
-            base_label: format!("balter_{name}"),
+            name: name.to_string(),
             latency,
             quantile,
             goal_tps: BASE_TPS,
@@ -30,7 +31,8 @@ impl LatencyController {
 
     fn goal_tps_metric(&self) {
         if cfg!(feature = "metrics") {
-            metrics::gauge!(format!("{}_lc_goal_tps", &self.base_label)).set(self.goal_tps.get());
+            let (name, labels) = scenario_metric("balter_scenario_lc_goal_tps", &self.name);
+            metrics::gauge!(name, labels).set(self.goal_tps.get());
         }
     }
 }
@@ -40,7 +42,10 @@ impl Controller for LatencyController {
         BASE_TPS
     }
 
-    fn limit(&mut self, sample: &Measurement, stable: bool) -> NonZeroU32 {
+    fn analyze(&mut self, samples: &SampleSet, stable: bool) -> ControllerOutcome {
+        let Some(sample) = samples.latest() else {
+            return ControllerOutcome::Limit(self.goal_tps);
+        };
         let measured_latency = sample.latency(self.quantile);
 
         trace!("LATENCY: Measured {measured_latency:?}");
@@ -63,6 +68,6 @@ impl Controller for LatencyController {
             error!("Error in the LatencyController. Calculated a goal_tps of {new_goal} which is invalid.");
         }
 
-        self.goal_tps
+        ControllerOutcome::Limit(self.goal_tps)
     }
 }
@@ -0,0 +1,158 @@
+use crate::controllers::{Controller, ControllerOutcome};
+use crate::metric_keys::scenario_metric;
+use crate::sample_set::SampleSet;
+use balter_core::BASE_TPS;
+use std::num::NonZeroU32;
+#[allow(unused)]
+use tracing::{debug, error, info, trace};
+
+/// Error rate above the baseline at which errors are considered to be climbing
+const ONSET_TOLERANCE: f64 = 0.01;
+/// The search stops once the bracket is within this fraction of the upper bound
+const ONSET_RESOLUTION: f64 = 0.02;
+
+/// Searches for the onset of overload: the highest TPS before the error rate starts climbing
+/// above the error rate measured at the initial TPS.
+pub(crate) struct OnsetController {
+    name: String,
+    goal_tps: NonZeroU32,
+    baseline: Option<f64>,
+    state: State,
+}
+
+impl OnsetController {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            goal_tps: BASE_TPS,
+            baseline: None,
+            state: State::Doubling,
+        }
+    }
+
+    /// TPS at which the error rate started climbing, once found
+    pub fn onset_tps(&self) -> Option<u32> {
+        match self.state {
+            State::Found => Some(self.goal_tps.get()),
+            _ => None,
+        }
+    }
+
+    fn search(&self, climbing: bool) -> (NonZeroU32, State) {
+        let goal = self.goal_tps.get();
+        let (low, high) = match (self.state, climbing) {
+            (State::Doubling, false) => {
+                trace!("Under onset w/ Doubling.");
+                return (
+                    NonZeroU32::new(goal.saturating_mul(2)).unwrap(),
+                    State::Doubling,
+                );
+            }
+            (State::Doubling, true) => (goal / 2, goal),
+            (State::Bisect { high, .. }, false) => (goal, high),
+            (State::Bisect { low, .. }, true) => (low, goal),
+            (State::Found, _) => return (self.goal_tps, State::Found),
+        };
+        trace!("Bisecting onset between {low} and {high}");
+
+        if (high - low) as f64 <= (high as f64 * ONSET_RESOLUTION).max(1.) {
+            info!("Onset of overload for {} found at {low} TPS", self.name);
+            return (
+                NonZeroU32::new(low).unwrap_or(NonZeroU32::MIN),
+                State::Found,
+            );
+        }
+
+        let mid = NonZeroU32::new(low + (high - low) / 2).unwrap_or(NonZeroU32::MIN);
+        (mid, State::Bisect { low, high })
+    }
+}
+
+impl Controller for OnsetController {
+    fn initial_tps(&self) -> NonZeroU32 {
+        BASE_TPS
+    }
+
+    fn analyze(&mut self, samples: &SampleSet, stable: bool) -> ControllerOutcome {
+        if samples.latest().is_none() || matches!(self.state, State::Found) {
+            return ControllerOutcome::Limit(self.goal_tps);
+        }
+        let error_rate = samples.recent_error_rate();
+
+        // NOTE: Some services error at a low rate regardless of load, so errors only count as
+        // climbing relative to those measured at the initial TPS.
+        let baseline = match self.baseline {
+            Some(baseline) => baseline,
+            None if stable => *self.baseline.insert(error_rate),
+            None => return ControllerOutcome::Limit(self.goal_tps),
+        };
+        let climbing = error_rate > baseline + ONSET_TOLERANCE;
+
+        if climbing || stable {
+            (self.goal_tps, self.state) = self.search(climbing);
+        } else {
+            debug!("TPS not stabalized; holding off on increasing Goal TPS");
+        }
+
+        if cfg!(feature = "metrics") {
+            let (name, labels) = scenario_metric("balter_scenario_oc_goal_tps", &self.name);
+            metrics::gauge!(name, labels).set(self.goal_tps.get());
+        }
+
+        ControllerOutcome::Limit(self.goal_tps)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Doubling,
+    Bisect { low: u32, high: u32 },
+    Found,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::Measurement;
+    use std::time::Duration;
+
+    /// Mock service with a 0.5% background error rate, which starts erroring above 1,000 TPS
+    fn sample(tps: NonZeroU32) -> SampleSet {
+        let tps = tps.get() as u64;
+        let error = (tps.saturating_sub(1_000) + tps / 200).min(tps);
+        let mut samples = SampleSet::new(1);
+        samples.push(
+            Measurement::new(tps - error, error, Duration::from_secs(1)),
+            Duration::ZERO,
+        );
+        samples
+    }
+
+    #[test]
+    fn test_onset() {
+        let mut controller = OnsetController::new("test");
+        let mut goal_tps = controller.initial_tps();
+        for _ in 0..30 {
+            match controller.analyze(&sample(goal_tps), true) {
+                ControllerOutcome::Limit(tps) => goal_tps = tps,
+                ControllerOutcome::Stop => panic!("Onset search stopped"),
+            }
+        }
+
+        let onset = controller.onset_tps().unwrap();
+        assert!(onset <= 1_010, "{onset}");
+        assert!(onset >= 980, "{onset}");
+        assert_eq!(goal_tps.get(), onset);
+    }
+
+    #[test]
+    fn test_onset_unstable() {
+        let mut controller = OnsetController::new("test");
+        let goal_tps = controller.initial_tps();
+        assert_eq!(
+            controller.analyze(&sample(goal_tps), false),
+            ControllerOutcome::Limit(goal_tps)
+        );
+        assert_eq!(controller.onset_tps(), None);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::controllers::{Controller, ControllerOutcome};
+use crate::metric_keys::scenario_metric;
+use crate::sample_set::SampleSet;
+use std::num::NonZeroU32;
+use std::time::{Duration, Instant};
+#[allow(unused)]
+use tracing::{debug, error, trace, warn};
+
+pub(crate) struct RampController {
+    name: String,
+    start: NonZeroU32,
+    end: NonZeroU32,
+    duration: Option<Duration>,
+    started: Instant,
+}
+
+impl RampController {
+    pub fn new(name: &str, start: NonZeroU32, end: NonZeroU32, duration: Option<Duration>) -> Self {
+        if duration.is_none() {
+            warn!("Ramping TPS without a duration; holding at the starting TPS of {start}.");
+        }
+
+        let s = Self {
+            name: name.to_string(),
+            start,
+            end,
+            duration,
+            started: Instant::now(),
+        };
+        s.goal_tps_metric(start);
+        s
+    }
+
+    /// Goal TPS at the given point in the run, linearly interpolated between `start` and `end`.
+    fn goal_at(&self, elapsed: Duration) -> NonZeroU32 {
+        let Some(duration) = self.duration else {
+            return self.start;
+        };
+
+        // NOTE: With very short durations we may only get a sample or two, so we clamp to the end
+        // of the ramp rather than overshooting it.
+        let progress = if duration.is_zero() {
+            1.
+        } else {
+            (elapsed.as_secs_f64() / duration.as_secs_f64()).min(1.)
+        };
+
+        interpolate(self.start, self.end, progress)
+    }
+
+    fn goal_tps_metric(&self, goal_tps: NonZeroU32) {
+        if cfg!(feature = "metrics") {
+            let (name, labels) = scenario_metric("balter_scenario_rc_goal_tps", &self.name);
+            metrics::gauge!(name, labels).set(goal_tps.get());
+        }
+    }
+}
+
+impl Controller for RampController {
+    fn initial_tps(&self) -> NonZeroU32 {
+        self.start
+    }
+
+    fn analyze(&mut self, _samples: &SampleSet, _stable: bool) -> ControllerOutcome {
+        let goal_tps = self.goal_at(self.started.elapsed());
+        trace!("RAMP: Goal {goal_tps}");
+        self.goal_tps_metric(goal_tps);
+        ControllerOutcome::Limit(goal_tps)
+    }
+}
+
+/// Linear interpolation between two TPS values, where `progress` is in `[0, 1]`. Works for both
+/// ramping up and ramping down.
+pub(crate) fn interpolate(start: NonZeroU32, end: NonZeroU32, progress: f64) -> NonZeroU32 {
+    let (start, end) = (start.get() as f64, end.get() as f64);
+    let tps = start + (end - start) * progress.clamp(0., 1.);
+    NonZeroU32::new(tps.round() as u32).unwrap_or(NonZeroU32::MIN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tps(val: u32) -> NonZeroU32 {
+        NonZeroU32::new(val).unwrap()
+    }
+
+    #[test]
+    fn test_ramp_up() {
+        let controller =
+            RampController::new("", tps(100), tps(1_100), Some(Duration::from_secs(10)));
+
+        assert_eq!(controller.goal_at(Duration::ZERO), tps(100));
+        assert_eq!(controller.goal_at(Duration::from_secs(5)), tps(600));
+        assert_eq!(controller.goal_at(Duration::from_secs(10)), tps(1_100));
+        assert_eq!(controller.goal_at(Duration::from_secs(20)), tps(1_100));
+    }
+
+    #[test]
+    fn test_ramp_down() {
+        let controller =
+            RampController::new("", tps(1_000), tps(10), Some(Duration::from_secs(100)));
+
+        assert_eq!(controller.goal_at(Duration::ZERO), tps(1_000));
+        assert_eq!(controller.goal_at(Duration::from_secs(50)), tps(505));
+        assert_eq!(controller.goal_at(Duration::from_secs(100)), tps(10));
+    }
+
+    #[test]
+    fn test_short_duration() {
+        let controller =
+            RampController::new("", tps(100), tps(200), Some(Duration::from_millis(10)));
+
+        // Only a single sample tick happens after the duration has passed
+        assert_eq!(controller.goal_at(Duration::from_secs(1)), tps(200));
+
+        let controller = RampController::new("", tps(100), tps(200), Some(Duration::ZERO));
+        assert_eq!(controller.goal_at(Duration::ZERO), tps(200));
+    }
+
+    #[test]
+    fn test_no_duration() {
+        let controller = RampController::new("", tps(100), tps(200), None);
+        assert_eq!(controller.goal_at(Duration::from_secs(60)), tps(100));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::controllers::{ramp::interpolate, Controller, ControllerOutcome};
+use crate::metric_keys::scenario_metric;
+use crate::sample_set::SampleSet;
+use balter_core::Stage;
+use std::num::NonZeroU32;
+use std::time::{Duration, Instant};
+#[allow(unused)]
+use tracing::{debug, error, trace, warn};
+
+pub(crate) struct StagedController {
+    name: String,
+    stages: Vec<(NonZeroU32, Duration)>,
+    started: Instant,
+}
+
+impl StagedController {
+    pub fn new(name: &str, stages: &[Stage]) -> Self {
+        let stages: Vec<_> = stages
+            .iter()
+            .map(|stage| {
+                (
+                    NonZeroU32::new(stage.target_tps).unwrap_or(NonZeroU32::MIN),
+                    stage.duration,
+                )
+            })
+            .collect();
+
+        if stages.is_empty() {
+            error!("No stages provided to the StagedController.");
+        }
+
+        let s = Self {
+            name: name.to_string(),
+            stages,
+            started: Instant::now(),
+        };
+        s.goal_tps_metric(s.initial_tps());
+        s
+    }
+
+    /// Goal TPS at the given point in the run. Each stage ramps linearly from the previous
+    /// stage's target to its own, and the final target is held once all stages are complete.
+    fn goal_at(&self, elapsed: Duration) -> NonZeroU32 {
+        let mut stage_start = Duration::ZERO;
+        let mut prev_tps = self.initial_tps();
+
+        for &(target_tps, duration) in &self.stages {
+            let stage_end = stage_start + duration;
+            if elapsed < stage_end {
+                let progress = (elapsed - stage_start).as_secs_f64() / duration.as_secs_f64();
+                return interpolate(prev_tps, target_tps, progress);
+            }
+
+            stage_start = stage_end;
+            prev_tps = target_tps;
+        }
+
+        prev_tps
+    }
+
+    fn goal_tps_metric(&self, goal_tps: NonZeroU32) {
+        if cfg!(feature = "metrics") {
+            let (name, labels) = scenario_metric("balter_scenario_sc_goal_tps", &self.name);
+            metrics::gauge!(name, labels).set(goal_tps.get());
+        }
+    }
+}
+
+impl Controller for StagedController {
+    fn initial_tps(&self) -> NonZeroU32 {
+        self.stages
+            .first()
+            .map(|(tps, _)| *tps)
+            .unwrap_or(NonZeroU32::MIN)
+    }
+
+    fn analyze(&mut self, _samples: &SampleSet, _stable: bool) -> ControllerOutcome {
+        let goal_tps = self.goal_at(self.started.elapsed());
+        trace!("STAGED: Goal {goal_tps}");
+        self.goal_tps_metric(goal_tps);
+        ControllerOutcome::Limit(goal_tps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tps(val: u32) -> NonZeroU32 {
+        NonZeroU32::new(val).unwrap()
+    }
+
+    #[test]
+    fn test_stages() {
+        let controller = StagedController::new(
+            "",
+            &[
+                Stage::new(100, Duration::from_secs(120)),
+                Stage::new(1_000, Duration::from_secs(300)),
+                Stage::new(1_000, Duration::from_secs(180)),
+                Stage::new(200, Duration::from_secs(100)),
+            ],
+        );
+
+        // First stage holds its target
+        assert_eq!(controller.goal_at(Duration::ZERO), tps(100));
+        assert_eq!(controller.goal_at(Duration::from_secs(119)), tps(100));
+
+        // Second stage ramps up
+        assert_eq!(controller.goal_at(Duration::from_secs(120)), tps(100));
+        assert_eq!(controller.goal_at(Duration::from_secs(270)), tps(550));
+
+        // Third stage holds
+        assert_eq!(controller.goal_at(Duration::from_secs(420)), tps(1_000));
+        assert_eq!(controller.goal_at(Duration::from_secs(599)), tps(1_000));
+
+        // Fourth stage ramps down
+        assert_eq!(controller.goal_at(Duration::from_secs(650)), tps(600));
+
+        // After all stages, the final target is held
+        assert_eq!(controller.goal_at(Duration::from_secs(1_000)), tps(200));
+    }
+}
@@ -0,0 +1,80 @@
+//! HTTP transactions with [reqwest] (requires `http` feature)
+use crate::transaction::{record_status, transaction_hook, TransactionError};
+use balter_core::{ErrorKind, TransactionLabels};
+use reqwest::{Client, Request, Response};
+
+const LABELS: TransactionLabels = TransactionLabels {
+    name: "http_request",
+    success: "balter_transaction_http_request_success",
+    error: "balter_transaction_http_request_error",
+    latency: "balter_transaction_http_request_latency",
+    retries: "balter_transaction_http_request_retries",
+};
+const STATUS: &str = "balter_transaction_http_request_status";
+
+/// Send an HTTP request as a Transaction.
+///
+/// Responses with a 4xx or 5xx status are returned as errors, and counted as
+/// [ErrorKind::Client] and [ErrorKind::Server] respectively. Timeouts and connection failures are
+/// counted as [ErrorKind::Timeout] and [ErrorKind::Connection]. Any other response is a success.
+/// The status code of every response is counted in
+/// [RunStatistics::status_codes](balter_core::RunStatistics::status_codes), and in the
+/// `balter_transaction_status` metric labeled with `status="{code}"`.
+///
+/// This is equivalent to a `#[transaction]` function which sends the request and checks the
+/// status of the response, with metrics labeled `transaction="http_request"`. Wrap the
+/// call in your own `#[transaction]` if you need metrics for each endpoint.
+///
+/// # Example
+/// ```no_run
+/// use balter::prelude::*;
+/// use reqwest::Client;
+///
+/// #[scenario]
+/// async fn my_scenario() {
+///     let client = Client::new();
+///     loop {
+///         let req = client.get("http://localhost:3000/").build().unwrap();
+///         let _ = balter::http::request(&client, req).await;
+///     }
+/// }
+/// ```
+pub async fn request(client: &Client, request: Request) -> Result<Response, reqwest::Error> {
+    transaction_hook(
+        LABELS,
+        async move {
+            let response = client.execute(request).await?;
+            record_status(LABELS, STATUS, response.status().as_u16());
+            response.error_for_status()
+        },
+        TransactionError::kind,
+        || None,
+    )
+    .await
+}
+
+impl TransactionError for reqwest::Error {
+    fn kind(&self) -> ErrorKind {
+        match self.status() {
+            Some(status) if status.is_server_error() => ErrorKind::Server,
+            Some(status) if status.is_client_error() => ErrorKind::Client,
+            _ if self.is_timeout() => ErrorKind::Timeout,
+            _ if self.is_connect() => ErrorKind::Connection,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_connection_error() {
+        let client = Client::new();
+        // NOTE: Nothing listens on port 1
+        let req = client.get("http://127.0.0.1:1/").build().unwrap();
+        let err = request(&client, req).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Connection);
+    }
+}
@@ -33,24 +33,46 @@
 //!     Ok(0)
 //! }
 //! ```
+#[cfg(feature = "http")]
+#[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+pub mod http;
+pub mod metrics;
 pub mod scenario;
+pub mod suite;
+pub mod testing;
+#[cfg(feature = "tower")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tower")))]
+pub mod tower;
 #[doc(hidden)]
 pub mod transaction;
+pub mod weighted;
 
 mod hints;
+mod rng;
+mod state;
 
 #[macro_use]
 #[doc(hidden)]
 pub mod macros;
 
-pub(crate) mod controllers;
+mod controllers;
 pub(crate) mod measurement;
+mod metric_keys;
+mod recorder;
+mod sample_set;
 pub(crate) mod sampler;
 
 #[cfg(not(feature = "rt"))]
 pub use balter_macros::{scenario, transaction};
+pub use controllers::{Controller, ControllerOutcome};
 pub use hints::Hint;
-pub use scenario::Scenario;
+pub use measurement::Measurement;
+pub use rng::{rng, TaskRng};
+pub use sample_set::SampleSet;
+pub use scenario::{Scenario, ScenarioFn, ScenarioHandle};
+pub use state::{args, state};
+pub use suite::Suite;
+pub use transaction::TransactionError;
 
 cfg_rt! {
     pub use balter_runtime::runtime::{self, BalterRuntime};
@@ -62,10 +84,59 @@ pub mod core {
     pub use balter_core::*;
 }
 
-pub use core::RunStatistics;
+pub use core::{
+    Arrival, ConcurrencySummary, ControllerKind, ErrorCounts, ErrorKind, LiveStats, PidGains,
+    RetryPolicy, RunStatistics, RuntimeConfig, SearchStrategy, Stage, StopReason, TpsTarget,
+};
 
+/// Types describing the results of a Scenario, for inspecting them after a run or from
+/// [on_sample](crate::scenario::ConfigurableScenario::on_sample).
+///
+/// These are also exported from the crate root and the [prelude].
+///
+/// # Example
+/// ```no_run
+/// use balter::prelude::*;
+/// use balter::stats::{RunStatistics, StopReason};
+///
+/// fn report(stats: &RunStatistics) {
+///     if stats.stop_reason.is_abort() {
+///         println!("Aborted: {:?}", stats.stop_reason);
+///     }
+///     println!("{} TPS with {} errors", stats.actual_tps, stats.errors.total());
+/// }
+/// ```
+pub mod stats {
+    pub use crate::measurement::Measurement;
+    pub use crate::sample_set::SampleSet;
+    pub use balter_core::{
+        ConcurrencySummary, ErrorCounts, ErrorKind, LatencyHistogram, LiveStats, RunStatistics,
+        StopReason,
+    };
+}
+
+/// Everything needed to write and run Scenarios, and to inspect their results.
+///
+/// # Example
+/// ```no_run
+/// use balter::prelude::*;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let stats: RunStatistics = my_scenario().tps(100).await;
+///     println!("p99 latency: {:?}", stats.latency_p99);
+/// }
+///
+/// #[scenario]
+/// async fn my_scenario() {
+/// }
+/// ```
 pub mod prelude {
+    pub use crate::controllers::{Controller, ControllerOutcome};
     pub use crate::scenario::ConfigurableScenario;
+    pub use crate::suite::Suite;
+    pub use crate::transaction::TransactionError;
+    pub use crate::weighted;
     cfg_rt! {
         pub use balter_runtime::runtime::{distributed_slice, BalterRuntime};
         pub use balter_runtime::traits::DistributedScenario;
@@ -75,5 +146,9 @@ pub mod prelude {
     #[cfg(not(feature = "rt"))]
     pub use balter_macros::{scenario, transaction};
 
-    pub use balter_core::RunStatistics;
+    pub use crate::stats::*;
+    pub use balter_core::{
+        Arrival, ControllerKind, PidGains, RetryPolicy, RuntimeConfig, SearchStrategy, Stage,
+        TpsTarget,
+    };
 }
@@ -1,49 +1,125 @@
-use pdatastructs::tdigest::{TDigest, K1};
+use balter_core::{LatencyHistogram, TpsTarget};
 use std::fmt;
-use std::time::Duration;
-use tracing::error;
+use std::time::{Duration, SystemTime};
 
-const TDIGEST_BACKLOG_SIZE: usize = 100;
-
-#[allow(unused)]
-#[derive(Debug, Clone)]
+/// Transactions measured over a single sample of a running Scenario.
+///
+/// The samples currently in a [SampleSet](crate::SampleSet) can be iterated over with
+/// [SampleSet::iter](crate::SampleSet::iter), for stop conditions and callbacks which need more
+/// than the statistics it aggregates.
+#[derive(Clone)]
 pub struct Measurement {
+    /// Throughput over the sample in the units targeted by `.tps()`: equal to
+    /// `transaction_tps` by default, or to `scenario_tps` with
+    /// `.tps_target(TpsTarget::Scenarios)`
     pub tps: f64,
+    /// Successful transactions per second over the sample
+    pub transaction_tps: f64,
+    /// Completed runs of the Scenario per second over the sample
+    pub scenario_tps: f64,
+    /// Fraction of transactions in the sample which failed
     pub error_rate: f64,
+    /// Length of the sample
     pub elapsed: Duration,
-    latency: TDigest<K1>,
+    /// Number of successful transactions in the sample
+    pub success: u64,
+    /// Number of failed transactions in the sample
+    pub error: u64,
+    /// Number of runs of the Scenario completed in the sample
+    pub scenarios: u64,
+    /// Wall-clock time at which the measurement was taken, for correlating with external logs
+    /// and metrics. For merged measurements, this is the time of the latest one.
+    pub timestamp: SystemTime,
+    target: TpsTarget,
+    histogram: LatencyHistogram,
 }
 
 impl Measurement {
-    pub fn new(success: u64, error: u64, elapsed: Duration) -> Self {
-        let tps = success as f64 / elapsed.as_secs_f64();
-        let error_rate = error as f64 / (success + error) as f64;
-        Self {
-            tps,
-            error_rate,
+    pub(crate) fn new(success: u64, error: u64, elapsed: Duration) -> Self {
+        let mut measurement = Self {
+            tps: 0.,
+            transaction_tps: 0.,
+            scenario_tps: 0.,
+            error_rate: 0.,
             elapsed,
-            latency: default_tdigest(),
-        }
+            success,
+            error,
+            scenarios: 0,
+            timestamp: SystemTime::now(),
+            target: TpsTarget::default(),
+            histogram: LatencyHistogram::new(),
+        };
+        measurement.update_rates();
+        measurement
     }
 
-    pub fn populate_latencies(&mut self, dur: &[Duration]) {
+    /// Record the runs of the Scenario completed in the sample, and which throughput `tps`
+    /// should report
+    pub(crate) fn set_scenarios(&mut self, scenarios: u64, target: TpsTarget) {
+        self.scenarios = scenarios;
+        self.target = target;
+        self.update_rates();
+    }
+
+    pub(crate) fn populate_latencies(&mut self, dur: &[Duration]) {
         for latency in dur {
-            self.latency.insert(latency.as_secs_f64());
+            self.histogram.record(*latency);
         }
     }
 
+    /// Combine another Measurement into this one, as if they were measured over a single
+    /// (longer) interval.
+    pub(crate) fn merge(&mut self, other: &Measurement) {
+        self.success += other.success;
+        self.error += other.error;
+        self.scenarios += other.scenarios;
+        self.elapsed += other.elapsed;
+        self.timestamp = self.timestamp.max(other.timestamp);
+        self.histogram.merge(&other.histogram);
+        self.update_rates();
+    }
+
+    /// Latency at the given quantile (between 0 and 1) across the transactions in the sample,
+    /// within 1% of the true latency
     pub fn latency(&self, quantile: f64) -> Duration {
-        let secs = self.latency.quantile(quantile);
+        self.histogram.quantile(quantile)
+    }
 
-        // TODO: Unfortunately TDigest sometimes returns NaN which we need to filter for.
-        let secs = if secs.is_finite() {
-            secs
-        } else {
-            error!("NaN Latency Calculation. This is a known bug in Balter.");
+    /// Histogram of the latencies of the transactions in the sample, which
+    /// [latency](Measurement::latency) reads its quantiles from
+    pub fn latency_histogram(&self) -> &LatencyHistogram {
+        &self.histogram
+    }
+
+    fn update_rates(&mut self) {
+        self.transaction_tps = self.success as f64 / self.elapsed.as_secs_f64();
+        self.scenario_tps = self.scenarios as f64 / self.elapsed.as_secs_f64();
+        self.tps = match self.target {
+            TpsTarget::Transactions => self.transaction_tps,
+            TpsTarget::Scenarios => self.scenario_tps,
+        };
+        // NOTE: At very low rates a sample may not contain any transactions, which counts as
+        // error-free rather than as a NaN error rate.
+        let total = self.success + self.error;
+        self.error_rate = if total == 0 {
             0.
+        } else {
+            self.error as f64 / total as f64
         };
+    }
+}
 
-        Duration::from_secs_f64(secs)
+impl fmt::Debug for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Measurement")
+            .field("tps", &self.tps)
+            .field("error_rate", &self.error_rate)
+            .field("elapsed", &self.elapsed)
+            .field("success", &self.success)
+            .field("error", &self.error)
+            .field("scenarios", &self.scenarios)
+            .field("timestamp", &self.timestamp)
+            .finish_non_exhaustive()
     }
 }
 
@@ -61,7 +137,42 @@ impl fmt::Display for Measurement {
     }
 }
 
-fn default_tdigest() -> TDigest<K1> {
-    // TODO: Double-check these values
-    TDigest::new(K1::new(10.), TDIGEST_BACKLOG_SIZE)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let mut a = Measurement::new(90, 10, Duration::from_secs(1));
+        a.populate_latencies(&[Duration::from_millis(10); 100]);
+
+        let mut b = Measurement::new(300, 0, Duration::from_secs(1));
+        b.populate_latencies(&[Duration::from_millis(50); 300]);
+
+        a.merge(&b);
+        assert_eq!(a.elapsed, Duration::from_secs(2));
+        assert_eq!(a.timestamp, b.timestamp);
+        assert_eq!(a.tps, 195.);
+        assert_eq!(a.error_rate, 10. / 400.);
+
+        let p50 = a.latency(0.5).as_secs_f64();
+        assert!((p50 - 0.05).abs() < 0.001);
+        let max = a.latency(1.).as_secs_f64();
+        assert!((max - 0.05).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_tps_target() {
+        let mut a = Measurement::new(100, 0, Duration::from_secs(1));
+        a.set_scenarios(20, TpsTarget::Scenarios);
+        assert_eq!(a.tps, 20.);
+        assert_eq!(a.transaction_tps, 100.);
+
+        let mut b = Measurement::new(100, 0, Duration::from_secs(1));
+        b.set_scenarios(40, TpsTarget::Scenarios);
+        a.merge(&b);
+        assert_eq!(a.scenarios, 60);
+        assert_eq!(a.tps, 30.);
+        assert_eq!(a.transaction_tps, 100.);
+    }
 }
@@ -0,0 +1,134 @@
+//! Names and labels of the metrics Balter emits.
+//!
+//! Metrics have fixed names, and are labeled with the `scenario` (and for transaction metrics,
+//! the `transaction`) they belong to. Under the distributed runtime they are also labeled with
+//! the `server` ID. The `legacy-metric-names` feature restores the old naming, in which the
+//! scenario or transaction name is part of the metric name instead.
+//!
+//! Labels set on the scenario with `.label()` are attached to every metric, regardless of the
+//! naming.
+use crate::metrics::current_labels;
+use balter_core::TransactionLabels;
+use metrics::{KeyName, Label};
+use std::sync::Arc;
+
+const SCENARIO_PREFIX: &str = "balter_scenario_";
+
+/// Labels set by Balter itself, which can't be set with `.label()`
+pub(crate) const RESERVED_LABELS: [&str; 5] =
+    ["scenario", "transaction", "server", "status", "kind"];
+
+/// Key of a metric about the scenario, where `name` is of the form `balter_scenario_{metric}`
+/// (`balter_{scenario}_{metric}` with legacy naming).
+pub(crate) fn scenario_metric(name: &'static str, scenario: &str) -> (KeyName, Vec<Label>) {
+    if cfg!(feature = "legacy-metric-names") {
+        let metric = name.strip_prefix(SCENARIO_PREFIX).unwrap_or(name);
+        return (
+            format!("balter_{scenario}_{metric}").into(),
+            custom_labels(),
+        );
+    }
+
+    let mut labels = vec![Label::new("scenario", scenario.to_string())];
+    labels.extend(server_label());
+    labels.extend(custom_labels());
+    (name.into(), labels)
+}
+
+/// Key of a metric about a transaction, where `name` is of the form
+/// `balter_transaction_{metric}` and `legacy_name` is the matching name from the
+/// [TransactionLabels].
+pub(crate) fn transaction_metric(
+    name: &'static str,
+    legacy_name: &'static str,
+    transaction: &TransactionLabels,
+    scenario: &Arc<str>,
+) -> (KeyName, Vec<Label>) {
+    let mut labels = vec![Label::new("scenario", scenario.clone())];
+    if cfg!(feature = "legacy-metric-names") {
+        labels.extend(custom_labels());
+        return (legacy_name.into(), labels);
+    }
+
+    labels.push(Label::new("transaction", transaction.name));
+    labels.extend(server_label());
+    labels.extend(custom_labels());
+    (name.into(), labels)
+}
+
+/// Key of a metric emitted by the user with [record](crate::metrics::record) or
+/// [increment](crate::metrics::increment), labeled with the `scenario` it was emitted from (if
+/// any). User metrics are always labeled, regardless of `legacy-metric-names`.
+pub(crate) fn user_metric(name: &'static str, scenario: Option<Arc<str>>) -> (KeyName, Vec<Label>) {
+    let mut labels: Vec<Label> = scenario
+        .map(|scenario| Label::new("scenario", scenario))
+        .into_iter()
+        .collect();
+    labels.extend(server_label());
+    labels.extend(custom_labels());
+    (name.into(), labels)
+}
+
+fn custom_labels() -> Vec<Label> {
+    current_labels().map_or_else(Vec::new, |labels| labels.to_vec())
+}
+
+#[cfg(feature = "rt")]
+fn server_label() -> Option<Label> {
+    static SERVER: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+    if SERVER.get().is_none() {
+        let server_id = balter_runtime::runtime::server_id()?;
+        let _ = SERVER.set(server_id.to_string());
+    }
+    SERVER.get().map(|id| Label::new("server", id.as_str()))
+}
+
+#[cfg(not(feature = "rt"))]
+fn server_label() -> Option<Label> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metric_keys() {
+        let (name, labels) = scenario_metric("balter_scenario_goal_tps", "my-scenario");
+        if cfg!(feature = "legacy-metric-names") {
+            assert_eq!(name.as_str(), "balter_my-scenario_goal_tps");
+            assert!(labels.is_empty());
+        } else {
+            assert_eq!(name.as_str(), "balter_scenario_goal_tps");
+            assert_eq!(labels[0], Label::new("scenario", "my-scenario"));
+        }
+
+        let transaction = TransactionLabels {
+            name: "my_transaction",
+            success: "balter_transaction_my_transaction_success",
+            error: "balter_transaction_my_transaction_error",
+            latency: "balter_transaction_my_transaction_latency",
+            retries: "balter_transaction_my_transaction_retries",
+        };
+        let (name, labels) = transaction_metric(
+            "balter_transaction_success",
+            transaction.success,
+            &transaction,
+            &Arc::from("my-scenario"),
+        );
+        if cfg!(feature = "legacy-metric-names") {
+            assert_eq!(name.as_str(), transaction.success);
+        } else {
+            assert_eq!(name.as_str(), "balter_transaction_success");
+            assert!(labels.contains(&Label::new("transaction", "my_transaction")));
+        }
+        assert_eq!(labels[0], Label::new("scenario", "my-scenario"));
+
+        let (name, labels) = user_metric("payload_bytes", Some(Arc::from("my-scenario")));
+        assert_eq!(name.as_str(), "payload_bytes");
+        assert_eq!(labels[0], Label::new("scenario", "my-scenario"));
+        let (_, labels) = user_metric("payload_bytes", None);
+        assert!(!labels.contains(&Label::new("scenario", "my-scenario")));
+    }
+}
@@ -0,0 +1,374 @@
+//! Custom metrics emitted from within a Scenario.
+//!
+//! Metrics are emitted via the [`metrics` crate](https://docs.rs/metrics), alongside Balter's own
+//! metrics, and are labeled with `scenario="{scenario}"` of the Scenario they were emitted from.
+//! Like Balter's own metrics, they are discarded if no recorder is installed or the `metrics`
+//! feature is disabled.
+//!
+//! To keep a Scenario's metrics apart from those of others in the same process, route them to
+//! a recorder of its own with
+//! [metrics_recorder](crate::scenario::ConfigurableScenario::metrics_recorder).
+use crate::metric_keys::user_metric;
+use crate::transaction::TRANSACTION_HOOK;
+use ::metrics::{Key, KeyName, Label};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::task::{Context, Poll};
+
+/// Record a value of the histogram `name`, labeled with the current Scenario.
+///
+/// # Example
+/// ```no_run
+/// use balter::prelude::*;
+///
+/// #[scenario]
+/// async fn my_scenario() {
+///     if let Ok(payload) = my_transaction().await {
+///         balter::metrics::record("payload_bytes", payload.len() as f64);
+///     }
+/// }
+///
+/// #[transaction]
+/// async fn my_transaction() -> Result<Vec<u8>, String> {
+///     Ok(vec![])
+/// }
+/// ```
+pub fn record(name: &'static str, value: f64) {
+    if cfg!(feature = "metrics") {
+        let (name, labels) = user_metric(name, current_scenario());
+        ::metrics::histogram!(name, labels).record(value);
+    }
+}
+
+/// Increment the counter `name` by `value`, labeled with the current Scenario.
+///
+/// # Example
+/// ```no_run
+/// use balter::prelude::*;
+///
+/// #[scenario]
+/// async fn my_scenario() {
+///     if my_transaction().await.is_ok() {
+///         balter::metrics::increment("orders_placed", 1);
+///     }
+/// }
+///
+/// #[transaction]
+/// async fn my_transaction() -> Result<(), String> {
+///     Ok(())
+/// }
+/// ```
+pub fn increment(name: &'static str, value: u64) {
+    if cfg!(feature = "metrics") {
+        let (name, labels) = user_metric(name, current_scenario());
+        ::metrics::counter!(name, labels).increment(value);
+    }
+}
+
+tokio::task_local! {
+    static TRACE_ID: Arc<str>;
+}
+
+/// Receives exemplars for the `balter_transaction_latency` histogram, installed with
+/// [set_exemplar_sink].
+///
+/// The `metrics` crate has no notion of exemplars, so they are handed to the sink alongside the
+/// usual observation rather than passed to the recorder. A sink would typically forward them to
+/// an exporter which supports exemplars, such as an OpenMetrics exporter.
+pub trait ExemplarSink: Send + Sync {
+    /// Record the latency `value` (in seconds) observed for the histogram `key`, with the trace
+    /// ID of the transaction as its exemplar
+    fn record(&self, key: &Key, value: f64, trace_id: &str);
+}
+
+static EXEMPLAR_SINK: OnceLock<Box<dyn ExemplarSink>> = OnceLock::new();
+
+/// Install the sink which receives the exemplars of transactions run within [with_trace_id].
+/// Returns the sink back if one has already been installed.
+pub fn set_exemplar_sink<S: ExemplarSink + 'static>(sink: S) -> Result<(), S> {
+    let mut sink = Some(sink);
+    EXEMPLAR_SINK.get_or_init(|| Box::new(sink.take().unwrap()));
+    match sink {
+        Some(sink) => Err(sink),
+        None => Ok(()),
+    }
+}
+
+/// Attach `trace_id` as an exemplar to the latency of each transaction run within `fut`.
+///
+/// The trace ID is handed to the [ExemplarSink] installed with [set_exemplar_sink], and is never
+/// added to the labels of the histogram, so it doesn't create a series per trace. Without a sink
+/// (or without a trace ID), latencies are recorded as usual.
+///
+/// # Example
+/// ```no_run
+/// use balter::prelude::*;
+///
+/// #[scenario]
+/// async fn my_scenario() {
+///     let trace_id = new_trace_id();
+///     balter::metrics::with_trace_id(trace_id, async {
+///         let _ = my_transaction().await;
+///     })
+///     .await;
+/// }
+///
+/// #[transaction]
+/// async fn my_transaction() -> Result<(), String> {
+///     Ok(())
+/// }
+///
+/// fn new_trace_id() -> String {
+///     "4bf92f3577b34da6a3ce929d0e0e4736".to_string()
+/// }
+/// ```
+pub async fn with_trace_id<F: Future>(trace_id: impl Into<Arc<str>>, fut: F) -> F::Output {
+    TRACE_ID.scope(trace_id.into(), fut).await
+}
+
+/// Hand the latency `value` of the histogram to the exemplar sink, if one is installed and the
+/// current task has a trace ID
+pub(crate) fn record_exemplar(name: &KeyName, labels: &[Label], value: f64) {
+    let Some(sink) = EXEMPLAR_SINK.get() else {
+        return;
+    };
+    if let Ok(trace_id) = TRACE_ID.try_with(Clone::clone) {
+        let key = Key::from_parts(name.clone(), labels.to_vec());
+        sink.record(&key, value, &trace_id);
+    }
+}
+
+/// NOTE: Outside of a running Scenario (e.g. in tasks the Scenario spawns itself), metrics are
+/// emitted without the `scenario` label.
+fn current_scenario() -> Option<Arc<str>> {
+    TRANSACTION_HOOK.try_with(|hook| hook.scenario.clone()).ok()
+}
+
+/// Recorder which a Scenario's metrics are routed to, set with
+/// [metrics_recorder](crate::scenario::ConfigurableScenario::metrics_recorder).
+pub(crate) type SharedRecorder = Arc<dyn ::metrics::Recorder + Send + Sync>;
+
+tokio::task_local! {
+    static SCENARIO_RECORDER: SharedRecorder;
+}
+
+/// Route the metrics emitted by `fut` (and by the worker tasks it spawns) to `recorder`, rather
+/// than the global recorder.
+pub(crate) async fn with_recorder<F: Future>(
+    recorder: Option<SharedRecorder>,
+    fut: F,
+) -> F::Output {
+    match recorder {
+        Some(recorder) => {
+            SCENARIO_RECORDER
+                .scope(recorder.clone(), WithRecorder::new(Some(recorder), fut))
+                .await
+        }
+        None => fut.await,
+    }
+}
+
+/// Recorder of the Scenario currently running, for handing off to its worker tasks.
+pub(crate) fn current_recorder() -> Option<SharedRecorder> {
+    SCENARIO_RECORDER.try_with(Clone::clone).ok()
+}
+
+/// Labels set with [label](crate::scenario::ConfigurableScenario::label), attached to every
+/// metric emitted by the Scenario.
+pub(crate) type ScenarioLabels = Arc<[::metrics::Label]>;
+
+tokio::task_local! {
+    static SCENARIO_LABELS: ScenarioLabels;
+}
+
+/// Attach `labels` to the metrics emitted by `fut`. Worker tasks are handed the labels of the
+/// Scenario which spawns them, in the same way as its recorder.
+pub(crate) async fn with_labels<F: Future>(labels: Option<ScenarioLabels>, fut: F) -> F::Output {
+    match labels {
+        Some(labels) => SCENARIO_LABELS.scope(labels, fut).await,
+        None => fut.await,
+    }
+}
+
+/// Labels of the Scenario currently running, if it has any.
+pub(crate) fn current_labels() -> Option<ScenarioLabels> {
+    SCENARIO_LABELS.try_with(Clone::clone).ok()
+}
+
+/// Future which sets its recorder as the local recorder each time it is polled.
+///
+/// NOTE: The `metrics` crate only supports local recorders for the duration of a closure, so we
+/// have to set it anew on each poll.
+#[pin_project::pin_project]
+pub(crate) struct WithRecorder<F> {
+    #[pin]
+    inner: F,
+    recorder: Option<SharedRecorder>,
+}
+
+impl<F> WithRecorder<F> {
+    pub(crate) fn new(recorder: Option<SharedRecorder>, inner: F) -> Self {
+        Self { inner, recorder }
+    }
+}
+
+impl<F: Future> Future for WithRecorder<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match this.recorder {
+            Some(recorder) => ::metrics::with_local_recorder(&**recorder, || this.inner.poll(cx)),
+            None => this.inner.poll(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scenario::{ConfigurableScenario, Scenario};
+    use ::metrics::{
+        Counter, CounterFn, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+    };
+    use std::sync::{Arc, Mutex};
+
+    /// Recorder which keeps the names of the counters, and the keys of the histograms, it is
+    /// asked for
+    #[derive(Clone, Default)]
+    struct NameRecorder(Arc<Mutex<Vec<String>>>, Arc<Mutex<Vec<Key>>>);
+
+    struct NoopCounter;
+
+    impl CounterFn for NoopCounter {
+        fn increment(&self, _: u64) {}
+        fn absolute(&self, _: u64) {}
+    }
+
+    impl Recorder for NameRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            self.0.lock().unwrap().push(key.name().to_string());
+            Counter::from_arc(Arc::new(NoopCounter))
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            self.1.lock().unwrap().push(key.clone());
+            Histogram::noop()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics_recorder() {
+        let recorder = NameRecorder::default();
+
+        Scenario::new("test_metrics_recorder", || async {
+            super::increment("test_metrics_recorder_count", 1);
+        })
+        .once()
+        .metrics_recorder(recorder.clone())
+        .await;
+
+        let names = recorder.0.lock().unwrap();
+        assert!(names
+            .iter()
+            .any(|name| name == "test_metrics_recorder_count"));
+    }
+
+    /// Sink which keeps the exemplars it is handed
+    #[derive(Clone, Default)]
+    struct VecSink(Arc<Mutex<Vec<(Key, String)>>>);
+
+    impl super::ExemplarSink for VecSink {
+        fn record(&self, key: &Key, _: f64, trace_id: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((key.clone(), trace_id.to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exemplars() {
+        use crate::testing::{Latency, MockScenario};
+
+        let sink = VecSink::default();
+        assert!(super::set_exemplar_sink(sink.clone()).is_ok());
+        assert!(super::set_exemplar_sink(VecSink::default()).is_err());
+
+        let recorder = NameRecorder::default();
+        let mock = MockScenario::new(Latency::Fixed(std::time::Duration::ZERO));
+        Scenario::from_fn("test_exemplars", move || {
+            let mock = mock.clone();
+            async move {
+                let _ = mock.transaction().await;
+                super::with_trace_id("abc123", mock.transaction()).await
+            }
+        })
+        .once()
+        .metrics_recorder(recorder.clone())
+        .await;
+
+        // Only the transaction run with a trace ID has an exemplar, and the trace ID never ends
+        // up in the labels of the histogram
+        let exemplars = sink.0.lock().unwrap();
+        assert_eq!(exemplars.len(), 1);
+        assert!(exemplars[0].0.name().contains("latency"));
+        assert_eq!(exemplars[0].1, "abc123");
+        assert!(recorder
+            .1
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|key| key.labels().all(|label| label.value() != "abc123")));
+    }
+
+    #[tokio::test]
+    async fn test_labels() {
+        use crate::testing::{Latency, MockScenario};
+
+        let recorder = NameRecorder::default();
+        let mock = MockScenario::new(Latency::Fixed(std::time::Duration::ZERO));
+        Scenario::from_fn("test_labels", move || {
+            let mock = mock.clone();
+            async move {
+                let _ = mock.transaction().await;
+                super::record("test_labels_payload", 1.);
+            }
+        })
+        .once()
+        .label("region", "eu-west-1")
+        .label("build", "abc")
+        .label("build", "def")
+        .metrics_recorder(recorder.clone())
+        .await;
+
+        let keys = recorder.1.lock().unwrap();
+        // NOTE: With legacy naming the latency metric is named after the transaction
+        for name in ["latency", "test_labels_payload"] {
+            let key = keys
+                .iter()
+                .find(|key| key.name().contains(name))
+                .unwrap_or_else(|| panic!("{name} not recorded"));
+            let labels: Vec<_> = key
+                .labels()
+                .filter(|label| ["region", "build"].contains(&label.key()))
+                .map(|label| (label.key(), label.value()))
+                .collect();
+            assert_eq!(labels, [("build", "def"), ("region", "eu-west-1")]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "reserved")]
+    fn test_reserved_label() {
+        drop(Scenario::new("test_reserved_label", || async {}).label("scenario", "other"));
+    }
+}
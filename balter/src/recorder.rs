@@ -0,0 +1,114 @@
+use crate::measurement::Measurement;
+use balter_core::LiveStats;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::SystemTime;
+#[allow(unused)]
+use tracing::{debug, error, info, trace, warn};
+
+/// Number of rows written between flushes to disk
+const FLUSH_ROWS: usize = 10;
+
+const HEADER: &str = "elapsed,concurrency,goal_tps,actual_tps,success,error,error_rate,\
+latency_p50,latency_p90,latency_p95,latency_p99,latency_max,timestamp";
+
+/// Records a row per sample to a CSV file, for offline analysis of a run.
+///
+/// NOTE: Durations are written in (fractional) seconds, and the wall-clock timestamp of each
+/// sample in (fractional) seconds since the Unix epoch.
+pub(crate) struct CsvRecorder {
+    writer: BufWriter<File>,
+    rows: usize,
+}
+
+impl CsvRecorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{HEADER}")?;
+        Ok(Self { writer, rows: 0 })
+    }
+
+    pub fn record(&mut self, stats: &LiveStats, measurement: &Measurement) {
+        if let Err(err) = self.write_row(stats, measurement) {
+            error!("Unable to record sample to CSV: {err}");
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if let Err(err) = self.writer.flush() {
+            error!("Unable to flush CSV recording: {err}");
+        }
+    }
+
+    fn write_row(&mut self, stats: &LiveStats, measurement: &Measurement) -> io::Result<()> {
+        writeln!(
+            self.writer,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            stats.elapsed.as_secs_f64(),
+            stats.concurrency,
+            stats.goal_tps,
+            measurement.tps,
+            measurement.success,
+            measurement.error,
+            measurement.error_rate,
+            measurement.latency(0.5).as_secs_f64(),
+            measurement.latency(0.9).as_secs_f64(),
+            measurement.latency(0.95).as_secs_f64(),
+            measurement.latency(0.99).as_secs_f64(),
+            measurement.latency(1.).as_secs_f64(),
+            measurement
+                .timestamp
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64(),
+        )?;
+
+        self.rows += 1;
+        if self.rows.is_multiple_of(FLUSH_ROWS) {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_csv_rows() {
+        let path = std::env::temp_dir().join(format!("balter-test-{}.csv", std::process::id()));
+        let mut recorder = CsvRecorder::create(&path).unwrap();
+
+        for i in 1..=3 {
+            let mut measurement = Measurement::new(100 * i, i, Duration::from_secs(1));
+            measurement.populate_latencies(&[Duration::from_millis(10)]);
+            let stats = LiveStats {
+                elapsed: Duration::from_secs(i),
+                concurrency: 5,
+                goal_tps: 100,
+                ..Default::default()
+            };
+            recorder.record(&stats, &measurement);
+        }
+        recorder.flush();
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], HEADER);
+        assert!(lines[3].starts_with("3,5,100,300,300,3,"));
+        assert_eq!(lines[3].split(',').count(), HEADER.split(',').count());
+
+        let timestamp: f64 = lines[3].rsplit(',').next().unwrap().parse().unwrap();
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64();
+        assert!((now - timestamp).abs() < 60.);
+    }
+}
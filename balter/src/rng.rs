@@ -0,0 +1,95 @@
+//! Random number generation within a Scenario.
+use rand::{rngs::SmallRng, RngCore, SeedableRng};
+use std::cell::RefCell;
+
+tokio::task_local! {
+    /// RNG for each task spawned by the sampler, so that tasks don't contend on a shared RNG.
+    pub(crate) static TASK_RNG: RefCell<SmallRng>;
+}
+
+/// Build the RNG for the worker task with the given index. Seeded RNGs derive a distinct but
+/// deterministic seed for each worker.
+pub(crate) fn task_rng(seed: Option<u64>, worker: usize) -> RefCell<SmallRng> {
+    let rng = match seed {
+        Some(seed) => {
+            SmallRng::seed_from_u64(seed ^ (worker as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+        }
+        None => SmallRng::from_entropy(),
+    };
+    RefCell::new(rng)
+}
+
+/// Handle to the RNG of the current Balter task.
+///
+/// When the Scenario is run with a seed (see
+/// [seed](crate::scenario::ConfigurableScenario::seed)) the values it produces are
+/// reproducible across runs. Outside of a running Scenario it falls back to a thread-local RNG.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TaskRng {
+    _private: (),
+}
+
+/// Get the RNG of the current Balter task. See [TaskRng].
+///
+/// # Example
+/// ```no_run
+/// use balter::prelude::*;
+/// use rand::Rng;
+///
+/// #[scenario]
+/// async fn my_scenario() {
+///     let user_id: u64 = balter::rng().gen_range(0..10_000);
+/// }
+/// ```
+pub fn rng() -> TaskRng {
+    TaskRng::default()
+}
+
+impl TaskRng {
+    fn with<R>(&mut self, f: impl FnOnce(&mut dyn RngCore) -> R) -> R {
+        let mut f = Some(f);
+        TASK_RNG
+            .try_with(|rng| (f.take().unwrap())(&mut *rng.borrow_mut()))
+            .unwrap_or_else(|_| (f.take().unwrap())(&mut rand::thread_rng()))
+    }
+}
+
+impl RngCore for TaskRng {
+    fn next_u32(&mut self) -> u32 {
+        self.with(|rng| rng.next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.with(|rng| rng.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.with(|rng| rng.fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.with(|rng| rng.try_fill_bytes(dest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[tokio::test]
+    async fn test_seeded_workers() {
+        let draw = |seed, worker| async move {
+            TASK_RNG
+                .scope(task_rng(Some(seed), worker), async { rng().gen::<u64>() })
+                .await
+        };
+
+        assert_eq!(draw(42, 0).await, draw(42, 0).await);
+        assert_ne!(draw(42, 0).await, draw(42, 1).await);
+        assert_ne!(draw(42, 0).await, draw(43, 0).await);
+
+        // Falls back to a thread-local RNG outside of a task
+        let _: u64 = rng().gen();
+    }
+}
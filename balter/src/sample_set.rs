@@ -0,0 +1,227 @@
+use crate::measurement::Measurement;
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+/// The most recent samples taken while running a Scenario.
+///
+/// Holds a sliding window of measurements (see
+/// [sample_window](crate::scenario::ConfigurableScenario::sample_window)), and is provided to
+/// custom stop conditions with [until](crate::scenario::ConfigurableScenario::until) and to
+/// callbacks registered with [on_sample](crate::scenario::ConfigurableScenario::on_sample).
+///
+/// All statistics are computed over the samples currently in the window, and are zero when the
+/// window is empty. The only exception is [recent_error_rate](SampleSet::recent_error_rate),
+/// which is computed over its own window (see
+/// [error_window](crate::scenario::ConfigurableScenario::error_window)). The samples themselves
+/// can be iterated over with [iter](SampleSet::iter).
+#[derive(Clone, Debug)]
+pub struct SampleSet {
+    samples: VecDeque<Measurement>,
+    window: usize,
+    error_window: usize,
+    elapsed: Duration,
+}
+
+impl SampleSet {
+    pub(crate) fn new(window: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(window),
+            window,
+            error_window: 1,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Also hold enough samples to compute the error rate over the last `error_window` samples.
+    pub(crate) fn with_error_window(mut self, error_window: usize) -> Self {
+        self.error_window = error_window;
+        self
+    }
+
+    pub(crate) fn push(&mut self, measurement: Measurement, elapsed: Duration) {
+        if self.samples.len() >= self.window.max(self.error_window) {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(measurement);
+        self.elapsed = elapsed;
+    }
+
+    /// The most recent `count` samples
+    fn recent(&self, count: usize) -> impl Iterator<Item = &Measurement> {
+        self.samples
+            .iter()
+            .skip(self.samples.len().saturating_sub(count))
+    }
+
+    fn windowed(&self) -> impl Iterator<Item = &Measurement> {
+        self.recent(self.window)
+    }
+
+    /// The most recent sample
+    pub fn latest(&self) -> Option<&Measurement> {
+        self.samples.back()
+    }
+
+    /// The samples in the window, from oldest to newest
+    pub fn iter(&self) -> impl Iterator<Item = &Measurement> {
+        self.windowed()
+    }
+
+    /// Number of samples currently in the window
+    pub fn len(&self) -> usize {
+        self.samples.len().min(self.window)
+    }
+
+    /// Maximum number of samples held in the window
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Time elapsed since the Scenario started, as of the most recent sample
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Wall-clock time at which the most recent sample was taken, for correlating samples with
+    /// external logs and metrics
+    pub fn timestamp(&self) -> Option<SystemTime> {
+        self.latest().map(|m| m.timestamp)
+    }
+
+    /// Mean TPS across the samples in the window
+    pub fn mean_tps(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.;
+        }
+        self.windowed().map(|m| m.tps).sum::<f64>() / self.len() as f64
+    }
+
+    /// Population standard deviation of the TPS across the samples in the window
+    pub fn std_dev_tps(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.;
+        }
+        let mean = self.mean_tps();
+        let var = self.windowed().map(|m| (m.tps - mean).powi(2)).sum::<f64>() / self.len() as f64;
+        var.sqrt()
+    }
+
+    /// Total number of successful transactions in the window
+    pub fn success_count(&self) -> u64 {
+        self.windowed().map(|m| m.success).sum()
+    }
+
+    /// Total number of failed transactions in the window
+    pub fn error_count(&self) -> u64 {
+        self.windowed().map(|m| m.error).sum()
+    }
+
+    /// Error rate across all transactions in the window
+    pub fn error_rate(&self) -> f64 {
+        error_rate(self.windowed())
+    }
+
+    /// Error rate across all transactions in the most recent
+    /// [error_window](crate::scenario::ConfigurableScenario::error_window) samples, which is
+    /// only the most recent sample unless configured otherwise.
+    pub fn recent_error_rate(&self) -> f64 {
+        error_rate(self.recent(self.error_window))
+    }
+
+    /// Latency at the given quantile (between 0 and 1) across all transactions in the window
+    pub fn latency(&self, quantile: f64) -> Duration {
+        let mut samples = self.windowed();
+        let Some(first) = samples.next() else {
+            return Duration::ZERO;
+        };
+
+        let mut total = first.clone();
+        for m in samples {
+            total.merge(m);
+        }
+        total.latency(quantile)
+    }
+}
+
+fn error_rate<'a>(samples: impl Iterator<Item = &'a Measurement>) -> f64 {
+    let (success, error) = samples.fold((0, 0), |(success, error), m| {
+        (success + m.success, error + m.error)
+    });
+
+    if success + error == 0 {
+        0.
+    } else {
+        error as f64 / (success + error) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window() {
+        let mut samples = SampleSet::new(3);
+        assert!(samples.is_empty());
+        assert_eq!(samples.std_dev_tps(), 0.);
+        assert_eq!(samples.error_rate(), 0.);
+        assert_eq!(samples.latency(0.99), Duration::ZERO);
+
+        for i in 1..=5 {
+            let mut m = Measurement::new(100 * i, i, Duration::from_secs(1));
+            m.populate_latencies(&[Duration::from_millis(10 * i)]);
+            samples.push(m, Duration::from_secs(i));
+        }
+
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples.window(), 3);
+        assert_eq!(samples.elapsed(), Duration::from_secs(5));
+        assert_eq!(samples.mean_tps(), 400.);
+        assert!((samples.std_dev_tps() - (20_000f64 / 3.).sqrt()).abs() < 1e-9);
+        assert_eq!(samples.success_count(), 1_200);
+        assert_eq!(samples.error_count(), 12);
+        assert_eq!(samples.error_rate(), 12. / 1_212.);
+        assert_eq!(
+            samples.iter().map(|m| m.success).collect::<Vec<_>>(),
+            vec![300, 400, 500]
+        );
+        assert_eq!(samples.latest().map(|m| m.error), Some(5));
+
+        let max = samples.latency(1.).as_secs_f64();
+        assert!((max - 0.05).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_error_window() {
+        let mut samples = SampleSet::new(2).with_error_window(4);
+        assert_eq!(samples.recent_error_rate(), 0.);
+
+        for i in 1..=5 {
+            samples.push(
+                Measurement::new(100 - 10 * i, 10 * i, Duration::from_secs(1)),
+                Duration::from_secs(i),
+            );
+        }
+
+        // The rest of the statistics are only over the last 2 samples
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples.error_count(), 90);
+        assert_eq!(samples.error_rate(), 0.45);
+        assert_eq!(samples.recent_error_rate(), 0.35);
+
+        let mut samples = SampleSet::new(3);
+        samples.push(
+            Measurement::new(50, 50, Duration::from_secs(1)),
+            Duration::ZERO,
+        );
+        samples.push(
+            Measurement::new(90, 10, Duration::from_secs(1)),
+            Duration::ZERO,
+        );
+        assert_eq!(samples.recent_error_rate(), 0.1);
+    }
+}
@@ -1,56 +1,250 @@
 mod base_sampler;
+mod catch_unwind;
 mod outlier_detection;
+mod pacer;
+mod pid;
 mod task_atomics;
 mod timer;
 
+pub(crate) use base_sampler::SpawnWorker;
+pub(crate) use pacer::Pacer;
+
 use crate::measurement::Measurement;
-use std::future::Future;
+use crate::sample_set::SampleSet;
+use crate::state::StateMap;
+use balter_core::{
+    ConcurrencySummary, ControllerKind, ErrorCounts, SamplerConfig, ScenarioConfig, TpsTarget,
+};
+use pid::PidController;
+use std::collections::BTreeMap;
 use std::num::NonZeroU32;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
 #[allow(unused)]
 use tracing::{debug, error, info, trace, warn};
 
-const MIN_SAMPLES: usize = 5;
 const MAX_RETRIES: usize = 4;
+/// Coefficient of variation in TPS above which a sample window is considered noisy
+const NOISY_CV: f64 = 0.25;
+/// Coefficient of variation in TPS below which the sample interval can be shortened
+const QUIET_CV: f64 = 0.05;
+/// Minimum ratio of the sample interval to the median transaction latency. Transactions still in
+/// flight at the end of an interval are counted in the next one, which biases the measured TPS
+/// unless each interval spans many transaction latencies.
+const LATENCY_INTERVAL_RATIO: u32 = 10;
+/// Relative change in TPS per relative change in concurrency below which TPS is considered to
+/// have plateaued
+const PLATEAU_ELASTICITY: f64 = 0.2;
+/// Growth in median latency over the concurrency history which indicates the service is
+/// saturated
+const SATURATED_LATENCY_RATIO: f64 = 1.2;
+/// Growth in error rate over the concurrency history which indicates the service is saturated
+const SATURATED_ERROR_RATE: f64 = 0.05;
+/// Consecutive samples in which the scenario completes runs without recording a single
+/// transaction, after which the scenario is assumed to never call one
+const MAX_SAMPLES_WITHOUT_TRANSACTIONS: usize = 3;
 
 pub(crate) struct Sampler<T> {
     sampler: base_sampler::BaseSampler<T>,
-    concurrency_history: Vec<(usize, f64)>,
+    config: SamplerConfig,
+    concurrency_history: Vec<(usize, Stats)>,
     tps_limited: Option<(usize, NonZeroU32)>,
+    concurrency_limited: bool,
+    run_measurement: Option<Measurement>,
+    stable_measurement: Option<Measurement>,
+    /// Whether the most recent sample was stable
+    stable: bool,
+    samples: SampleSet,
+    started: Instant,
+    pid: Option<PidController>,
+    interval_bounds: (Duration, Duration),
+    latency_warned: bool,
+    /// Lowest p99 latency measured over a sample window, used to detect latency spikes
+    latency_baseline: Option<Duration>,
+    /// Time spent at each concurrency
+    concurrency_dwell: BTreeMap<usize, Duration>,
+    /// Concurrency of the previous measurement, and how often it has changed between measurements
+    concurrency_changes: (Option<usize>, usize),
+    /// Whether the concurrency has yet to be estimated from the first sample
+    warmup: bool,
+    /// Whether the sampler was paused at the last sample
+    was_paused: bool,
+    /// Consecutive samples in which runs of the scenario completed without any transactions
+    samples_without_transactions: usize,
 }
 
-impl<T, F> Sampler<T>
-where
-    T: Fn() -> F + Send + Sync + 'static + Clone,
-    F: Future<Output = ()> + Send,
-{
-    pub async fn new(name: &str, scenario: T, tps_limit: NonZeroU32, concurrency: usize) -> Self {
-        let mut sampler = base_sampler::BaseSampler::new(name, scenario, tps_limit).await;
-        sampler.set_concurrency(concurrency);
+impl<T: SpawnWorker> Sampler<T> {
+    pub async fn new(
+        scenario: T,
+        tps_limit: NonZeroU32,
+        config: &ScenarioConfig,
+        state: StateMap,
+    ) -> Self {
+        let mut sampler = base_sampler::BaseSampler::new(
+            &config.name,
+            scenario,
+            tps_limit,
+            config.sampler.interval,
+        )
+        .await;
+        if let Some(iterations) = config.iterations {
+            sampler.set_iterations(iterations);
+        }
+        if let Some(budget) = config.request_budget {
+            sampler.set_request_budget(budget);
+        }
+        if config.dry_run {
+            sampler.set_dry_run();
+        }
+        if let Some(retry) = config.retry {
+            sampler.set_retry_policy(retry);
+        }
+        if let Some(seed) = config.seed {
+            sampler.set_seed(seed);
+        }
+        sampler.set_state(state);
+        if let Some(period) = config.tps_period {
+            sampler.set_tps_period(period);
+        }
+        sampler.set_burst(config.sampler.burst);
+        sampler.set_arrival(config.sampler.arrival);
+        sampler.set_tps_target(config.sampler.tps_target);
+        if !config.transaction_tps.is_empty() {
+            sampler.set_transaction_tps(&config.transaction_tps);
+        }
+        let concurrency = config.concurrency();
+        sampler.set_initial_concurrency(
+            config
+                .sampler
+                .max_concurrency
+                .map_or(concurrency, |max| concurrency.min(max)),
+        );
+
+        // NOTE: By default the interval can adapt between the standard and slow intervals, while
+        // always allowing for the initial interval.
+        let interval = sampler.interval();
+        let interval_bounds = config.sampler.interval_bounds.unwrap_or((
+            interval.min(balter_core::BASE_INTERVAL),
+            interval.max(balter_core::BASE_INTERVAL_SLOW),
+        ));
+
         Self {
             sampler,
+            config: config.sampler,
             concurrency_history: vec![],
             tps_limited: None,
+            concurrency_limited: false,
+            run_measurement: None,
+            stable_measurement: None,
+            stable: false,
+            samples: SampleSet::new(config.sampler.window)
+                .with_error_window(config.sampler.error_window),
+            started: Instant::now(),
+            pid: match config.sampler.controller {
+                ControllerKind::Ratio | ControllerKind::Fixed => None,
+                ControllerKind::Pid(gains) => Some(PidController::new(gains)),
+            },
+            interval_bounds,
+            latency_warned: false,
+            latency_baseline: None,
+            concurrency_dwell: BTreeMap::new(),
+            concurrency_changes: (None, 0),
+            warmup: !config.sampler.skip_warmup
+                && config.sampler.controller != ControllerKind::Fixed,
+            was_paused: false,
+            samples_without_transactions: 0,
         }
     }
 
     pub async fn sample(&mut self) -> (bool, Measurement) {
+        let (stable, measurement) = self.sample_window().await;
+        self.stable = stable;
+        (stable, measurement)
+    }
+
+    async fn sample_window(&mut self) -> (bool, Measurement) {
         let mut retries = 0;
+        let mut skipped = 0;
         let mut prev = vec![];
         loop {
+            if self.was_paused && !self.sampler.paused() {
+                self.was_paused = false;
+                self.sampler.restart().await;
+            }
+
             let measurement = self.sampler.sample().await;
+
+            // NOTE: Measurements overlapping a pause are discarded, so that the drop in TPS
+            // neither skews the statistics nor ramps up the concurrency once resumed.
+            if self.sampler.paused() || self.was_paused {
+                self.was_paused = true;
+                break (false, measurement);
+            }
+
+            self.record_concurrency(measurement.elapsed);
+            merge_into(&mut self.run_measurement, &measurement);
+            self.samples
+                .push(measurement.clone(), self.started.elapsed());
+            self.sampler.set_output(
+                self.samples.mean_tps(),
+                measurement.scenario_tps,
+                self.samples.error_rate(),
+            );
+
+            if self.sampler.iterations_complete() || self.sampler.budget_exhausted() {
+                break (false, measurement);
+            }
+
+            // NOTE: Without any transactions the TPS always reads as zero, and adjusting the
+            // concurrency to chase the goal would only ramp it up to the maximum.
+            if self.missing_transactions(&measurement) {
+                break (false, measurement);
+            }
+
+            // NOTE: The final sample is cut short at the end of the duration, and is too short
+            // to judge stability by, so the previous verdict stands.
+            if self.sampler.deadline_reached() {
+                break (self.stable, measurement);
+            }
+
+            // NOTE: A latency spike is reacted to right away, rather than waiting for a full
+            // sample window, to avoid piling more load onto a struggling service.
+            if self.back_off_on_latency_spike(&measurement) {
+                break (false, measurement);
+            }
+
+            if self.warmup {
+                self.warmup = false;
+                if self.jump_to_estimate(&measurement) {
+                    continue;
+                }
+            }
+
+            // NOTE: The first few samples after a change in concurrency or TPS limit tend to be
+            // noisy while the tasks settle, so we optionally ignore them.
+            if skipped < self.config.skip {
+                skipped += 1;
+                continue;
+            }
+
             prev.push(measurement.clone());
 
-            if prev.len() < MIN_SAMPLES {
+            if prev.len() < self.config.window {
                 continue;
             }
 
             let stats = calculate_stats(&prev);
             trace!("Stats: {stats:?}");
 
+            if self.config.latency_backoff.is_some() {
+                self.update_latency_baseline(&prev);
+            }
+
+            self.adjust_interval(stats, measurement.latency(0.5)).await;
+
             // Check if the statistics have stabilized, if not we retry, and if
             // we have retried too many times we note with a warning.
-            // TODO: Would be nice to have adaptable interval here.
-            if stats.outlier_count > 0 || stats.std_percent() > 0.25 {
+            if stats.outlier_count > 0 || stats.cv() > NOISY_CV {
                 prev.clear();
                 retries += 1;
 
@@ -61,11 +255,14 @@ where
                 }
             }
 
-            if !self.check_underpowered() {
+            if self.config.controller != ControllerKind::Fixed && !self.check_underpowered(stats) {
                 self.adjust_concurrency(stats);
             }
 
             if self.at_goal(stats) {
+                for m in &prev {
+                    merge_into(&mut self.stable_measurement, m);
+                }
                 break (true, measurement);
             } else {
                 break (false, measurement);
@@ -77,58 +274,319 @@ where
         self.sampler.set_tps_limit(tps_limit);
     }
 
-    pub fn shutdown(self) -> SamplerStats {
+    /// Track whether the scenario is completing runs without recording any transactions, which
+    /// happens when it never calls a `#[transaction]`.
+    fn missing_transactions(&mut self, measurement: &Measurement) -> bool {
+        if self.config.tps_target == TpsTarget::Transactions
+            && measurement.scenarios > 0
+            && measurement.success + measurement.error == 0
+        {
+            self.samples_without_transactions += 1;
+            true
+        } else {
+            self.samples_without_transactions = 0;
+            false
+        }
+    }
+
+    /// Whether the scenario has completed runs without recording a single transaction for
+    /// several samples in a row
+    pub fn no_transactions(&self) -> bool {
+        self.samples_without_transactions >= MAX_SAMPLES_WITHOUT_TRANSACTIONS
+    }
+
+    /// Pause the scenario whenever the flag is set. Must be called before the first sample.
+    pub fn set_pause(&mut self, paused: watch::Receiver<bool>) {
+        self.sampler.set_pause(paused);
+    }
+
+    pub fn paused(&self) -> bool {
+        self.sampler.paused()
+    }
+
+    /// Override the goal TPS from outside the controllers. Once the goal has been found to be
+    /// out of reach (see `tps_limited`), it is capped at the highest TPS the sampler could reach.
+    pub fn override_tps_limit(&mut self, tps_limit: NonZeroU32) {
+        let tps_limit = match self.tps_limited {
+            Some((_, max_tps)) if tps_limit > max_tps => {
+                debug!("Capping overridden goal TPS of {tps_limit} at {max_tps}");
+                max_tps
+            }
+            _ => tps_limit,
+        };
+        self.sampler.set_tps_limit(tps_limit);
+    }
+
+    /// Override the concurrency from outside the sampler, up to the maximum concurrency. Unless
+    /// the concurrency is fixed, it is still adjusted from there to reach the goal TPS.
+    pub fn override_concurrency(&mut self, concurrency: usize) {
+        let concurrency = self
+            .config
+            .max_concurrency
+            .map_or(concurrency, |max| concurrency.min(max));
+        self.sampler.set_concurrency(concurrency);
+    }
+
+    /// Stop sampling at the deadline, cutting the final sample short if need be
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.sampler
+            .set_deadline(tokio::time::Instant::from_std(deadline));
+    }
+
+    pub async fn shutdown(mut self) -> SamplerStats {
         let concurrency = self.sampler.concurrency();
         let tps_limit = self.sampler.tps_limit();
-        self.sampler.shutdown();
+
+        let measurement = self.sampler.shutdown(self.config.shutdown_timeout).await;
+        if measurement.success + measurement.error > 0 {
+            merge_into(&mut self.run_measurement, &measurement);
+        }
+
+        let panics = self.sampler.panics();
+        let scenario_errors = self.sampler.scenario_errors();
+        let errors = self.sampler.error_counts();
+        let status_codes = self.sampler.status_codes();
+
+        let dwell: Vec<_> = self.concurrency_dwell.into_iter().collect();
+        let concurrency_summary =
+            ConcurrencySummary::from_dwell(&dwell, self.concurrency_changes.1);
 
         SamplerStats {
             tps_limit,
             concurrency,
+            concurrency_summary,
             tps_limited: self.tps_limited.is_some(),
+            concurrency_limited: self.concurrency_limited,
+            panics,
+            scenario_errors,
+            errors,
+            status_codes,
+            run_measurement: self.run_measurement,
+            stable_measurement: self.stable_measurement,
+        }
+    }
+
+    /// Record the time the sampler just spent at its current concurrency
+    fn record_concurrency(&mut self, elapsed: Duration) {
+        let concurrency = self.sampler.concurrency();
+        *self.concurrency_dwell.entry(concurrency).or_default() += elapsed;
+
+        let (prev, changes) = &mut self.concurrency_changes;
+        if prev.is_some_and(|prev| prev != concurrency) {
+            *changes += 1;
         }
+        *prev = Some(concurrency);
     }
 
     pub fn tps_limit(&self) -> NonZeroU32 {
         self.sampler.tps_limit()
     }
 
-    fn check_underpowered(&mut self) -> bool {
+    pub fn concurrency(&self) -> usize {
+        self.sampler.concurrency()
+    }
+
+    pub fn iterations_complete(&self) -> bool {
+        self.sampler.iterations_complete()
+    }
+
+    pub fn budget_exhausted(&self) -> bool {
+        self.sampler.budget_exhausted()
+    }
+
+    pub fn panics(&self) -> u64 {
+        self.sampler.panics()
+    }
+
+    pub fn samples(&self) -> &SampleSet {
+        &self.samples
+    }
+
+    fn check_underpowered(&mut self, stats: Stats) -> bool {
         if self.tps_limited.is_some() {
             return true;
         }
 
-        if self.concurrency_history.len() > 4
-            && detect_zero_slope(&self.concurrency_history[self.concurrency_history.len() - 3..])
-        {
-            let (max_concurrency, max_tps) =
-                self.concurrency_history[self.concurrency_history.len() - 3];
-
-            let max_tps = max_tps * 0.9;
-            let max_tps = NonZeroU32::new(max_tps.ceil().max(1.) as u32).unwrap();
-            self.tps_limited = Some((max_concurrency, max_tps));
-            self.sampler.set_tps_limit(max_tps);
-            self.sampler.set_concurrency(max_concurrency);
-            self.concurrency_history.clear();
-            true
+        if let Some(max_concurrency) = self.config.max_concurrency {
+            if self.sampler.concurrency() >= max_concurrency && !self.at_goal(stats) {
+                warn!(
+                    "Unable to reach goal TPS within the maximum concurrency of {max_concurrency}."
+                );
+                let max_tps = underpowered_tps(stats.mean);
+                self.tps_limited = Some((max_concurrency, max_tps));
+                self.concurrency_limited = true;
+                self.sampler.set_tps_limit(max_tps);
+                self.sampler.set_concurrency(max_concurrency);
+                return true;
+            }
+        }
+
+        if self.concurrency_history.len() <= 4 {
+            return false;
+        }
+
+        let history = &self.concurrency_history[self.concurrency_history.len() - 3..];
+        let (max_concurrency, max_stats) = history[0];
+        match detect_saturation(history) {
+            Saturation::Scaling => {
+                debug!("TPS is scaling with concurrency; the load generator needs more tasks.");
+                return false;
+            }
+            Saturation::Service => warn!(
+                "TPS plateaued at {:.0} while latency or errors climbed with concurrency; the service under test is the bottleneck.",
+                max_stats.mean
+            ),
+            Saturation::Generator => warn!(
+                "TPS plateaued at {:.0} without latency or errors climbing; the load generator is the bottleneck.",
+                max_stats.mean
+            ),
+        }
+
+        let max_tps = underpowered_tps(max_stats.mean);
+        self.tps_limited = Some((max_concurrency, max_tps));
+        self.sampler.set_tps_limit(max_tps);
+        self.sampler.set_concurrency(max_concurrency);
+        self.concurrency_history.clear();
+        true
+    }
+
+    /// Lengthen the sample interval when measurements are noisy, and shorten it when they are
+    /// consistent, within the configured bounds. The interval is kept long enough relative to
+    /// the transaction `latency` that in-flight transactions don't skew the measured TPS.
+    async fn adjust_interval(&mut self, stats: Stats, latency: Duration) {
+        let interval = self.sampler.interval();
+        let (min, max) = self.interval_bounds;
+
+        let latency_floor = latency * LATENCY_INTERVAL_RATIO;
+        if latency_floor > max && !self.latency_warned {
+            warn!(
+                "Transaction latency of {latency:?} is high relative to the maximum sample interval of {max:?}; measured TPS may be inaccurate."
+            );
+            self.latency_warned = true;
+        }
+
+        let new_interval = if stats.cv() > NOISY_CV {
+            (interval * 2).min(max)
+        } else if stats.cv() < QUIET_CV {
+            (interval / 2).max(min)
         } else {
-            false
+            interval
+        };
+        let new_interval = new_interval.max(latency_floor.min(max));
+
+        if new_interval != interval {
+            debug!(
+                "Adjusting sample interval from {interval:?} to {new_interval:?} (CV={:.3}, latency={latency:?})",
+                stats.cv()
+            );
+            self.sampler.set_interval(new_interval).await;
+        }
+    }
+
+    /// Halve the concurrency if the p99 latency of the `measurement` jumped beyond the configured
+    /// multiple of the baseline, returning whether it did.
+    fn back_off_on_latency_spike(&mut self, measurement: &Measurement) -> bool {
+        let (Some(multiple), Some(baseline)) = (self.config.latency_backoff, self.latency_baseline)
+        else {
+            return false;
+        };
+
+        let concurrency = self.sampler.concurrency();
+        if self.config.controller == ControllerKind::Fixed || concurrency <= 1 {
+            return false;
+        }
+
+        let latency = measurement.latency(0.99);
+        if latency.as_secs_f64() <= baseline.as_secs_f64() * multiple {
+            return false;
         }
+
+        let new_concurrency = concurrency / 2;
+        warn!(
+            "p99 latency of {latency:?} is over {multiple}x the baseline of {baseline:?}; reducing concurrency from {concurrency} to {new_concurrency}."
+        );
+        self.sampler.set_concurrency(new_concurrency);
+        // NOTE: The history no longer reflects how the service scales with concurrency.
+        self.concurrency_history.clear();
+        true
+    }
+
+    fn update_latency_baseline(&mut self, window: &[Measurement]) {
+        let mut total = None;
+        for m in window {
+            merge_into(&mut total, m);
+        }
+        let Some(latency) = total.map(|total| total.latency(0.99)) else {
+            return;
+        };
+
+        if !latency.is_zero() {
+            self.latency_baseline = Some(
+                self.latency_baseline
+                    .map_or(latency, |baseline| baseline.min(latency)),
+            );
+        }
+    }
+
+    /// Jump straight to the concurrency needed to reach the goal TPS at the latency of the first
+    /// `measurement`, rather than ramping up to it, returning whether the concurrency changed.
+    ///
+    /// NOTE: The jump is not limited by the concurrency step, since it's based on a measurement
+    /// rather than a guess; the adaptive controller takes over from there.
+    fn jump_to_estimate(&mut self, measurement: &Measurement) -> bool {
+        if measurement.success + measurement.error == 0 {
+            return false;
+        }
+
+        let concurrency = self.sampler.concurrency();
+        let goal_tps = self.sampler.tps_limit().get() as f64;
+        let estimate = estimate_concurrency(goal_tps, measurement.latency(0.5));
+        let estimate = self
+            .config
+            .max_concurrency
+            .map_or(estimate, |max| estimate.min(max));
+        if estimate <= concurrency {
+            return false;
+        }
+
+        debug!("Estimated a concurrency of {estimate} from the first sample; jumping from {concurrency}");
+        self.sampler.set_concurrency(estimate);
+        true
     }
 
     fn at_goal(&self, stats: Stats) -> bool {
         let goal_tps = self.sampler.tps_limit().get() as f64;
-        (stats.mean + stats.std) >= (goal_tps * 0.98)
+        (stats.mean + stats.std) >= (goal_tps * (1. - self.config.tps_tolerance))
     }
 
     fn adjust_concurrency(&mut self, stats: Stats) {
         self.concurrency_history
-            .push((self.sampler.concurrency(), stats.mean));
+            .push((self.sampler.concurrency(), stats));
 
-        let tps_per_task = stats.mean / self.sampler.concurrency() as f64;
-        let new_concurrency =
-            (self.sampler.tps_limit().get() as f64 / tps_per_task).ceil() as usize;
-        let new_concurrency = new_concurrency.max(self.sampler.concurrency()).max(1);
+        let concurrency = self.sampler.concurrency();
+        let goal_tps = self.sampler.tps_limit().get() as f64;
+        let new_concurrency = match &mut self.pid {
+            Some(pid) => pid.next_concurrency(concurrency, stats.mean, goal_tps),
+            None => pid::ratio_concurrency(concurrency, stats.mean, goal_tps),
+        };
+        let new_concurrency = self
+            .config
+            .concurrency_step
+            .limit(concurrency, new_concurrency);
+
+        // NOTE: Rounding can leave the controller stuck at the same concurrency short of the
+        // goal (e.g. the PID controller at a single task), where the history holds no change in
+        // concurrency to judge scaling by, so we nudge it up by a task instead.
+        let new_concurrency = if new_concurrency == concurrency && !self.at_goal(stats) {
+            debug!("Concurrency is stuck at {concurrency} short of the goal TPS; nudging it up");
+            concurrency + 1
+        } else {
+            new_concurrency
+        };
+        let new_concurrency = self
+            .config
+            .max_concurrency
+            .map_or(new_concurrency, |max| new_concurrency.min(max));
 
         self.sampler.set_concurrency(new_concurrency);
     }
@@ -137,7 +595,17 @@ where
 pub(crate) struct SamplerStats {
     pub tps_limit: NonZeroU32,
     pub concurrency: usize,
+    pub concurrency_summary: ConcurrencySummary,
     pub tps_limited: bool,
+    pub concurrency_limited: bool,
+    pub panics: u64,
+    pub scenario_errors: u64,
+    pub errors: ErrorCounts,
+    pub status_codes: BTreeMap<u16, u64>,
+    /// Every measurement taken over the run, merged together
+    pub run_measurement: Option<Measurement>,
+    /// Measurements from windows in which the sampler was stable, merged together
+    pub stable_measurement: Option<Measurement>,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -146,11 +614,14 @@ struct Stats {
     std: f64,
     #[allow(unused)]
     outlier_count: usize,
+    /// Median latency across all measurements
+    latency: Duration,
+    error_rate: f64,
 }
 
 impl Stats {
-    // Calculate Standard deviation as a percentage of the Mean
-    fn std_percent(&self) -> f64 {
+    // Coefficient of variation; the standard deviation as a percentage of the Mean
+    fn cv(&self) -> f64 {
         1. - ((self.mean - self.std) / self.mean)
     }
 }
@@ -164,34 +635,563 @@ fn calculate_stats(measurements: &[Measurement]) -> Stats {
 
     let outlier_count = outlier_detection::num_outliers(&tps);
 
+    let mut total = None;
+    for m in measurements {
+        merge_into(&mut total, m);
+    }
+    let (latency, error_rate) = match total {
+        Some(total) if total.success + total.error > 0 => (total.latency(0.5), total.error_rate),
+        _ => (Duration::ZERO, 0.),
+    };
+
     Stats {
         mean,
         std,
         outlier_count,
+        latency,
+        error_rate,
+    }
+}
+
+fn merge_into(total: &mut Option<Measurement>, measurement: &Measurement) {
+    match total {
+        Some(total) => total.merge(measurement),
+        None => *total = Some(measurement.clone()),
+    }
+}
+
+/// Concurrency needed to sustain the goal TPS at the given transaction latency, by Little's Law
+fn estimate_concurrency(goal_tps: f64, latency: Duration) -> usize {
+    (goal_tps * latency.as_secs_f64()).ceil() as usize
+}
+
+/// TPS limit to fall back to once the Scenario is underpowered, slightly below the best measured
+/// TPS. Clamps to 1 TPS, since measured TPS can fall below 1 on heavily constrained machines.
+fn underpowered_tps(measured_tps: f64) -> NonZeroU32 {
+    NonZeroU32::new((measured_tps * 0.9).ceil() as u32).unwrap_or(NonZeroU32::MIN)
+}
+
+/// Whether TPS is still increasing with concurrency, and if not, what is holding it back.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Saturation {
+    Scaling,
+    /// TPS plateaued while latency or errors climbed
+    Service,
+    /// TPS plateaued without latency or errors climbing
+    Generator,
+}
+
+fn detect_saturation(history: &[(usize, Stats)]) -> Saturation {
+    let mut plateaued = false;
+    for pair in history.windows(2) {
+        let ((c0, s0), (c1, s1)) = (pair[0], pair[1]);
+
+        // NOTE: Concurrency can stay the same (e.g. at the maximum concurrency), which says
+        // nothing about how TPS scales, and can decrease with the PID controller. Likewise, no
+        // TPS at either concurrency (e.g. transactions slower than the sample interval) is no
+        // evidence of a plateau.
+        if c0 == c1 || c0 == 0 || (s0.mean <= 0. && s1.mean <= 0.) {
+            continue;
+        }
+
+        // NOTE: The change in TPS is relative to the change in concurrency, so that this works
+        // the same whether each task manages thousands of TPS or a fraction of one.
+        let concurrency_change = (c1 as f64 - c0 as f64) / c0 as f64;
+        let tps_change = if s0.mean > 0. {
+            (s1.mean - s0.mean) / s0.mean
+        } else {
+            f64::INFINITY
+        };
+        let elasticity = tps_change / concurrency_change;
+        trace!(
+            "({c0}, {:.2}) -> ({c1}, {:.2}): elasticity of {elasticity:.2}",
+            s0.mean,
+            s1.mean
+        );
+
+        if elasticity.is_nan() || elasticity >= PLATEAU_ELASTICITY {
+            return Saturation::Scaling;
+        }
+        plateaued = true;
+    }
+
+    let (Some((_, first)), Some((_, last)), true) = (history.first(), history.last(), plateaued)
+    else {
+        return Saturation::Scaling;
+    };
+
+    if last.latency.as_secs_f64() > first.latency.as_secs_f64() * SATURATED_LATENCY_RATIO
+        || last.error_rate > first.error_rate + SATURATED_ERROR_RATE
+    {
+        Saturation::Service
+    } else {
+        Saturation::Generator
     }
 }
 
-fn detect_zero_slope(values: &[(usize, f64)]) -> bool {
-    let slopes: Vec<_> = values
-        .windows(2)
-        .map(|arr| {
-            let (c0, t0) = arr[0];
-            let (c1, t1) = arr[1];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_scenario;
+    use balter_core::ConcurrencyStep;
+    use std::future::Future;
+    use std::sync::Arc;
+
+    async fn settled_interval<T, F>(scenario: T) -> Duration
+    where
+        T: Fn() -> F + Send + Sync + 'static + Clone,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let mut config = ScenarioConfig::new("");
+        config.sampler.interval = Some(Duration::from_millis(50));
+        config.sampler.interval_bounds =
+            Some((Duration::from_millis(50), Duration::from_millis(800)));
+        config.hints.concurrency = 10;
+
+        let mut sampler = Sampler::new(
+            scenario,
+            NonZeroU32::new(100).unwrap(),
+            &config,
+            StateMap::default(),
+        )
+        .await;
+        for _ in 0..3 {
+            let _ = sampler.sample().await;
+        }
+        let interval = sampler.sampler.interval();
+        sampler.shutdown().await;
+        interval
+    }
+
+    #[test]
+    fn test_underpowered_tps() {
+        assert_eq!(underpowered_tps(1_000.).get(), 900);
+        assert_eq!(underpowered_tps(0.4).get(), 1);
+        assert_eq!(underpowered_tps(0.).get(), 1);
+        assert_eq!(underpowered_tps(f64::NAN).get(), 1);
+    }
+
+    #[test]
+    fn test_detect_saturation() {
+        let history = |points: &[(usize, f64, u64, f64)]| -> Vec<(usize, Stats)> {
+            points
+                .iter()
+                .map(|&(concurrency, mean, latency_ms, error_rate)| {
+                    let stats = Stats {
+                        mean,
+                        std: 0.,
+                        outlier_count: 0,
+                        latency: Duration::from_millis(latency_ms),
+                        error_rate,
+                    };
+                    (concurrency, stats)
+                })
+                .collect()
+        };
+
+        // Generator-bound: TPS climbs with concurrency, at any scale
+        let scaling = history(&[
+            (10, 1_000., 10, 0.),
+            (20, 1_900., 10, 0.),
+            (30, 2_700., 11, 0.),
+        ]);
+        assert_eq!(detect_saturation(&scaling), Saturation::Scaling);
+        let scaling = history(&[(10, 0.1, 10, 0.), (20, 0.2, 10, 0.), (30, 0.3, 10, 0.)]);
+        assert_eq!(detect_saturation(&scaling), Saturation::Scaling);
+
+        // Service-bound: TPS plateaus while latency or errors climb
+        let latency = history(&[
+            (10, 1_000., 10, 0.),
+            (20, 1_020., 20, 0.),
+            (30, 1_010., 30, 0.),
+        ]);
+        assert_eq!(detect_saturation(&latency), Saturation::Service);
+        let errors = history(&[
+            (10, 1_000., 1, 0.),
+            (20, 1_000., 1, 0.3),
+            (30, 990., 1, 0.5),
+        ]);
+        assert_eq!(detect_saturation(&errors), Saturation::Service);
+
+        // TPS plateaus while the service looks healthy
+        let generator = history(&[(10, 0.5, 10, 0.), (20, 0.5, 10, 0.), (30, 0.52, 10, 0.)]);
+        assert_eq!(detect_saturation(&generator), Saturation::Generator);
+
+        // Unchanged concurrency says nothing about scaling, and decreasing concurrency still
+        // detects the plateau.
+        let unchanged = history(&[(10, 500., 10, 0.), (10, 500., 10, 0.), (10, 500., 10, 0.)]);
+        assert_eq!(detect_saturation(&unchanged), Saturation::Scaling);
+        let decreasing = history(&[(30, 600., 30, 0.), (20, 600., 20, 0.), (10, 590., 10, 0.)]);
+        assert_eq!(detect_saturation(&decreasing), Saturation::Generator);
+        let no_tps = history(&[(10, 0., 10, 0.), (20, 0., 10, 0.), (30, 0., 10, 0.)]);
+        assert_eq!(detect_saturation(&no_tps), Saturation::Scaling);
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_concurrency_scaling() {
+        let mut config = ScenarioConfig::new("");
+        config.sampler.interval = Some(Duration::from_millis(50));
+        config.hints.concurrency = 4;
 
-            let slope = (t1 - t0) / (c1 - c0) as f64;
+        // Each task can only manage ~1000 TPS, so more are needed to reach the goal
+        let mut sampler = Sampler::new(
+            mock_scenario!(Duration::from_millis(1), Duration::from_micros(10)),
+            NonZeroU32::new(20_000).unwrap(),
+            &config,
+            StateMap::default(),
+        )
+        .await;
+        let _ = sampler.sample().await;
+        let _ = sampler.sample().await;
+        assert!(sampler.concurrency() > 4);
 
-            if slope.is_nan() {
-                error!("NaN Slope detected. Ignoring.");
-                return 0.;
+        let summary = sampler.shutdown().await.concurrency_summary;
+        assert_eq!(summary.min, 4);
+        assert!(summary.max > 4);
+        assert!(summary.changes > 0);
+    }
+
+    #[tokio::test]
+    async fn test_stuck_concurrency() {
+        let mut config = ScenarioConfig::new("");
+        config.sampler.controller = ControllerKind::Pid(balter_core::PidGains::default());
+        config.hints.concurrency = 1;
+
+        let mut sampler = Sampler::new(
+            mock_scenario!(Duration::from_millis(20), Duration::from_micros(10)),
+            NonZeroU32::new(100).unwrap(),
+            &config,
+            StateMap::default(),
+        )
+        .await;
+
+        // A single task at half the goal TPS: the PID controller alone rounds back down to it
+        let stats = Stats {
+            mean: 50.,
+            std: 0.,
+            outlier_count: 0,
+            latency: Duration::from_millis(20),
+            error_rate: 0.,
+        };
+        let mut pid = PidController::new(balter_core::PidGains::default());
+        for _ in 0..4 {
+            assert_eq!(pid.next_concurrency(1, stats.mean, 100.), 1);
+        }
+
+        for concurrency in 2..=4 {
+            sampler.adjust_concurrency(stats);
+            assert_eq!(sampler.concurrency(), concurrency);
+        }
+
+        // Once at the goal TPS, the controller is left to settle the concurrency
+        let at_goal = Stats {
+            mean: 100.,
+            ..stats
+        };
+        sampler.adjust_concurrency(at_goal);
+        assert!(sampler.concurrency() <= 4);
+        sampler.shutdown().await;
+    }
+
+    #[test]
+    fn test_estimate_concurrency() {
+        assert_eq!(estimate_concurrency(1_000., Duration::from_millis(10)), 10);
+        assert_eq!(
+            estimate_concurrency(50_000., Duration::from_millis(10)),
+            500
+        );
+        assert_eq!(estimate_concurrency(10., Duration::from_micros(10)), 1);
+    }
+
+    #[tokio::test]
+    async fn test_warmup() {
+        /// Number of samples taken until the sampler stabilizes at the goal TPS
+        async fn samples_to_converge(skip_warmup: bool) -> usize {
+            let mut config = ScenarioConfig::new("");
+            config.sampler.interval = Some(Duration::from_millis(50));
+            config.sampler.window = 3;
+            config.sampler.concurrency_step = ConcurrencyStep::Absolute(2);
+            config.sampler.skip_warmup = skip_warmup;
+
+            // Each task can only manage ~50 TPS, so ~18 are needed to reach the goal
+            let mut sampler = Sampler::new(
+                mock_scenario!(Duration::from_millis(20), Duration::from_micros(10)),
+                NonZeroU32::new(900).unwrap(),
+                &config,
+                StateMap::default(),
+            )
+            .await;
+
+            let mut samples = 0;
+            for _ in 0..20 {
+                samples += 1;
+                if sampler.sample().await.0 {
+                    break;
+                }
+            }
+            sampler.shutdown().await;
+            samples
+        }
+
+        let with_warmup = samples_to_converge(false).await;
+        let without_warmup = samples_to_converge(true).await;
+        assert!(
+            with_warmup < without_warmup,
+            "with warmup: {with_warmup}, without: {without_warmup}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fixed_concurrency() {
+        let mut config = ScenarioConfig::new("");
+        config.sampler.interval = Some(Duration::from_millis(50));
+        config.sampler.controller = ControllerKind::Fixed;
+        config.hints.concurrency = 5;
+
+        // Each task can only manage ~100 TPS, far short of the goal
+        let mut sampler = Sampler::new(
+            mock_scenario!(Duration::from_millis(10), Duration::from_micros(10)),
+            NonZeroU32::new(10_000).unwrap(),
+            &config,
+            StateMap::default(),
+        )
+        .await;
+        for _ in 0..4 {
+            let _ = sampler.sample().await;
+            assert_eq!(sampler.concurrency(), 5);
+        }
+
+        let stats = sampler.shutdown().await;
+        assert_eq!(stats.concurrency, 5);
+        assert_eq!(
+            stats.concurrency_summary,
+            ConcurrencySummary {
+                min: 5,
+                max: 5,
+                median: 5,
+                mode: 5,
+                changes: 0,
+            }
+        );
+        assert_eq!(stats.tps_limit.get(), 10_000);
+        assert!(!stats.tps_limited);
+        let tps = stats.run_measurement.unwrap().tps;
+        assert!(tps > 100. && tps < 1_000., "tps: {tps}");
+    }
+
+    #[tokio::test]
+    async fn test_deadline() {
+        let mut config = ScenarioConfig::new("");
+        config.sampler.interval = Some(Duration::from_millis(200));
+
+        let mut sampler = Sampler::new(
+            mock_scenario!(Duration::from_millis(1), Duration::from_micros(10)),
+            NonZeroU32::new(500).unwrap(),
+            &config,
+            StateMap::default(),
+        )
+        .await;
+
+        let start = Instant::now();
+        sampler.set_deadline(start + Duration::from_millis(1_100));
+        loop {
+            let (_, measurement) = sampler.sample().await;
+            if measurement.elapsed < Duration::from_millis(150) {
+                break;
             }
+        }
+
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed >= Duration::from_millis(1_100) && elapsed < Duration::from_millis(1_150),
+            "elapsed: {elapsed:?}"
+        );
+        sampler.shutdown().await;
+    }
 
-            let b = t1 - slope * c0 as f64;
-            trace!("({}, {:.2}), ({}, {:.2})", c0, t0, c1, t1,);
-            trace!("y = {:.2}x + {:.2}", slope, b);
+    #[tokio::test]
+    async fn test_tps_period() {
+        let mut config = ScenarioConfig::new("");
+        config.sampler.interval = Some(Duration::from_millis(500));
+        config.sampler.controller = ControllerKind::Fixed;
+        config.tps_period = Some(Duration::from_millis(250));
 
-            slope
-        })
-        .collect();
+        let mut sampler = Sampler::new(
+            mock_scenario!(Duration::from_millis(1), Duration::from_micros(10)),
+            NonZeroU32::MIN,
+            &config,
+            StateMap::default(),
+        )
+        .await;
+        for _ in 0..2 {
+            let (_, measurement) = sampler.sample().await;
+            assert!(!measurement.error_rate.is_nan());
+        }
+
+        let stats = sampler.shutdown().await;
+        let measurement = stats.run_measurement.unwrap();
+        assert_eq!(measurement.error, 0);
+        assert!(
+            measurement.tps > 3. && measurement.tps < 5.,
+            "tps: {}",
+            measurement.tps
+        );
+    }
 
-    slopes.iter().all(|m| *m < 1.)
+    #[tokio::test]
+    async fn test_tps_tolerance() {
+        async fn at_goal(tolerance: f64, mean: f64, std: f64) -> bool {
+            let mut config = ScenarioConfig::new("");
+            config.sampler.tps_tolerance = tolerance;
+            let sampler = Sampler::new(
+                mock_scenario!(Duration::from_millis(1), Duration::from_micros(10)),
+                NonZeroU32::new(1_000).unwrap(),
+                &config,
+                StateMap::default(),
+            )
+            .await;
+
+            let at_goal = sampler.at_goal(Stats {
+                mean,
+                std,
+                outlier_count: 0,
+                latency: Duration::from_millis(1),
+                error_rate: 0.,
+            });
+            sampler.shutdown().await;
+            at_goal
+        }
+
+        // Held at 900 TPS, 10% short of the goal
+        assert!(!at_goal(0.05, 900., 0.).await);
+        assert!(at_goal(0.15, 900., 0.).await);
+
+        // The spread across samples counts towards the goal
+        assert!(at_goal(0.05, 900., 60.).await);
+        assert!(at_goal(0.05, 950., 0.).await);
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrency() {
+        let mut config = ScenarioConfig::new("");
+        config.sampler.interval = Some(Duration::from_millis(50));
+        config.sampler.max_concurrency = Some(20);
+
+        // Each task can only manage ~100 TPS, far short of the goal
+        let mut sampler = Sampler::new(
+            mock_scenario!(Duration::from_millis(10), Duration::from_micros(10)),
+            NonZeroU32::new(10_000).unwrap(),
+            &config,
+            StateMap::default(),
+        )
+        .await;
+        for _ in 0..4 {
+            let _ = sampler.sample().await;
+            assert!(sampler.concurrency() <= 20);
+        }
+
+        let stats = sampler.shutdown().await;
+        assert!(stats.tps_limited);
+        assert!(stats.concurrency_limited);
+        assert_eq!(stats.concurrency, 20);
+        assert!(stats.tps_limit.get() < 2_000);
+    }
+
+    #[tokio::test]
+    async fn test_latency_backoff() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let mut config = ScenarioConfig::new("");
+        config.sampler.interval = Some(Duration::from_millis(50));
+        config.sampler.interval_bounds =
+            Some((Duration::from_millis(50), Duration::from_millis(50)));
+        config.sampler.latency_backoff = Some(3.);
+        config.hints.concurrency = 20;
+
+        let spike = Arc::new(AtomicBool::new(false));
+        let scenario_spike = spike.clone();
+        let mut sampler = Sampler::new(
+            move || {
+                let spike = scenario_spike.clone();
+                async move {
+                    let latency = if spike.load(Ordering::Relaxed) {
+                        Duration::from_millis(20)
+                    } else {
+                        Duration::from_millis(2)
+                    };
+                    mock_scenario!(latency, Duration::from_micros(10))().await;
+                }
+            },
+            NonZeroU32::new(2_000).unwrap(),
+            &config,
+            StateMap::default(),
+        )
+        .await;
+
+        for _ in 0..3 {
+            let _ = sampler.sample().await;
+        }
+        let concurrency = sampler.concurrency();
+        assert!(concurrency >= 4, "concurrency: {concurrency}");
+
+        // The spike is reacted to within a single measurement, rather than a sample window
+        spike.store(true, Ordering::Relaxed);
+        let started = Instant::now();
+        let (stable, _) = sampler.sample().await;
+        assert!(!stable);
+        assert!(started.elapsed() < Duration::from_millis(200));
+        assert_eq!(sampler.concurrency(), concurrency / 2);
+
+        sampler.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_latency_interval() {
+        let mut config = ScenarioConfig::new("");
+        config.sampler.interval = Some(Duration::from_millis(200));
+        config.sampler.controller = ControllerKind::Fixed;
+        config.hints.concurrency = 10;
+
+        // Each task completes ~10 transactions per second, so ~100 TPS in total
+        let mut sampler = Sampler::new(
+            mock_scenario!(Duration::from_millis(100), Duration::from_micros(10)),
+            NonZeroU32::new(10_000).unwrap(),
+            &config,
+            StateMap::default(),
+        )
+        .await;
+
+        // NOTE: Transactions are only counted once they finish, so a 200ms sample only sees ~2
+        // transactions per task, and whether one lands just before or after the end of the
+        // sample swings the measured TPS by 50%.
+        let _ = sampler.sample().await;
+        let interval = sampler.sampler.interval();
+        assert!(interval >= Duration::from_secs(1), "interval: {interval:?}");
+
+        let (_, measurement) = sampler.sample().await;
+        assert!(
+            (measurement.tps - 100.).abs() < 10.,
+            "tps: {}",
+            measurement.tps
+        );
+        sampler.shutdown().await;
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_adaptive_interval() {
+        let trivial = settled_interval(mock_scenario!(
+            Duration::from_millis(1),
+            Duration::from_micros(10)
+        ))
+        .await;
+        let noisy = settled_interval(mock_scenario!(
+            Duration::from_millis(50),
+            Duration::from_millis(50)
+        ))
+        .await;
+
+        assert!(noisy > trivial, "noisy: {noisy:?}, trivial: {trivial:?}");
+    }
 }
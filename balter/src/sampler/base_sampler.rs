@@ -1,43 +1,232 @@
+use super::catch_unwind::{panic_message, CatchUnwind};
 use super::task_atomics::TaskAtomics;
 use super::timer::Timer;
 use crate::measurement::Measurement;
-use crate::transaction::TRANSACTION_HOOK;
+use crate::metric_keys::scenario_metric;
+use crate::metrics::{
+    current_labels, current_recorder, with_labels, ScenarioLabels, SharedRecorder, WithRecorder,
+};
+use crate::rng::{task_rng, TASK_RNG};
+use crate::scenario::{ScenarioFn, SingleThreaded};
+use crate::state::{StateMap, TASK_STATE};
+use crate::transaction::{TransactionData, TRANSACTION_HOOK};
+use balter_core::{Arrival, ErrorCounts, ErrorKind, RetryPolicy, TpsTarget};
+use rand::rngs::SmallRng;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::future::Future;
 use std::num::NonZeroU32;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc,
+};
+use std::time::Duration;
+use tokio::sync::watch;
 use tokio::task::JoinHandle;
 #[allow(unused)]
 use tracing::{debug, error, info, trace, warn};
 
+/// Number of tasks spawned at a time when raising the concurrency
+const SPAWN_BATCH: usize = 256;
+
 pub(crate) struct BaseSampler<T> {
-    base_label: String,
+    name: String,
     scenario: T,
-    tasks: Vec<JoinHandle<()>>,
+    tasks: Vec<WorkerHandle>,
+    /// Number of tasks still to be spawned to reach the concurrency last set
+    pending: usize,
+    /// Tasks signalled to stop after lowering the concurrency, which haven't finished yet
+    stopping: Vec<JoinHandle<()>>,
     timer: Timer,
     task_atomics: TaskAtomics,
+    running: Arc<AtomicBool>,
+    /// Total iterations to run, and the number started so far
+    iterations: Option<(u64, Arc<AtomicU64>)>,
+    /// Total number of times the scenario has panicked
+    panics: Arc<AtomicU64>,
+    seed: Option<u64>,
+    state: StateMap,
+    /// Whether the tasks are paused, in which case they stay idle rather than running the scenario
+    paused: watch::Receiver<bool>,
+}
+
+/// Spawns the worker tasks which run a scenario.
+///
+/// Scenario functions are spawned onto the tokio runtime by default, which requires the futures
+/// they return to be `Send`. [SingleThreaded] scenarios are instead spawned onto the current
+/// [LocalSet](tokio::task::LocalSet), and [ScenarioFn]s (which can't implement `Fn`) are called
+/// through their boxed closure.
+pub(crate) trait SpawnWorker: Clone + Send + Sync + 'static {
+    fn spawn_worker(self, worker: Worker) -> JoinHandle<()>;
 }
 
-impl<T, F> BaseSampler<T>
+impl<T, F> SpawnWorker for T
 where
     T: Fn() -> F + Send + Sync + 'static + Clone,
-    F: Future<Output = ()> + Send,
+    F: Future<Output = ()> + Send + 'static,
 {
-    pub async fn new(name: &str, scenario: T, tps_limit: NonZeroU32) -> Self {
-        let interval = if tps_limit.get() < 150 {
-            balter_core::BASE_INTERVAL_SLOW
-        } else {
-            balter_core::BASE_INTERVAL
+    fn spawn_worker(self, worker: Worker) -> JoinHandle<()> {
+        tokio::spawn(worker.run(self))
+    }
+}
+
+impl<T, F> SpawnWorker for SingleThreaded<T>
+where
+    T: Fn() -> F + Send + Sync + 'static + Clone,
+    F: Future<Output = ()> + 'static,
+{
+    fn spawn_worker(self, worker: Worker) -> JoinHandle<()> {
+        tokio::task::spawn_local(worker.run(self.0))
+    }
+}
+
+impl SpawnWorker for ScenarioFn {
+    fn spawn_worker(self, worker: Worker) -> JoinHandle<()> {
+        tokio::spawn(worker.run(move || (self.0)()))
+    }
+}
+
+/// Handle to a spawned worker task, along with the flag signalling it alone to stop
+struct WorkerHandle {
+    handle: JoinHandle<()>,
+    stop: Arc<AtomicBool>,
+}
+
+/// Everything a worker task needs to run the scenario, besides the scenario function itself
+pub(crate) struct Worker {
+    transaction_data: TransactionData,
+    running: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    iterations: Option<(u64, Arc<AtomicU64>)>,
+    panics: Arc<AtomicU64>,
+    state: StateMap,
+    rng: RefCell<SmallRng>,
+    scenario_name: String,
+    recorder: Option<SharedRecorder>,
+    labels: Option<ScenarioLabels>,
+    paused: watch::Receiver<bool>,
+}
+
+impl Worker {
+    async fn run<T, F>(self, scenario: T)
+    where
+        T: Fn() -> F,
+        F: Future<Output = ()>,
+    {
+        let Worker {
+            transaction_data,
+            running,
+            stop,
+            iterations,
+            panics,
+            state,
+            rng,
+            scenario_name,
+            recorder,
+            labels,
+            mut paused,
+        } = self;
+        let hook = transaction_data.clone();
+
+        let run = TASK_STATE.scope(
+            state,
+            TASK_RNG.scope(
+                rng,
+                TRANSACTION_HOOK.scope(transaction_data, async move {
+                    // NOTE: We have an outer loop just in case the user-provided
+                    // scenario does not have a loop.
+                    while running.load(Ordering::Relaxed) && !stop.load(Ordering::Relaxed) {
+                        if *paused.borrow() {
+                            // NOTE: Once the sender is dropped nothing can resume the tasks, and
+                            // waiting would return straight away, so they carry on as if resumed
+                            // rather than spinning on the closed channel.
+                            if paused.wait_for(|paused| !paused).await.is_err() {
+                                paused = watch::channel(false).1;
+                            }
+                            continue;
+                        }
+
+                        if let Some((limit, started)) = &iterations {
+                            if started.fetch_add(1, Ordering::Relaxed) >= *limit {
+                                break;
+                            }
+                        }
+
+                        if hook.tps_target == TpsTarget::Scenarios {
+                            hook.acquire().await;
+                        }
+
+                        // NOTE: A panicking scenario would otherwise kill the task and
+                        // silently lower the concurrency, so we count it as an error and
+                        // carry on with the next run. The scenario function is called within
+                        // the future, to also catch panics before it returns its own.
+                        if let Err(panic) = CatchUnwind::new(async { scenario().await }).await {
+                            error!("Scenario panicked: {}", panic_message(&*panic));
+                            panics.fetch_add(1, Ordering::Relaxed);
+                            hook.record_failure(ErrorKind::Panic);
+                            if cfg!(feature = "metrics") {
+                                let (name, labels) =
+                                    scenario_metric("balter_scenario_panic_count", &scenario_name);
+                                metrics::counter!(name, labels).increment(1);
+                            }
+                        }
+                        hook.scenarios.fetch_add(1, Ordering::Relaxed);
+                    }
+                }),
+            ),
+        );
+        WithRecorder::new(recorder, with_labels(labels, run)).await
+    }
+}
+
+impl<T: SpawnWorker> BaseSampler<T> {
+    pub async fn new(
+        name: &str,
+        scenario: T,
+        tps_limit: NonZeroU32,
+        interval: Option<Duration>,
+    ) -> Self {
+        let interval = match interval {
+            Some(interval) if interval < balter_core::MIN_INTERVAL => {
+                warn!(
+                    "Sample interval of {interval:?} is below the minimum; using {:?} instead.",
+                    balter_core::MIN_INTERVAL
+                );
+                balter_core::MIN_INTERVAL
+            }
+            Some(interval) => interval,
+            None if tps_limit.get() < 150 => balter_core::BASE_INTERVAL_SLOW,
+            None => balter_core::BASE_INTERVAL,
         };
         let timer = Timer::new(interval).await;
         Self {
-            base_label: format!("balter_{name}"),
+            name: name.to_string(),
             scenario,
             tasks: vec![],
+            pending: 0,
+            stopping: vec![],
             timer,
-            task_atomics: TaskAtomics::new(tps_limit),
+            task_atomics: TaskAtomics::new(name, tps_limit),
+            running: Arc::new(AtomicBool::new(true)),
+            iterations: None,
+            panics: Arc::new(AtomicU64::new(0)),
+            seed: None,
+            state: StateMap::default(),
+            paused: watch::channel(false).1,
         }
     }
 
+    /// Start the next sample interval afresh, discarding anything measured since the last
+    /// sample.
+    pub async fn restart(&mut self) {
+        self.timer.restart().await;
+        let _ = self.task_atomics.collect(Duration::ZERO);
+    }
+
     pub async fn sample(&mut self) -> Measurement {
+        self.respawn_dead().await;
+        self.spawn_pending().await;
+        self.await_stopping().await;
         let elapsed = self.timer.tick().await;
         let measurements = self.task_atomics.collect(elapsed);
         trace!("{measurements}");
@@ -46,7 +235,8 @@ where
 
     pub fn set_tps_limit(&mut self, tps_limit: NonZeroU32) {
         if cfg!(feature = "metrics") {
-            metrics::gauge!(format!("{}_goal_tps", &self.base_label)).set(tps_limit.get());
+            let (name, labels) = scenario_metric("balter_scenario_goal_tps", &self.name);
+            metrics::gauge!(name, labels).set(tps_limit.get());
         }
 
         self.task_atomics.set_tps_limit(tps_limit);
@@ -56,106 +246,973 @@ where
         self.task_atomics.tps_limit()
     }
 
+    /// Limit the scenario to one transaction per `period`, for rates below 1 TPS
+    pub fn set_tps_period(&mut self, period: Duration) {
+        self.task_atomics.set_tps_period(period);
+    }
+
+    pub fn set_burst(&mut self, burst: NonZeroU32) {
+        self.task_atomics.set_burst(burst);
+    }
+
+    pub fn set_arrival(&mut self, arrival: Arrival) {
+        self.task_atomics.set_arrival(arrival);
+    }
+
+    /// Cap the TPS of individual transactions, by transaction name. Must be called before any
+    /// tasks are spawned.
+    pub fn set_transaction_tps(&mut self, caps: &BTreeMap<String, NonZeroU32>) {
+        debug_assert!(self.tasks.is_empty());
+        self.task_atomics.set_transaction_tps(caps);
+    }
+
+    /// Set whether the rate limiter paces transactions or runs of the scenario. Must be called
+    /// before any tasks are spawned.
+    pub fn set_tps_target(&mut self, tps_target: TpsTarget) {
+        debug_assert!(self.tasks.is_empty());
+        self.task_atomics.set_tps_target(tps_target);
+    }
+
+    /// Report the measured output of the scenario, which is only used for metrics.
+    pub fn set_output(&self, tps: f64, scenario_tps: f64, error_rate: f64) {
+        if cfg!(feature = "metrics") {
+            let (name, labels) = scenario_metric("balter_scenario_output_tps", &self.name);
+            metrics::gauge!(name, labels).set(tps);
+            let (name, labels) = scenario_metric("balter_scenario_iteration_rate", &self.name);
+            metrics::gauge!(name, labels).set(scenario_tps);
+            let (name, labels) = scenario_metric("balter_scenario_error_rate", &self.name);
+            metrics::gauge!(name, labels).set(error_rate);
+        }
+    }
+
     pub fn set_concurrency(&mut self, concurrency: usize) {
         if cfg!(feature = "metrics") {
-            metrics::gauge!(format!("{}_concurrency", &self.base_label)).set(concurrency as f64);
+            let (name, labels) = scenario_metric("balter_scenario_concurrency", &self.name);
+            metrics::gauge!(name, labels).set(concurrency as f64);
         }
 
-        #[allow(clippy::comparison_chain)]
-        if self.tasks.len() == concurrency {
-            #[allow(clippy::needless_return)]
-            return;
-        } else if self.tasks.len() > concurrency {
-            for handle in self.tasks.drain(concurrency..) {
-                handle.abort();
+        if self.tasks.len() >= concurrency {
+            self.pending = 0;
+            for task in self.tasks.drain(concurrency..) {
+                task.stop.store(true, Ordering::Relaxed);
+                self.stopping.push(task.handle);
             }
         } else {
-            while self.tasks.len() < concurrency {
-                let scenario = self.scenario.clone();
-                let transaction_data = self.task_atomics.clone_to_transaction_data();
+            self.pending = concurrency - self.tasks.len();
+            self.spawn_batch();
+        }
+    }
 
-                self.tasks.push(tokio::spawn(TRANSACTION_HOOK.scope(
-                    transaction_data,
-                    async move {
-                        // NOTE: We have an outer loop just in case the user-provided
-                        // scenario does not have a loop.
-                        loop {
-                            scenario().await;
-                        }
-                    },
-                )));
+    /// Set the concurrency to start at, leaving the tasks to be spawned at the first sample.
+    pub fn set_initial_concurrency(&mut self, concurrency: usize) {
+        debug_assert!(self.tasks.is_empty());
+        if cfg!(feature = "metrics") {
+            let (name, labels) = scenario_metric("balter_scenario_concurrency", &self.name);
+            metrics::gauge!(name, labels).set(concurrency as f64);
+        }
+        self.pending = concurrency;
+    }
+
+    /// Spawn the remaining tasks in batches while waiting for the next sample, yielding between
+    /// batches so that the spawned tasks (and the rest of the runtime) can make progress.
+    ///
+    /// NOTE: Spawning thousands of tasks at once can take long enough to delay the sample,
+    /// skewing its measurements. Any tasks which haven't been spawned by the time the sample is
+    /// due are left for the next one.
+    async fn spawn_pending(&mut self) {
+        let next_tick = self.timer.next_tick();
+        while self.pending > 0 && tokio::time::Instant::now() < next_tick {
+            tokio::task::yield_now().await;
+            self.spawn_batch();
+        }
+        if self.pending > 0 {
+            debug!("{} tasks left to spawn at the next sample", self.pending);
+        }
+    }
+
+    /// Wait for the tasks signalled to stop to finish their current scenario run, up until the
+    /// next sample is due, at which point any still running are aborted.
+    ///
+    /// NOTE: Aborting tasks outright would cut their transactions short without recording them,
+    /// while leaving them running would keep generating load the concurrency no longer accounts
+    /// for.
+    async fn await_stopping(&mut self) {
+        let next_tick = self.timer.next_tick();
+        let mut aborted = 0;
+        for mut handle in self.stopping.drain(..) {
+            if tokio::time::timeout_at(next_tick, &mut handle)
+                .await
+                .is_err()
+            {
+                handle.abort();
+                aborted += 1;
+            }
+        }
+        if aborted > 0 {
+            debug!("Aborted {aborted} tasks which did not stop before the next sample");
+        }
+    }
+
+    /// Replace any tasks which died while they should still be running, so that the
+    /// concurrency holds.
+    ///
+    /// NOTE: Panics in the scenario are caught within the task, so this only happens if the task
+    /// is cut short some other way (e.g. a panic while dropping a scenario future).
+    async fn respawn_dead(&mut self) {
+        let iterations_started = self
+            .iterations
+            .as_ref()
+            .is_some_and(|(limit, started)| started.load(Ordering::Relaxed) >= *limit);
+        if !self.running.load(Ordering::Relaxed) || iterations_started {
+            return;
+        }
+
+        let mut respawned = 0;
+        for index in 0..self.tasks.len() {
+            if !self.tasks[index].handle.is_finished() {
+                continue;
+            }
+
+            let task = self.spawn_worker(index);
+            let dead = std::mem::replace(&mut self.tasks[index], task);
+            if let Err(err) = dead.handle.await {
+                if err.is_panic() {
+                    error!("Task panicked: {}", panic_message(&*err.into_panic()));
+                    self.panics.fetch_add(1, Ordering::Relaxed);
+                }
             }
+            respawned += 1;
+        }
+        if respawned > 0 {
+            warn!("Respawned {respawned} tasks which died unexpectedly.");
+        }
+    }
+
+    fn spawn_batch(&mut self) {
+        for _ in 0..self.pending.min(SPAWN_BATCH) {
+            let task = self.spawn_worker(self.tasks.len());
+            self.tasks.push(task);
+            self.pending -= 1;
         }
     }
 
+    /// Spawn the worker task at the given index, which determines its state and RNG
+    fn spawn_worker(&self, index: usize) -> WorkerHandle {
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker = Worker {
+            transaction_data: self.task_atomics.clone_to_transaction_data(),
+            running: self.running.clone(),
+            stop: stop.clone(),
+            iterations: self.iterations.clone(),
+            panics: self.panics.clone(),
+            state: self.state.for_worker(index),
+            rng: task_rng(self.seed, index),
+            scenario_name: self.name.clone(),
+            recorder: current_recorder(),
+            labels: current_labels(),
+            paused: self.paused.clone(),
+        };
+        let handle = self.scenario.clone().spawn_worker(worker);
+        WorkerHandle { handle, stop }
+    }
+
+    /// Limit the total number of times the scenario is run across all tasks. Must be called
+    /// before any tasks are spawned.
+    pub fn set_iterations(&mut self, iterations: u64) {
+        debug_assert!(self.tasks.is_empty());
+        self.iterations = Some((iterations, Arc::new(AtomicU64::new(0))));
+    }
+
+    /// Limit the total number of transactions sent across all tasks. Must be called before any
+    /// tasks are spawned.
+    pub fn set_request_budget(&mut self, budget: u64) {
+        debug_assert!(self.tasks.is_empty());
+        self.task_atomics.set_request_budget(budget);
+    }
+
+    /// Skip the body of each transaction. Must be called before any tasks are spawned.
+    pub fn set_dry_run(&mut self) {
+        debug_assert!(self.tasks.is_empty());
+        self.task_atomics.set_dry_run();
+    }
+
+    /// Seed the RNG of each task. Must be called before any tasks are spawned.
+    pub fn set_seed(&mut self, seed: u64) {
+        debug_assert!(self.tasks.is_empty());
+        self.seed = Some(seed);
+    }
+
+    /// Share the state with each task. Must be called before any tasks are spawned.
+    pub fn set_state(&mut self, state: StateMap) {
+        debug_assert!(self.tasks.is_empty());
+        self.state = state;
+    }
+
+    /// Pause the tasks whenever the flag is set. Must be called before any tasks are spawned.
+    pub fn set_pause(&mut self, paused: watch::Receiver<bool>) {
+        debug_assert!(self.tasks.is_empty());
+        self.paused = paused;
+    }
+
+    /// Whether the tasks are paused. A flag left set when its sender is dropped counts as
+    /// resumed, as the tasks themselves treat it.
+    pub fn paused(&self) -> bool {
+        *self.paused.borrow() && self.paused.has_changed().is_ok()
+    }
+
+    /// Retry failed transactions according to the given policy. Must be called before any
+    /// tasks are spawned.
+    pub fn set_retry_policy(&mut self, retry: RetryPolicy) {
+        debug_assert!(self.tasks.is_empty());
+        self.task_atomics.set_retry_policy(retry);
+    }
+
+    /// Total number of times the scenario has panicked
+    pub fn panics(&self) -> u64 {
+        self.panics.load(Ordering::Relaxed)
+    }
+
+    /// Total number of times the scenario has returned an error
+    pub fn scenario_errors(&self) -> u64 {
+        self.task_atomics.scenario_errors()
+    }
+
+    /// Total transaction errors of each kind
+    pub fn error_counts(&self) -> ErrorCounts {
+        self.task_atomics.error_counts()
+    }
+
+    /// Total number of responses with each HTTP status code
+    pub fn status_codes(&self) -> BTreeMap<u16, u64> {
+        self.task_atomics.status_codes()
+    }
+
+    /// Whether every iteration has been started and all tasks have completed.
+    pub fn iterations_complete(&self) -> bool {
+        self.iterations
+            .as_ref()
+            .is_some_and(|(limit, started)| started.load(Ordering::Relaxed) >= *limit)
+            && self.pending == 0
+            && self.tasks.iter().all(|task| task.handle.is_finished())
+            && self.stopping.iter().all(|handle| handle.is_finished())
+    }
+
+    pub fn budget_exhausted(&self) -> bool {
+        self.task_atomics.budget_exhausted()
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.timer.interval_dur()
+    }
+
+    /// Cut the final sample interval short at the deadline, rather than overrunning it
+    pub fn set_deadline(&mut self, deadline: tokio::time::Instant) {
+        self.timer.set_deadline(deadline);
+    }
+
+    pub fn deadline_reached(&self) -> bool {
+        self.timer.deadline_reached()
+    }
+
+    pub async fn set_interval(&mut self, interval: Duration) {
+        if cfg!(feature = "metrics") {
+            let (name, labels) = scenario_metric("balter_scenario_sample_interval", &self.name);
+            metrics::gauge!(name, labels).set(interval.as_secs_f64());
+        }
+
+        self.timer.set_interval_dur(interval).await;
+    }
+
+    /// Concurrency last set, including any tasks which are still to be spawned
     pub fn concurrency(&self) -> usize {
-        self.tasks.len()
+        self.tasks.len() + self.pending
     }
 
-    pub fn shutdown(mut self) {
-        self.set_concurrency(0);
+    /// Stop all tasks, returning a final measurement of the transactions which completed since
+    /// the last sample (including those still in flight when shutting down). Tasks are signalled
+    /// to stop after their current scenario run, and any still running after `timeout` (such as
+    /// scenarios which loop internally) are aborted.
+    pub async fn shutdown(&mut self, timeout: Duration) -> Measurement {
+        if cfg!(feature = "metrics") {
+            let (name, labels) = scenario_metric("balter_scenario_concurrency", &self.name);
+            metrics::gauge!(name, labels).set(0.);
+        }
+        self.set_output(0., 0., 0.);
+
+        self.running.store(false, Ordering::Relaxed);
+        self.pending = 0;
+
+        // NOTE: Paused tasks have nothing in flight, and would wait out the timeout for a resume.
+        let deadline = if self.paused() {
+            tokio::time::Instant::now()
+        } else {
+            tokio::time::Instant::now() + timeout
+        };
+        let mut aborted = 0;
+        let handles = self.tasks.drain(..).map(|task| task.handle);
+        for mut handle in handles.chain(self.stopping.drain(..)) {
+            if tokio::time::timeout_at(deadline, &mut handle)
+                .await
+                .is_err()
+            {
+                handle.abort();
+                aborted += 1;
+            }
+        }
+
+        // NOTE: Tasks waiting on an exhausted request budget never finish on their own.
+        if aborted > 0 && self.task_atomics.budget_exhausted() {
+            debug!("Aborted {aborted} tasks after the request budget was used up.");
+        } else if aborted > 0 && self.paused() {
+            debug!("Aborted {aborted} paused tasks.");
+        } else if aborted > 0 {
+            warn!("Aborted {aborted} tasks which did not finish within the shutdown timeout of {timeout:?}.");
+        }
+
+        // NOTE: Collecting also records the remaining latencies to the metrics recorder, so that
+        // metrics reflect the whole run once the scenario completes.
+        let measurement = self.task_atomics.collect(self.timer.elapsed());
+        trace!("Final {measurement}");
+        measurement
+    }
+}
+
+/// NOTE: Dropping a [JoinHandle] detaches its task rather than stopping it, so the worker tasks
+/// would otherwise keep running in the background if the Scenario is dropped before it completes
+/// (e.g. when it loses a `select!` or times out).
+impl<T> Drop for BaseSampler<T> {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        let handles = self.tasks.drain(..).map(|task| task.handle);
+        for handle in handles.chain(self.stopping.drain(..)) {
+            handle.abort();
+        }
     }
 }
 
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
-    use rand_distr::{Distribution, SkewNormal};
     use std::time::Duration;
 
     #[macro_export]
     macro_rules! mock_scenario {
-        ($m:expr, $s:expr) => {
-            || async {
-                let labels = balter_core::TransactionLabels {
-                    success: "",
-                    error: "",
-                    latency: "",
-                };
-                let mean: std::time::Duration = $m;
-                let std: std::time::Duration = $s;
-                let _ = $crate::transaction::transaction_hook::<_, (), ()>(labels, async {
-                    let normal =
-                        SkewNormal::new(mean.as_secs_f64(), std.as_secs_f64(), 20.).unwrap();
-                    let v: f64 = normal.sample(&mut rand::thread_rng()).max(0.);
-                    tokio::time::sleep(std::time::Duration::from_secs_f64(v)).await;
-                    Ok(())
-                })
-                .await;
+        ($m:expr, $s:expr) => {{
+            let mock = $crate::testing::MockScenario::new($crate::testing::Latency::SkewNormal {
+                location: $m,
+                scale: $s,
+                shape: 20.,
+            });
+            move || {
+                let mock = mock.clone();
+                async move {
+                    let _ = mock.transaction().await;
+                }
             }
-        };
+        }};
     }
 
     #[tracing_test::traced_test]
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_simple() {
         let mut sampler = BaseSampler::new(
             "",
             mock_scenario!(Duration::from_millis(1), Duration::from_micros(10)),
             NonZeroU32::new(1_000).unwrap(),
+            None,
         )
         .await;
 
+        sampler.set_seed(0);
         sampler.set_concurrency(20);
 
+        // NOTE: With tokio's clock paused, the rate limiter lets through exactly the goal TPS once
+        // the first transactions are no longer in flight across the end of the sample.
+        let _ = sampler.sample().await;
         let sample = sampler.sample().await;
-        assert!(sample.tps >= 990. && sample.tps <= 1_010.);
+        assert_eq!(sample.elapsed, Duration::from_secs(1));
+        assert_eq!(sample.success, 1_000);
     }
 
     #[tracing_test::traced_test]
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_noisy() {
         let mut sampler = BaseSampler::new(
             "",
             mock_scenario!(Duration::from_millis(10), Duration::from_millis(5)),
             NonZeroU32::new(1_000).unwrap(),
+            None,
         )
         .await;
 
+        sampler.set_seed(0);
         sampler.set_concurrency(210);
 
+        let _ = sampler.sample().await;
+        let sample = sampler.sample().await;
+        assert!(sample.tps >= 990. && sample.tps <= 1_010.);
+    }
+
+    #[tokio::test]
+    async fn test_spawn_batches() {
+        let mut sampler = BaseSampler::new(
+            "",
+            mock_scenario!(Duration::from_millis(10), Duration::from_micros(10)),
+            NonZeroU32::new(100_000).unwrap(),
+            None,
+        )
+        .await;
+
+        sampler.set_concurrency(5 * SPAWN_BATCH);
+        assert_eq!(sampler.tasks.len(), SPAWN_BATCH);
+        assert_eq!(sampler.concurrency(), 5 * SPAWN_BATCH);
+
+        let _ = sampler.sample().await;
+        assert_eq!(sampler.tasks.len(), 5 * SPAWN_BATCH);
+
+        sampler.set_concurrency(10);
+        assert_eq!(sampler.tasks.len(), 10);
+        assert_eq!(sampler.concurrency(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_decrease() {
+        let mut sampler = BaseSampler::new(
+            "",
+            mock_scenario!(Duration::from_millis(100), Duration::from_micros(10)),
+            NonZeroU32::new(100_000).unwrap(),
+            None,
+        )
+        .await;
+
+        sampler.set_concurrency(50);
+        let sample = sampler.sample().await;
+        assert!(sample.tps >= 400., "{}", sample.tps);
+
+        // NOTE: The stopped tasks finish their in-flight transactions during the next sample, so
+        // the load only drops fully from the one after.
+        sampler.set_concurrency(5);
+        let _ = sampler.sample().await;
+        assert!(sampler.stopping.is_empty());
+
+        let sample = sampler.sample().await;
+        assert!(sample.tps <= 55., "{}", sample.tps);
+        assert_eq!(sampler.concurrency(), 5);
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_shutdown_timeout() {
+        let mut sampler = BaseSampler::new(
+            "",
+            || async {
+                // Scenario which never returns
+                loop {
+                    tokio::task::yield_now().await;
+                }
+            },
+            NonZeroU32::new(1_000).unwrap(),
+            None,
+        )
+        .await;
+
+        sampler.set_concurrency(20);
+        let _ = sampler.sample().await;
+
+        let timeout = Duration::from_millis(200);
+        let start = std::time::Instant::now();
+        sampler.shutdown(timeout).await;
+        let elapsed = start.elapsed();
+
+        assert!(elapsed >= timeout);
+        assert!(elapsed < timeout + Duration::from_millis(100));
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_shutdown_graceful() {
+        let mut sampler = BaseSampler::new(
+            "",
+            mock_scenario!(Duration::from_millis(1), Duration::from_micros(10)),
+            NonZeroU32::new(1_000).unwrap(),
+            None,
+        )
+        .await;
+
+        sampler.set_concurrency(20);
+        let _ = sampler.sample().await;
+
+        let start = std::time::Instant::now();
+        sampler.shutdown(Duration::from_secs(5)).await;
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_flush() {
+        let mut sampler = BaseSampler::new(
+            "",
+            mock_scenario!(Duration::from_millis(50), Duration::from_micros(10)),
+            NonZeroU32::new(1_000).unwrap(),
+            None,
+        )
+        .await;
+
+        sampler.set_concurrency(10);
+        let _ = sampler.sample().await;
+
+        // NOTE: Every task has a transaction in flight when shutting down
+        let measurement = sampler.shutdown(Duration::from_secs(5)).await;
+        assert_eq!(measurement.success, 10);
+        assert!(measurement.latency(0.5) >= Duration::from_micros(49_500));
+        assert_eq!(sampler.concurrency(), 0);
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_low_tps_interval() {
+        let mut sampler = BaseSampler::new(
+            "",
+            mock_scenario!(Duration::from_millis(1), Duration::from_micros(10)),
+            NonZeroU32::new(20).unwrap(),
+            Some(Duration::from_secs(1)),
+        )
+        .await;
+        assert_eq!(sampler.timer.interval_dur(), Duration::from_secs(1));
+
+        sampler.set_concurrency(5);
+
+        for _ in 0..3 {
+            let sample = sampler.sample().await;
+            assert!(sample.tps >= 18. && sample.tps <= 22.);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_min_interval() {
+        let sampler = BaseSampler::new(
+            "",
+            mock_scenario!(Duration::from_millis(1), Duration::from_micros(10)),
+            NonZeroU32::new(1_000).unwrap(),
+            Some(Duration::from_millis(1)),
+        )
+        .await;
+        assert_eq!(sampler.timer.interval_dur(), balter_core::MIN_INTERVAL);
+    }
+
+    #[tokio::test]
+    async fn test_per_worker_state() {
+        use std::collections::HashSet;
+        use std::sync::Mutex;
+
+        let seen = Arc::new(Mutex::new(HashSet::new()));
+        let built = Arc::new(AtomicU64::new(0));
+        let scenario_seen = seen.clone();
+        let mut sampler = BaseSampler::new(
+            "",
+            move || {
+                let seen = scenario_seen.clone();
+                async move {
+                    let id = crate::state::<usize>();
+                    seen.lock().unwrap().insert(*id);
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                }
+            },
+            NonZeroU32::new(1_000).unwrap(),
+            None,
+        )
+        .await;
+
+        let mut state = StateMap::default();
+        let factory_built = built.clone();
+        state.insert_worker(move |id| {
+            factory_built.fetch_add(1, Ordering::Relaxed);
+            id
+        });
+        sampler.set_state(state);
+
+        sampler.set_concurrency(4);
+        let _ = sampler.sample().await;
+        assert_eq!(*seen.lock().unwrap(), HashSet::from([0, 1, 2, 3]));
+        assert_eq!(built.load(Ordering::Relaxed), 4);
+
+        // Only new tasks build their state, reusing the IDs of removed ones
+        sampler.set_concurrency(2);
+        sampler.set_concurrency(6);
+        seen.lock().unwrap().clear();
+        let _ = sampler.sample().await;
+        assert_eq!(*seen.lock().unwrap(), HashSet::from([0, 1, 2, 3, 4, 5]));
+        assert_eq!(built.load(Ordering::Relaxed), 8);
+    }
+
+    #[tokio::test]
+    async fn test_iterations() {
+        let count = Arc::new(AtomicU64::new(0));
+        let scenario_count = count.clone();
+        let mut sampler = BaseSampler::new(
+            "",
+            move || {
+                let count = scenario_count.clone();
+                async move {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                    count.fetch_add(1, Ordering::Relaxed);
+                }
+            },
+            NonZeroU32::new(1_000).unwrap(),
+            Some(Duration::from_millis(100)),
+        )
+        .await;
+
+        sampler.set_iterations(250);
+        sampler.set_concurrency(10);
+
+        while !sampler.iterations_complete() {
+            let _ = sampler.sample().await;
+        }
+
+        assert_eq!(count.load(Ordering::Relaxed), 250);
+        sampler.shutdown(Duration::from_secs(1)).await;
+    }
+
+    #[tokio::test]
+    async fn test_single_threaded() {
+        let count = Arc::new(AtomicU64::new(0));
+        let scenario_count = count.clone();
+        let local = tokio::task::LocalSet::new();
+        local
+            .run_until(async {
+                let mut sampler = BaseSampler::new(
+                    "",
+                    SingleThreaded(move || {
+                        let count = scenario_count.clone();
+                        async move {
+                            // NOTE: Holding an Rc across an await makes the future !Send
+                            let local_count = std::rc::Rc::new(1);
+                            tokio::time::sleep(Duration::from_millis(1)).await;
+                            count.fetch_add(*local_count, Ordering::Relaxed);
+                        }
+                    }),
+                    NonZeroU32::new(1_000).unwrap(),
+                    Some(Duration::from_millis(100)),
+                )
+                .await;
+
+                sampler.set_iterations(100);
+                sampler.set_concurrency(4);
+
+                while !sampler.iterations_complete() {
+                    let _ = sampler.sample().await;
+                }
+                sampler.shutdown(Duration::from_secs(1)).await;
+            })
+            .await;
+
+        assert_eq!(count.load(Ordering::Relaxed), 100);
+    }
+
+    #[tokio::test]
+    async fn test_request_budget() {
+        let mut sampler = BaseSampler::new(
+            "",
+            mock_scenario!(Duration::from_millis(1), Duration::from_micros(10)),
+            NonZeroU32::new(1_000).unwrap(),
+            Some(Duration::from_millis(100)),
+        )
+        .await;
+
+        sampler.set_request_budget(150);
+        sampler.set_concurrency(10);
+
+        let mut total = sampler.sample().await;
+        while !sampler.budget_exhausted() {
+            total.merge(&sampler.sample().await);
+        }
+        total.merge(&sampler.shutdown(Duration::from_millis(100)).await);
+
+        assert_eq!(total.success, 150);
+    }
+
+    #[tokio::test]
+    async fn test_panics() {
+        let count = Arc::new(AtomicU64::new(0));
+        let scenario_count = count.clone();
+        let mut sampler = BaseSampler::new(
+            "",
+            move || {
+                let count = scenario_count.clone();
+                async move {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                    if count.fetch_add(1, Ordering::Relaxed) % 4 == 3 {
+                        panic!("Every fourth run panics");
+                    }
+                }
+            },
+            NonZeroU32::new(1_000).unwrap(),
+            Some(Duration::from_millis(100)),
+        )
+        .await;
+
+        sampler.set_iterations(100);
+        sampler.set_concurrency(4);
+
+        // NOTE: If panics killed the tasks, the iterations would never complete.
+        let mut total = sampler.sample().await;
+        while !sampler.iterations_complete() {
+            total.merge(&sampler.sample().await);
+        }
+        total.merge(&sampler.sample().await);
+
+        assert_eq!(count.load(Ordering::Relaxed), 100);
+        assert_eq!(sampler.panics(), 25);
+        assert_eq!(total.error, 25);
+        assert_eq!(sampler.error_counts().panic, 25);
+        assert_eq!(sampler.error_counts().total(), total.error);
+        sampler.shutdown(Duration::from_secs(1)).await;
+    }
+
+    #[tokio::test]
+    async fn test_pause_sender_dropped() {
+        let count = Arc::new(AtomicU64::new(0));
+        let scenario_count = count.clone();
+        let mut sampler = BaseSampler::new(
+            "",
+            move || {
+                scenario_count.fetch_add(1, Ordering::Relaxed);
+                tokio::time::sleep(Duration::from_millis(1))
+            },
+            NonZeroU32::new(1_000).unwrap(),
+            Some(Duration::from_millis(50)),
+        )
+        .await;
+        let (pause, paused) = watch::channel(false);
+        sampler.set_pause(paused);
+        sampler.set_concurrency(5);
+        assert!(sampler.sample().await.scenarios > 0);
+
+        pause.send_replace(true);
+        let _ = sampler.sample().await;
+        let idle = count.load(Ordering::Relaxed);
+        assert_eq!(sampler.sample().await.scenarios, 0);
+        assert_eq!(count.load(Ordering::Relaxed), idle);
+
+        // NOTE: Spinning tasks would never run the scenario again, and would starve the
+        // sampler on this single-threaded runtime.
+        drop(pause);
+        assert!(!sampler.paused());
+        let measurement = sampler.sample().await;
+        assert!(measurement.scenarios > 0);
+        assert!(count.load(Ordering::Relaxed) > idle);
+        sampler.shutdown(Duration::from_secs(1)).await;
+    }
+
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_fixed_concurrency_survives_panics() {
+        let count = Arc::new(AtomicU64::new(0));
+        let scenario_count = count.clone();
+        let mut sampler = BaseSampler::new(
+            "",
+            move || {
+                // NOTE: Panicking before returning the future, as a synchronous scenario
+                // function would.
+                if scenario_count.fetch_add(1, Ordering::Relaxed) % 4 == 3 {
+                    panic!("Every fourth run panics");
+                }
+                tokio::time::sleep(Duration::from_millis(1))
+            },
+            NonZeroU32::new(1_000).unwrap(),
+            Some(Duration::from_millis(50)),
+        )
+        .await;
+        sampler.set_concurrency(5);
+
+        let live = |sampler: &BaseSampler<_>| {
+            sampler
+                .tasks
+                .iter()
+                .filter(|task| !task.handle.is_finished())
+                .count()
+        };
+        for _ in 0..4 {
+            let measurement = sampler.sample().await;
+            assert_eq!(live(&sampler), 5);
+            assert!(measurement.scenarios > 0);
+        }
+        assert!(sampler.panics() > 0);
+
+        // Tasks which die anyway are replaced at the next sample
+        sampler.tasks[0].handle.abort();
+        sampler.tasks[3].handle.abort();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(live(&sampler), 3);
+        let _ = sampler.sample().await;
+        assert_eq!(live(&sampler), 5);
+        assert_eq!(sampler.concurrency(), 5);
+        assert!(logs_contain("Respawned 2 tasks"));
+        sampler.shutdown(Duration::from_secs(1)).await;
+    }
+
+    #[tokio::test]
+    async fn test_scenario_errors() {
+        let mut sampler = BaseSampler::new(
+            "",
+            || async {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                crate::transaction::scenario_hook(Err::<(), _>("setup failed"));
+            },
+            NonZeroU32::new(1_000).unwrap(),
+            Some(Duration::from_millis(100)),
+        )
+        .await;
+
+        sampler.set_iterations(20);
+        sampler.set_concurrency(2);
+
+        let mut total = sampler.sample().await;
+        while !sampler.iterations_complete() {
+            total.merge(&sampler.sample().await);
+        }
+
+        assert_eq!(sampler.scenario_errors(), 20);
+        assert_eq!(total.error, 0);
+        sampler.shutdown(Duration::from_secs(1)).await;
+    }
+
+    #[tokio::test]
+    async fn test_seed() {
+        async fn run(seed: u64) -> Vec<u64> {
+            use rand::Rng;
+            use std::sync::Mutex;
+
+            let values = Arc::new(Mutex::new(vec![]));
+            let scenario_values = values.clone();
+            let mut sampler = BaseSampler::new(
+                "",
+                move || {
+                    let values = scenario_values.clone();
+                    async move {
+                        values.lock().unwrap().push(crate::rng().gen());
+                    }
+                },
+                NonZeroU32::new(1_000).unwrap(),
+                Some(Duration::from_millis(100)),
+            )
+            .await;
+
+            sampler.set_seed(seed);
+            sampler.set_iterations(20);
+            sampler.set_concurrency(1);
+            while !sampler.iterations_complete() {
+                let _ = sampler.sample().await;
+            }
+            sampler.shutdown(Duration::from_secs(1)).await;
+
+            let values = values.lock().unwrap().clone();
+            values
+        }
+
+        assert_eq!(run(42).await, run(42).await);
+        assert_ne!(run(42).await, run(43).await);
+    }
+
+    #[tokio::test]
+    async fn test_burst() {
+        async fn first_sample(burst: u32) -> u64 {
+            let mut sampler = BaseSampler::new(
+                "",
+                mock_scenario!(Duration::from_micros(100), Duration::from_micros(10)),
+                NonZeroU32::new(100).unwrap(),
+                Some(Duration::from_millis(50)),
+            )
+            .await;
+            sampler.set_burst(NonZeroU32::new(burst).unwrap());
+            sampler.set_concurrency(100);
+
+            let sample = sampler.sample().await;
+            sampler.shutdown(Duration::from_secs(1)).await;
+            sample.success
+        }
+
+        // At 100 TPS, a 50ms window only fits ~5 evenly spaced transactions, whereas a burst
+        // allows them all to go through at once.
+        assert!(first_sample(1).await <= 10);
+        assert!(first_sample(50).await >= 50);
+    }
+
+    #[tokio::test]
+    async fn test_poisson_arrival() {
+        let mut sampler = BaseSampler::new(
+            "",
+            mock_scenario!(Duration::from_millis(1), Duration::from_micros(10)),
+            NonZeroU32::new(1_000).unwrap(),
+            None,
+        )
+        .await;
+        sampler.set_arrival(Arrival::Poisson);
+        sampler.set_concurrency(50);
+
+        let _ = sampler.sample().await;
         let sample = sampler.sample().await;
-        assert!(sample.tps >= 900. && sample.tps <= 1100.);
+        assert!(sample.tps >= 900. && sample.tps <= 1_100., "{}", sample.tps);
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let attempts = Arc::new(AtomicU64::new(0));
+        let scenario_attempts = attempts.clone();
+        let mut sampler = BaseSampler::new(
+            "",
+            move || {
+                let attempts = scenario_attempts.clone();
+                async move {
+                    let labels = balter_core::TransactionLabels {
+                        name: "",
+                        success: "",
+                        error: "",
+                        latency: "",
+                        retries: "",
+                    };
+                    // Every third attempt succeeds
+                    let mut attempt = 0;
+                    loop {
+                        attempt += 1;
+                        let res = crate::transaction::transaction_retry_hook(
+                            labels,
+                            attempt,
+                            async {
+                                if attempts.fetch_add(1, Ordering::Relaxed) % 3 == 2 {
+                                    Ok::<_, ()>(())
+                                } else {
+                                    Err(())
+                                }
+                            },
+                            |_| balter_core::ErrorKind::Timeout,
+                            || Some(Ok(())),
+                        )
+                        .await;
+                        if res.is_some() {
+                            break;
+                        }
+                    }
+                }
+            },
+            NonZeroU32::new(1_000).unwrap(),
+            Some(Duration::from_millis(100)),
+        )
+        .await;
+
+        sampler.set_retry_policy(RetryPolicy::new(3, Duration::from_millis(1)));
+        sampler.set_iterations(20);
+        sampler.set_concurrency(1);
+
+        let mut total = sampler.sample().await;
+        while !sampler.iterations_complete() {
+            total.merge(&sampler.sample().await);
+        }
+        total.merge(&sampler.sample().await);
+
+        assert_eq!(attempts.load(Ordering::Relaxed), 60);
+        assert_eq!(total.success, 20);
+        assert_eq!(total.error, 0);
     }
 
     /*
@@ -166,6 +1223,7 @@ pub(crate) mod tests {
             "",
             mock_scenario!(Duration::from_millis(400), Duration::from_millis(100)),
             NonZeroU32::new(50).unwrap(),
+            None,
         )
         .await;
 
@@ -0,0 +1,171 @@
+use governor::clock::Clock;
+use governor::middleware::NoOpMiddleware;
+use governor::state::{InMemoryState, NotKeyed};
+use governor::RateLimiter;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Rate limiter which keeps time with the tokio runtime
+pub(crate) type TokioRateLimiter =
+    RateLimiter<NotKeyed, InMemoryState, TokioClock, NoOpMiddleware<std::time::Instant>>;
+
+/// Clock of the tokio runtime.
+///
+/// NOTE: The rate limiter would otherwise keep wall-clock time, so that tests which pause tokio's
+/// clock (`#[tokio::test(start_paused = true)]`) also pause the rate limiter, and can assert
+/// exact TPS without depending on the speed of the machine running them.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct TokioClock;
+
+impl Clock for TokioClock {
+    type Instant = std::time::Instant;
+
+    fn now(&self) -> Self::Instant {
+        Instant::now().into_std()
+    }
+}
+
+/// Paces transactions across all tasks to match the goal TPS.
+pub(crate) enum Pacer {
+    /// Evenly spaced transactions, as set by the rate limiter, along with the queue of tasks
+    /// waiting on it
+    Constant(TokioRateLimiter, tokio::sync::Mutex<()>),
+    /// Exponentially distributed gaps between transactions
+    Poisson(PoissonArrivals),
+}
+
+impl Pacer {
+    pub fn constant(limiter: TokioRateLimiter) -> Self {
+        Pacer::Constant(limiter, tokio::sync::Mutex::new(()))
+    }
+
+    /// Wait until the next transaction can be sent, counting it as throttled if it has to wait.
+    pub async fn until_ready(&self, throttled: &AtomicU64) {
+        match self {
+            Pacer::Constant(limiter, queue) => {
+                if limiter.check().is_ok() {
+                    return;
+                }
+                throttled.fetch_add(1, Ordering::Relaxed);
+
+                // NOTE: Every throttled task would otherwise sleep until the same next permit,
+                // and wake up only for all but one to go back to sleep, which adds up at high
+                // concurrency and low TPS. Instead the tasks queue up (the lock is fair), and only
+                // the one at the front waits on the rate limiter.
+                let _front = queue.lock().await;
+                while let Err(not_until) = limiter.check() {
+                    tokio::time::sleep(not_until.wait_time_from(TokioClock.now())).await;
+                }
+            }
+            Pacer::Poisson(arrivals) => {
+                if let Some(arrival) = arrivals.reserve() {
+                    throttled.fetch_add(1, Ordering::Relaxed);
+                    tokio::time::sleep_until(arrival).await;
+                }
+            }
+        }
+    }
+}
+
+/// Schedule of Poisson arrivals shared by all tasks, where each transaction reserves the next
+/// arrival and waits for it.
+pub(crate) struct PoissonArrivals {
+    period: Duration,
+    next: Mutex<(Instant, SmallRng)>,
+}
+
+impl PoissonArrivals {
+    /// Arrivals averaging one per `period`
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period,
+            next: Mutex::new((Instant::now(), SmallRng::from_entropy())),
+        }
+    }
+
+    /// Reserve the next arrival, returning it if it's still to come.
+    ///
+    /// NOTE: Arrivals missed for lack of tasks ready to send them are dropped rather than sent in
+    /// a burst to catch up, just like the rate limiter with a burst of 1.
+    fn reserve(&self) -> Option<Instant> {
+        let now = Instant::now();
+        let mut next = self.next.lock().unwrap();
+        let (next, rng) = &mut *next;
+        let arrival = (*next).max(now);
+        // NOTE: Inverse transform sampling of the exponential distribution
+        let gap = -(1. - rng.gen::<f64>()).ln();
+        *next = arrival + self.period.mul_f64(gap);
+        (arrival > now).then_some(arrival)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poisson_gaps() {
+        let arrivals = PoissonArrivals::new(Duration::from_millis(1));
+        let gaps: Vec<f64> = (0..10_000)
+            .map(|_| {
+                let arrival = arrivals.reserve().unwrap_or_else(Instant::now);
+                let next = arrivals.next.lock().unwrap().0;
+                (next - arrival).as_secs_f64()
+            })
+            .collect();
+
+        // NOTE: Exponential gaps have a standard deviation equal to their mean
+        let mean = gaps.iter().sum::<f64>() / gaps.len() as f64;
+        let std =
+            (gaps.iter().map(|gap| (gap - mean).powi(2)).sum::<f64>() / gaps.len() as f64).sqrt();
+        assert!((mean - 0.001).abs() < 0.0001, "{mean}");
+        assert!((std - 0.001).abs() < 0.0001, "{std}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throttled_wait() {
+        use governor::Quota;
+        use std::future::Future;
+        use std::num::NonZeroU32;
+        use std::sync::Arc;
+
+        const TASKS: u64 = 200;
+
+        let quota = Quota::per_second(NonZeroU32::new(10).unwrap()).allow_burst(NonZeroU32::MIN);
+        let pacer = Arc::new(Pacer::constant(RateLimiter::direct_with_clock(
+            quota,
+            &TokioClock,
+        )));
+        let throttled = Arc::new(AtomicU64::new(0));
+        let polls = Arc::new(AtomicU64::new(0));
+
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..TASKS)
+            .map(|_| {
+                let (pacer, throttled, polls) = (pacer.clone(), throttled.clone(), polls.clone());
+                tokio::spawn(async move {
+                    let mut wait = Box::pin(pacer.until_ready(&throttled));
+                    std::future::poll_fn(|cx| {
+                        polls.fetch_add(1, Ordering::Relaxed);
+                        wait.as_mut().poll(cx)
+                    })
+                    .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(19_900));
+        assert_eq!(throttled.load(Ordering::Relaxed), TASKS - 1);
+        // NOTE: Each task is woken when it reaches the front of the queue and when its permit is
+        // ready, rather than every time any permit is.
+        let polls = polls.load(Ordering::Relaxed);
+        assert!(polls <= 3 * TASKS, "polls: {polls}");
+    }
+}
@@ -0,0 +1,123 @@
+use balter_core::PidGains;
+#[allow(unused)]
+use tracing::{debug, error, info, trace, warn};
+
+/// Bounds on the relative change in concurrency per adjustment
+const MAX_DECREASE: f64 = 0.5;
+const MAX_INCREASE: f64 = 1.;
+
+/// PID controller over concurrency, operating on the TPS error normalized by the goal TPS.
+///
+/// NOTE: This uses the incremental (velocity) form of PID, which outputs a change in concurrency
+/// rather than an absolute value. Since the rate limiter caps measured TPS at the goal, the
+/// error is never negative once we have enough concurrency; the positional form would wind up
+/// its integral term and grow concurrency without bound.
+pub(crate) struct PidController {
+    gains: PidGains,
+    prev_errors: (f64, f64),
+}
+
+impl PidController {
+    pub fn new(gains: PidGains) -> Self {
+        Self {
+            gains,
+            prev_errors: (0., 0.),
+        }
+    }
+
+    pub fn next_concurrency(
+        &mut self,
+        concurrency: usize,
+        measured_tps: f64,
+        goal_tps: f64,
+    ) -> usize {
+        let error = (goal_tps - measured_tps) / goal_tps;
+        let (e1, e2) = self.prev_errors;
+        self.prev_errors = (error, e1);
+
+        let PidGains { kp, ki, kd } = self.gains;
+        let output = kp * (error - e1) + ki * error + kd * (error - 2. * e1 + e2);
+        trace!("PID: error={error:.3}, output={output:.3}");
+
+        let output = output.clamp(-MAX_DECREASE, MAX_INCREASE);
+        ((concurrency as f64 * (1. + output)).round() as usize).max(1)
+    }
+}
+
+/// The default concurrency adjustment: scale concurrency by the ratio of goal TPS to measured
+/// TPS, never decreasing it.
+pub(crate) fn ratio_concurrency(concurrency: usize, measured_tps: f64, goal_tps: f64) -> usize {
+    let tps_per_task = measured_tps / concurrency as f64;
+    let new_concurrency = (goal_tps / tps_per_task).ceil() as usize;
+    new_concurrency.max(concurrency).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GOAL: f64 = 3_000.;
+
+    /// Mock service where each task gets faster with more concurrency (e.g. due to batching),
+    /// with the measured TPS capped at the goal by the rate limiter.
+    fn plant(concurrency: usize, step: usize) -> f64 {
+        let noise = if step.is_multiple_of(2) { 1.05 } else { 0.95 };
+        ((concurrency as f64).powf(1.5) * 10. * noise).min(GOAL)
+    }
+
+    /// Returns the step at which the goal TPS was reached, and the final concurrency
+    fn run(mut adjust: impl FnMut(usize, f64) -> usize) -> (usize, usize) {
+        let mut concurrency = 10;
+        let mut settled = None;
+        for step in 0..50 {
+            let tps = plant(concurrency, step);
+            if settled.is_none() && tps >= GOAL * 0.95 {
+                settled = Some(step);
+            }
+            concurrency = adjust(concurrency, tps);
+        }
+        (settled.expect("Never reached goal TPS"), concurrency)
+    }
+
+    #[test]
+    fn test_concurrency_step() {
+        use balter_core::ConcurrencyStep;
+
+        // Each task only manages 1 TPS, so reaching the goal requires thousands of tasks.
+        let steps_to_goal = |step: ConcurrencyStep| {
+            let mut concurrency = 10;
+            for i in 0.. {
+                if concurrency as f64 >= GOAL {
+                    return i;
+                }
+                let new = ratio_concurrency(concurrency, concurrency as f64, GOAL);
+                concurrency = step.limit(concurrency, new);
+            }
+            unreachable!()
+        };
+
+        let absolute = steps_to_goal(ConcurrencyStep::Absolute(100));
+        let proportional = steps_to_goal(ConcurrencyStep::Proportional(0.5));
+        assert_eq!(absolute, 30);
+        assert!(proportional < 15, "proportional: {proportional}");
+    }
+
+    #[test]
+    fn test_pid_settling() {
+        let (ratio_settled, ratio_concurrency) = run(|c, tps| ratio_concurrency(c, tps, GOAL));
+
+        let mut pid = PidController::new(PidGains::default());
+        let (pid_settled, pid_concurrency) = run(|c, tps| pid.next_concurrency(c, tps, GOAL));
+
+        // The ratio controller reaches the goal almost immediately, but with roughly double the
+        // ~45 tasks actually required. The PID controller takes a few more samples, but settles
+        // close to the optimal concurrency.
+        assert!(ratio_settled <= 2);
+        assert!(ratio_concurrency > 80, "ratio: {ratio_concurrency}");
+        assert!(pid_settled <= 10, "pid: {pid_settled}");
+        assert!(
+            (45..55).contains(&pid_concurrency),
+            "pid: {pid_concurrency}"
+        );
+    }
+}
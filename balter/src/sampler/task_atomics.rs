@@ -1,66 +1,336 @@
 use crate::measurement::Measurement;
-use crate::transaction::TransactionData;
+use crate::metric_keys::scenario_metric;
+use crate::sampler::pacer::{Pacer, PoissonArrivals, TokioClock};
+use crate::transaction::{TransactionData, STATUS_CODES};
 use arc_swap::ArcSwap;
-use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use balter_core::{Arrival, ErrorCounts, ErrorKind, RetryPolicy, TpsTarget};
+use governor::{Quota, RateLimiter};
 use metrics_util::AtomicBucket;
+use std::collections::{BTreeMap, HashMap};
 use std::num::NonZeroU32;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+#[allow(unused)]
+use tracing::{debug, error, info, trace, warn};
 
 pub(crate) struct TaskAtomics {
-    limiter: Arc<ArcSwap<DefaultDirectRateLimiter>>,
+    scenario: Arc<str>,
+    limiter: Arc<ArcSwap<Pacer>>,
     tps_limit: NonZeroU32,
+    /// Period between transactions, which overrides the TPS limit for rates below 1 TPS
+    tps_period: Option<Duration>,
+    burst: NonZeroU32,
+    arrival: Arrival,
     success: Arc<AtomicU64>,
     error: Arc<AtomicU64>,
     latency: Arc<AtomicBucket<Duration>>,
+    retry: Option<RetryPolicy>,
+    scenario_errors: Arc<AtomicU64>,
+    /// Number of runs of the scenario completed since the last sample
+    scenarios: Arc<AtomicU64>,
+    tps_target: TpsTarget,
+    error_kinds: Arc<[AtomicU64; ErrorKind::ALL.len()]>,
+    acquired: Arc<AtomicU64>,
+    throttled: Arc<AtomicU64>,
+    transaction_limiters: Arc<HashMap<String, Pacer>>,
+    capped: Arc<AtomicU64>,
+    budget: Option<(u64, Arc<AtomicU64>)>,
+    dry_run: bool,
+    status_codes: Arc<[AtomicU64; STATUS_CODES]>,
 }
 
 impl TaskAtomics {
-    pub fn new(tps_limit: NonZeroU32) -> Self {
+    pub fn new(scenario: &str, tps_limit: NonZeroU32) -> Self {
         Self {
-            limiter: Arc::new(ArcSwap::new(Arc::new(rate_limiter(tps_limit)))),
+            scenario: scenario.into(),
+            limiter: Arc::new(ArcSwap::new(Arc::new(pacer(
+                tps_limit,
+                None,
+                NonZeroU32::MIN,
+                Arrival::Constant,
+            )))),
             tps_limit,
+            tps_period: None,
+            burst: NonZeroU32::MIN,
+            arrival: Arrival::Constant,
             success: Arc::new(AtomicU64::new(0)),
             error: Arc::new(AtomicU64::new(0)),
             latency: Arc::new(AtomicBucket::new()),
+            retry: None,
+            scenario_errors: Arc::new(AtomicU64::new(0)),
+            scenarios: Arc::new(AtomicU64::new(0)),
+            tps_target: TpsTarget::default(),
+            error_kinds: Arc::new(Default::default()),
+            acquired: Arc::new(AtomicU64::new(0)),
+            throttled: Arc::new(AtomicU64::new(0)),
+            transaction_limiters: Arc::new(HashMap::new()),
+            capped: Arc::new(AtomicU64::new(0)),
+            budget: None,
+            dry_run: false,
+            status_codes: Arc::new(std::array::from_fn(|_| AtomicU64::new(0))),
         }
     }
 
     pub fn set_tps_limit(&mut self, tps_limit: NonZeroU32) {
         if tps_limit != self.tps_limit {
             self.tps_limit = tps_limit;
-            self.limiter.store(Arc::new(rate_limiter(tps_limit)));
+            self.reset_pacer();
         }
     }
 
+    pub fn set_tps_period(&mut self, period: Duration) {
+        self.tps_period = Some(period);
+        self.reset_pacer();
+    }
+
+    pub fn set_burst(&mut self, burst: NonZeroU32) {
+        if burst != self.burst {
+            self.burst = burst;
+            self.reset_pacer();
+        }
+    }
+
+    pub fn set_arrival(&mut self, arrival: Arrival) {
+        if arrival != self.arrival {
+            self.arrival = arrival;
+            self.reset_pacer();
+        }
+    }
+
+    fn reset_pacer(&self) {
+        self.limiter.store(Arc::new(pacer(
+            self.tps_limit,
+            self.tps_period,
+            self.burst,
+            self.arrival,
+        )));
+    }
+
+    /// Cap the TPS of individual transactions, by transaction name. Must be called before any
+    /// transaction data is handed out to tasks.
+    pub fn set_transaction_tps(&mut self, caps: &BTreeMap<String, NonZeroU32>) {
+        self.transaction_limiters = Arc::new(
+            caps.iter()
+                .map(|(name, tps)| {
+                    let limiter = pacer(*tps, None, NonZeroU32::MIN, Arrival::Constant);
+                    (name.clone(), limiter)
+                })
+                .collect(),
+        );
+    }
+
+    pub fn set_retry_policy(&mut self, retry: RetryPolicy) {
+        self.retry = Some(retry);
+    }
+
+    pub fn set_tps_target(&mut self, tps_target: TpsTarget) {
+        self.tps_target = tps_target;
+    }
+
+    pub fn set_dry_run(&mut self) {
+        self.dry_run = true;
+    }
+
+    pub fn set_request_budget(&mut self, budget: u64) {
+        self.budget = Some((budget, Arc::new(AtomicU64::new(0))));
+    }
+
+    /// Whether every transaction allowed by the request budget has been sent
+    pub fn budget_exhausted(&self) -> bool {
+        self.budget
+            .as_ref()
+            .is_some_and(|(limit, sent)| sent.load(Ordering::Relaxed) >= *limit)
+    }
+
     pub fn tps_limit(&self) -> NonZeroU32 {
         self.tps_limit
     }
 
+    /// Total number of runs of the scenario which returned an error
+    pub fn scenario_errors(&self) -> u64 {
+        self.scenario_errors.load(Ordering::Relaxed)
+    }
+
+    /// Total transaction errors of each kind
+    pub fn error_counts(&self) -> ErrorCounts {
+        ErrorCounts::from(std::array::from_fn(|i| {
+            self.error_kinds[i].load(Ordering::Relaxed)
+        }))
+    }
+
+    /// Total number of responses with each HTTP status code
+    pub fn status_codes(&self) -> BTreeMap<u16, u64> {
+        (0..)
+            .zip(self.status_codes.iter())
+            .map(|(status, count)| (status, count.load(Ordering::Relaxed)))
+            .filter(|(_, count)| *count > 0)
+            .collect()
+    }
+
     pub fn clone_to_transaction_data(&self) -> TransactionData {
         TransactionData {
+            scenario: self.scenario.clone(),
             limiter: self.limiter.clone(),
             success: self.success.clone(),
             error: self.error.clone(),
             latency: self.latency.clone(),
+            retry: self.retry,
+            scenario_errors: self.scenario_errors.clone(),
+            scenarios: self.scenarios.clone(),
+            tps_target: self.tps_target,
+            error_kinds: self.error_kinds.clone(),
+            acquired: self.acquired.clone(),
+            throttled: self.throttled.clone(),
+            transaction_limiters: self.transaction_limiters.clone(),
+            capped: self.capped.clone(),
+            budget: self.budget.clone(),
+            dry_run: self.dry_run,
+            status_codes: self.status_codes.clone(),
         }
     }
 
     pub fn collect(&self, elapsed: Duration) -> Measurement {
         let success = self.success.swap(0, Ordering::Relaxed);
         let error = self.error.swap(0, Ordering::Relaxed);
+        let scenarios = self.scenarios.swap(0, Ordering::Relaxed);
         let mut measurements = Measurement::new(success, error, elapsed);
-        self.latency
-            .clear_with(|dur| measurements.populate_latencies(dur));
+        measurements.set_scenarios(scenarios, self.tps_target);
+
+        // NOTE: If few transactions are throttled while TPS is below the goal, the limiter isn't
+        // what's holding the scenario back, and more concurrency is needed.
+        let acquired = self.acquired.swap(0, Ordering::Relaxed);
+        let throttled = self.throttled.swap(0, Ordering::Relaxed);
+        debug!("Rate limiter throttled {throttled} of {acquired} transactions");
+        let capped = self.capped.swap(0, Ordering::Relaxed);
+        if capped > 0 {
+            debug!("Transaction caps held back {capped} transactions");
+        }
+        if cfg!(feature = "metrics") {
+            let (name, labels) =
+                scenario_metric("balter_scenario_limiter_throttled", &self.scenario);
+            metrics::counter!(name, labels).increment(throttled);
+        }
+
+        let histogram = cfg!(feature = "metrics").then(|| {
+            let (name, labels) = scenario_metric("balter_scenario_latency", &self.scenario);
+            metrics::histogram!(name, labels)
+        });
+        self.latency.clear_with(|dur| {
+            measurements.populate_latencies(dur);
+            if let Some(histogram) = &histogram {
+                for latency in dur {
+                    histogram.record(latency.as_secs_f64());
+                }
+            }
+        });
         measurements
     }
 }
 
-fn rate_limiter(tps_limit: NonZeroU32) -> DefaultDirectRateLimiter {
-    RateLimiter::direct(
-        Quota::per_second(tps_limit)
-            // TODO: Make burst configurable
-            .allow_burst(NonZeroU32::new(1).unwrap()),
-    )
+fn pacer(
+    tps_limit: NonZeroU32,
+    tps_period: Option<Duration>,
+    burst: NonZeroU32,
+    arrival: Arrival,
+) -> Pacer {
+    match arrival {
+        Arrival::Constant => {
+            let quota = match tps_period {
+                Some(period) => Quota::with_period(period).expect("TPS period must be non-zero"),
+                None => Quota::per_second(tps_limit),
+            };
+            Pacer::constant(RateLimiter::direct_with_clock(
+                quota.allow_burst(burst),
+                &TokioClock,
+            ))
+        }
+        Arrival::Poisson => Pacer::Poisson(PoissonArrivals::new(
+            tps_period.unwrap_or(Duration::from_secs(1) / tps_limit.get()),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::{transaction_infallible_hook, TRANSACTION_HOOK};
+    use balter_core::TransactionLabels;
+
+    const LABELS: TransactionLabels = TransactionLabels {
+        name: "test",
+        success: "balter_transaction_test_success",
+        error: "balter_transaction_test_error",
+        latency: "balter_transaction_test_latency",
+        retries: "balter_transaction_test_retries",
+    };
+
+    #[tokio::test]
+    async fn test_throttled() {
+        let atomics = TaskAtomics::new("test", NonZeroU32::new(10).unwrap());
+
+        TRANSACTION_HOOK
+            .scope(atomics.clone_to_transaction_data(), async {
+                for _ in 0..3 {
+                    transaction_infallible_hook(LABELS, async {}, || None).await;
+                }
+            })
+            .await;
+
+        // NOTE: Only the first transaction fits in the burst of the rate limiter
+        assert_eq!(atomics.acquired.load(Ordering::Relaxed), 3);
+        assert_eq!(atomics.throttled.load(Ordering::Relaxed), 2);
+
+        let measurement = atomics.collect(Duration::from_secs(1));
+        assert_eq!(measurement.success, 3);
+        assert_eq!(atomics.acquired.load(Ordering::Relaxed), 0);
+        assert_eq!(atomics.throttled.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::needless_borrow)]
+    async fn test_dry_run() {
+        use crate::transaction::{
+            transaction_hook, DryRun, DryRunDefault, DryRunFallback, DryRunResult,
+        };
+        use std::sync::atomic::AtomicBool;
+
+        struct NoDefault;
+
+        assert_eq!(
+            (&&DryRun::<Result<u32, String>>::new()).dry_run_output(),
+            Some(Ok(0))
+        );
+        assert_eq!(
+            (&&DryRun::<String>::new()).dry_run_output(),
+            Some(String::new())
+        );
+        assert!((&&DryRun::<Result<NoDefault, String>>::new())
+            .dry_run_output()
+            .is_none());
+
+        let mut atomics = TaskAtomics::new("test", NonZeroU32::new(1_000).unwrap());
+        atomics.set_dry_run();
+
+        let called = AtomicBool::new(false);
+        let res = TRANSACTION_HOOK
+            .scope(atomics.clone_to_transaction_data(), async {
+                transaction_hook(
+                    LABELS,
+                    async {
+                        called.store(true, Ordering::Relaxed);
+                        Err::<u32, _>("sent")
+                    },
+                    |_| ErrorKind::Other,
+                    || (&&DryRun::<Result<u32, &str>>::new()).dry_run_output(),
+                )
+                .await
+            })
+            .await;
+
+        assert_eq!(res, Ok(0));
+        assert!(!called.load(Ordering::Relaxed));
+        assert_eq!(atomics.acquired.load(Ordering::Relaxed), 1);
+        assert_eq!(atomics.collect(Duration::from_secs(1)).success, 1);
+    }
 }
@@ -7,6 +7,8 @@ pub(crate) struct Timer {
     interval: Interval,
     last_tick: Instant,
     interval_dur: Duration,
+    /// Point at which the final tick is due, even if it cuts the interval short
+    deadline: Option<Instant>,
 }
 
 impl Timer {
@@ -19,20 +21,67 @@ impl Timer {
             interval,
             last_tick,
             interval_dur,
+            deadline: None,
         }
     }
 
     pub async fn tick(&mut self) -> Duration {
+        if let Some(deadline) = self.deadline {
+            if deadline < self.last_tick + self.interval_dur {
+                tokio::time::sleep_until(deadline).await;
+                let elapsed = self.last_tick.elapsed();
+                self.last_tick = Instant::now();
+                return elapsed;
+            }
+        }
+
         let next = self.interval.tick().await;
         let elapsed = self.last_tick.elapsed();
         self.last_tick = next;
         elapsed
     }
 
-    #[allow(unused)]
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+    }
+
+    /// Point at which the next tick is due
+    pub fn next_tick(&self) -> Instant {
+        let next = self.last_tick + self.interval_dur;
+        self.deadline.map_or(next, |deadline| deadline.min(next))
+    }
+
+    /// Whether the deadline has passed
+    pub fn deadline_reached(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Time since the last tick, without waiting for the next one
+    pub fn elapsed(&self) -> Duration {
+        self.last_tick.elapsed()
+    }
+
+    /// Start the current interval afresh from now
+    pub async fn restart(&mut self) {
+        let deadline = self.deadline;
+        *self = Self::new(self.interval_dur).await;
+        self.deadline = deadline;
+    }
+
     pub async fn set_interval_dur(&mut self, dur: Duration) {
-        if dur < Duration::from_secs(10) {
+        let deadline = self.deadline;
+        if dur < balter_core::MIN_INTERVAL {
+            warn!(
+                "Balter's polling interval of {} is below the minimum; using {} instead.",
+                humantime::format_duration(dur),
+                humantime::format_duration(balter_core::MIN_INTERVAL)
+            );
+            *self = Self::new(balter_core::MIN_INTERVAL).await;
+            self.deadline = deadline;
+        } else if dur <= balter_core::MAX_INTERVAL {
             *self = Self::new(dur).await;
+            self.deadline = deadline;
         } else {
             error!("Balter's polling interval is greater than 10s. This is likely a sign of an issue; not increasing the polling interval.")
         }
@@ -47,7 +96,9 @@ impl Timer {
     pub async fn double(&mut self) {
         if self.interval_dur < Duration::from_secs(10) {
             self.interval_dur *= 2;
+            let deadline = self.deadline;
             *self = Self::new(self.interval_dur).await;
+            self.deadline = deadline;
         } else {
             error!("Balter's Sampling interval is greater than 10s. This is likely a sign of an issue; not increasing the sampling interval.")
         }
@@ -1,28 +1,108 @@
 //! Scenario logic and constants
-use crate::controllers::{CompositeController, Controller};
+use crate::controllers::{CompositeController, Controller, ControllerOutcome};
 use crate::hints::Hint;
-use crate::sampler::Sampler;
-use balter_core::{LatencyConfig, RunStatistics, ScenarioConfig};
+use crate::metric_keys::RESERVED_LABELS;
+use crate::metrics::{with_labels, with_recorder, SharedRecorder};
+use crate::recorder::CsvRecorder;
+use crate::sample_set::SampleSet;
+use crate::sampler::{Sampler, SpawnWorker};
+use crate::state::StateMap;
+use crate::transaction::{scenario_hook, ScenarioResult};
+use balter_core::{
+    Arrival, ConcurrencyStep, ControllerKind, ErrorRateAbort, LatencyConfig, LiveStats, RampConfig,
+    RetryPolicy, RunStatistics, RuntimeConfig, ScenarioConfig, SearchStrategy, Stage, StopReason,
+    TpsTarget,
+};
 #[cfg(feature = "rt")]
 use balter_runtime::runtime::{RuntimeMessage, BALTER_OUT};
+use serde::Serialize;
+#[cfg(feature = "rt")]
+use std::net::SocketAddr;
 use std::{
     future::Future,
     num::NonZeroU32,
+    path::Path,
     pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
     time::{Duration, Instant},
 };
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, instrument, trace, warn, Instrument};
 
 /// Load test scenario structure
 ///
-/// Handler for running scenarios. Not intended for manual creation, use the [`#[scenario]`](balter_macros::scenario) macro which will add these methods to functions.
+/// Handler for running scenarios. Generally created with the [`#[scenario]`](balter_macros::scenario) macro which will add these methods to functions, or with [Scenario::from_fn] for scenarios which are built at runtime.
 #[pin_project::pin_project]
 pub struct Scenario<T> {
     func: T,
     runner_fut: Option<Pin<Box<dyn Future<Output = RunStatistics> + Send>>>,
     config: ScenarioConfig,
+    hooks: RunHooks,
+}
+
+type UntilFn = Box<dyn Fn(&SampleSet) -> bool + Send>;
+type OnSampleFn = Box<dyn Fn(&SampleSet, &LiveStats) + Send>;
+type OnStabilityChangeFn = Box<dyn Fn(&LiveStats) + Send>;
+type SetupFn = Box<dyn FnOnce(StateMap) -> Pin<Box<dyn Future<Output = StateMap> + Send>> + Send>;
+type TeardownFut = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Callbacks and signals checked by the runner loop, which (unlike the [ScenarioConfig]) are
+/// local to this instance and not distributed.
+#[derive(Default)]
+pub(crate) struct RunHooks {
+    controller: Option<Box<dyn Controller>>,
+    until: Option<UntilFn>,
+    on_sample: Option<OnSampleFn>,
+    on_stability_change: Option<OnStabilityChangeFn>,
+    csv: Option<CsvRecorder>,
+    stop: Option<Arc<AtomicBool>>,
+    state: StateMap,
+    setup: Option<SetupFn>,
+    teardown: Option<TeardownFut>,
+    recorder: Option<SharedRecorder>,
+    control: Option<ScenarioControl>,
+    #[cfg(feature = "rt")]
+    peers: Vec<SocketAddr>,
+}
+
+/// Channels between a spawned Scenario and its [ScenarioHandle]
+struct ScenarioControl {
+    live: watch::Sender<LiveStats>,
+    commands: mpsc::UnboundedReceiver<ScenarioCommand>,
+    paused: watch::Receiver<bool>,
+}
+
+enum ScenarioCommand {
+    GoalTps(NonZeroU32),
+    Concurrency(usize),
+}
+
+/// Runs the teardown hook, which is spawned onto the runtime if the runner is dropped (or
+/// panics) before it gets the chance to run it.
+struct Teardown(Option<TeardownFut>);
+
+impl Teardown {
+    async fn run(mut self) {
+        if let Some(teardown) = self.0.take() {
+            teardown.await;
+        }
+    }
+}
+
+impl Drop for Teardown {
+    fn drop(&mut self) {
+        if let Some(teardown) = self.0.take() {
+            if let Ok(handle) = tokio::runtime::Handle::try_current() {
+                handle.spawn(teardown);
+            }
+        }
+    }
 }
 
 impl<T> Scenario<T> {
@@ -32,22 +112,114 @@ impl<T> Scenario<T> {
             func,
             runner_fut: None,
             config: ScenarioConfig::new(name),
+            hooks: RunHooks::default(),
         }
     }
 }
 
-impl<T, F> Future for Scenario<T>
-where
-    T: Fn() -> F + Send + 'static + Clone + Sync,
-    F: Future<Output = ()> + Send,
-{
-    type Output = RunStatistics;
+/// Handle to a Scenario running on a separate task, returned by
+/// [spawn](ConfigurableScenario::spawn).
+///
+/// Dropping the handle does not stop the Scenario, and resumes it if it was paused.
+pub struct ScenarioHandle {
+    stop: Arc<AtomicBool>,
+    task: JoinHandle<RunStatistics>,
+    live: watch::Receiver<LiveStats>,
+    commands: mpsc::UnboundedSender<ScenarioCommand>,
+    paused: watch::Sender<bool>,
+}
+
+impl ScenarioHandle {
+    /// Signal the Scenario to stop.
+    ///
+    /// The Scenario stops after its current sample, and shuts down as it would at the end of
+    /// its duration. Use [stats](ScenarioHandle::stats) to wait for it to finish.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the Scenario has finished running
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
 
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    /// Watch the [LiveStats] of the Scenario, which are updated each time it is sampled.
+    pub fn live_stats(&self) -> watch::Receiver<LiveStats> {
+        self.live.clone()
+    }
+
+    /// Override the goal TPS of the Scenario, from its next sample onwards.
+    ///
+    /// The override replaces the goal set by the Scenario's own load testing mode (`.tps()`,
+    /// `.error_rate()`, etc.) for the rest of the run. If the Scenario has already found the
+    /// goal TPS to be out of reach, the override is capped at the highest TPS it could reach.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the provided TPS is zero
+    pub fn set_goal_tps(&self, tps: u32) {
+        let tps = NonZeroU32::new(tps).expect("TPS must be non-zero.");
+        let _ = self.commands.send(ScenarioCommand::GoalTps(tps));
+    }
+
+    /// Pause the Scenario, until [resume](ScenarioHandle::resume) is called.
+    ///
+    /// Each task finishes its current run of the Scenario and then waits, so the load drops to
+    /// zero while the tasks themselves are kept. Samples taken while paused report the drop in
+    /// [LiveStats], but are otherwise ignored, so resuming picks up at the same concurrency and
+    /// goal TPS rather than ramping up again. The Scenario's duration keeps running while paused.
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    /// Resume the Scenario after a [pause](ScenarioHandle::pause)
+    pub fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+
+    /// Whether the Scenario is paused
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Set the concurrency of the Scenario, from its next sample onwards, up to the
+    /// [max_concurrency](ConfigurableScenario::max_concurrency). Unless the concurrency is
+    /// fixed, Balter still adjusts it from there to reach the goal TPS.
+    pub fn set_concurrency(&self, concurrency: usize) {
+        let _ = self
+            .commands
+            .send(ScenarioCommand::Concurrency(concurrency));
+    }
+
+    /// Wait for the Scenario to finish, returning its [RunStatistics].
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the Scenario's runner panicked.
+    pub async fn stats(self) -> RunStatistics {
+        match self.task.await {
+            Ok(stats) => stats,
+            Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+            Err(err) => panic!("Scenario task failed: {err}"),
+        }
+    }
+}
+
+impl<T: Clone> Scenario<T> {
+    fn poll_runner(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        runner: impl FnOnce(
+            T,
+            ScenarioConfig,
+            RunHooks,
+        ) -> Pin<Box<dyn Future<Output = RunStatistics> + Send>>,
+    ) -> Poll<RunStatistics> {
         if self.runner_fut.is_none() {
             let func = self.func.clone();
             let config = self.config.clone();
-            self.runner_fut = Some(Box::pin(async move { run_scenario(func, config).await }));
+            let hooks = std::mem::take(&mut self.hooks);
+            self.runner_fut = Some(runner(func, config, hooks));
         }
 
         if let Some(runner) = &mut self.runner_fut {
@@ -58,94 +230,2027 @@ where
     }
 }
 
+impl<T, F> Future for Scenario<T>
+where
+    T: Fn() -> F + Send + 'static + Clone + Sync,
+    F: Future<Output = ()> + Send + 'static,
+{
+    type Output = RunStatistics;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.poll_runner(cx, |func, config, hooks| {
+            Box::pin(run_scenario_on_runtime(func, config, hooks))
+        })
+    }
+}
+
+/// Scenario function whose futures are not `Send`, created by the
+/// [`#[scenario(single_threaded)]`](balter_macros::scenario) macro.
+///
+/// Single-threaded scenarios run on a dedicated thread, with all of their tasks spawned onto a
+/// [LocalSet](tokio::task::LocalSet).
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct SingleThreaded<T>(pub(crate) T);
+
+impl<T> Scenario<SingleThreaded<T>> {
+    #[doc(hidden)]
+    pub fn new_single_threaded(name: &str, func: T) -> Self {
+        Scenario::new(name, SingleThreaded(func))
+    }
+}
+
+impl<T, F> Future for Scenario<SingleThreaded<T>>
+where
+    T: Fn() -> F + Send + 'static + Clone + Sync,
+    F: Future<Output = ()> + 'static,
+{
+    type Output = RunStatistics;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.poll_runner(cx, |func, config, hooks| {
+            Box::pin(run_single_threaded(func, config, hooks))
+        })
+    }
+}
+
+/// Scenario function of a [Scenario] built at runtime with [Scenario::from_fn].
+#[derive(Clone)]
+pub struct ScenarioFn(
+    pub(crate) Arc<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>,
+);
+
+impl Scenario<ScenarioFn> {
+    /// Create a Scenario named `name` from a closure, without the
+    /// [`#[scenario]`](balter_macros::scenario) macro.
+    ///
+    /// This is useful for scenarios whose names or bodies are only known at runtime, such as
+    /// those generated from a spec file. As with the macro, the closure can return `()` or a
+    /// `Result`, where errors are counted towards [RunStatistics::scenario_errors]. To run a
+    /// Scenario built this way with the distributed runtime, register it with
+    /// `BalterRuntime::scenario` on each server (requires `rt` feature).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use balter::Scenario;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     for endpoint in ["/users", "/orders"] {
+    ///         Scenario::from_fn(endpoint, move || async move {
+    ///             get(endpoint).await
+    ///         })
+    ///         .tps(100)
+    ///         .duration(Duration::from_secs(60))
+    ///         .await;
+    ///     }
+    /// }
+    ///
+    /// #[transaction]
+    /// async fn get(endpoint: &str) -> Result<(), String> {
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_fn<F, Fut, R>(name: &str, func: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: ScenarioResult,
+    {
+        let func = ScenarioFn(Arc::new(move || {
+            let fut = func();
+            Box::pin(async move { scenario_hook(fut.await) })
+        }));
+        Scenario::new(name, func)
+    }
+}
+
+impl Future for Scenario<ScenarioFn> {
+    type Output = RunStatistics;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.poll_runner(cx, |func, config, hooks| {
+            Box::pin(run_scenario_on_runtime(func, config, hooks))
+        })
+    }
+}
+
 pub trait ConfigurableScenario<T: Send>: Future<Output = T> + Sized + Send {
     fn error_rate(self, error_rate: f64) -> Self;
+    fn search_strategy(self, search: SearchStrategy) -> Self;
+    fn onset(self) -> Self;
     fn tps(self, tps: u32) -> Self;
+    fn tps_every(self, period: Duration) -> Self;
+    fn aimd(self, error_rate: f64) -> Self;
+    fn transaction_tps(self, transaction: &str, tps: u32) -> Self;
+    #[cfg(feature = "rt")]
+    fn global_tps(self, tps: u32) -> Self;
+    fn ramp_tps(self, start: u32, end: u32) -> Self;
+    fn stages(self, stages: Vec<Stage>) -> Self;
     fn latency(self, latency: Duration, quantile: f64) -> Self;
     fn duration(self, duration: Duration) -> Self;
+    fn iterations(self, iterations: u64) -> Self;
+    fn once(self) -> Self;
+    fn request_budget(self, budget: u64) -> Self;
+    fn dry_run(self) -> Self;
     fn hint(self, hint: Hint) -> Self;
+    fn retry(self, policy: RetryPolicy) -> Self;
+    fn controller(self, controller: ControllerKind) -> Self;
+    fn burst(self, burst: u32) -> Self;
+    fn arrival(self, arrival: Arrival) -> Self;
+    fn tps_target(self, target: TpsTarget) -> Self;
+    fn concurrency_step(self, step: usize) -> Self;
+    fn concurrency_step_ratio(self, ratio: f64) -> Self;
+    fn max_concurrency(self, max_concurrency: usize) -> Self;
+    fn latency_backoff(self, multiple: f64) -> Self;
+    fn concurrency(self, concurrency: usize) -> Self;
+    fn fixed_concurrency(self, concurrency: usize) -> Self;
+    fn skip_warmup(self) -> Self;
+    fn abort_on_error_rate(self, error_rate: f64, consecutive_samples: usize) -> Self;
+    fn seed(self, seed: u64) -> Self;
+    fn max_panics(self, max_panics: u64) -> Self;
+    fn whole_run_statistics(self) -> Self;
+    fn runtime(self, runtime: RuntimeConfig) -> Self;
+    fn shutdown_timeout(self, timeout: Duration) -> Self;
+    fn sample_window(self, window: usize) -> Self;
+    fn error_window(self, window: usize) -> Self;
+    fn tps_tolerance(self, tolerance: f64) -> Self;
+    fn skip_samples(self, skip: usize) -> Self;
+    fn sample_interval(self, interval: Duration) -> Self;
+    fn sample_interval_bounds(self, min: Duration, max: Duration) -> Self;
+    fn until(self, condition: impl Fn(&SampleSet) -> bool + Send + 'static) -> Self;
+    fn tps_controller(self, controller: impl Controller + 'static) -> Self;
+    fn on_sample(self, callback: impl Fn(&SampleSet, &LiveStats) + Send + 'static) -> Self;
+    fn on_stability_change(self, callback: impl Fn(&LiveStats) + Send + 'static) -> Self;
+    fn record_csv(self, path: impl AsRef<Path>) -> Self;
+    fn args<A: Serialize>(self, args: A) -> Self;
+    fn with_state<S: Send + Sync + 'static>(self, state: Arc<S>) -> Self;
+    fn per_worker<W: Send + Sync + 'static>(
+        self,
+        factory: impl Fn(usize) -> W + Send + Sync + 'static,
+    ) -> Self;
+    fn setup<S: Send + Sync + 'static>(
+        self,
+        setup: impl Future<Output = S> + Send + 'static,
+    ) -> Self;
+    fn teardown(self, teardown: impl Future<Output = ()> + Send + 'static) -> Self;
+    fn metrics_recorder(self, recorder: impl ::metrics::Recorder + Send + Sync + 'static) -> Self;
+    fn label(self, key: &str, value: &str) -> Self;
+    #[cfg(feature = "rt")]
+    fn peers(self, peers: &[SocketAddr]) -> Self;
+    fn spawn(self) -> ScenarioHandle;
+    fn name(&self) -> &str;
 }
 
-impl<T, F> ConfigurableScenario<RunStatistics> for Scenario<T>
-where
-    T: Fn() -> F + Send + 'static + Clone + Sync,
-    F: Future<Output = ()> + Send,
-{
-    /// Run the scenario at the specified TPS.
+impl<T> ConfigurableScenario<RunStatistics> for Scenario<T>
+where
+    T: Clone + Send + 'static,
+    Self: Future<Output = RunStatistics>,
+{
+    /// Run the scenario at the specified TPS.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         // Scale scenario until 5K TPS
+    ///         .tps(5_000)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the provided TPS is zero
+    fn tps(mut self, tps: u32) -> Self {
+        self.config.max_tps =
+            Some(NonZeroU32::new(tps).expect("TPS provided must be non-zero. Given: {tps}"));
+        self.config.global_tps = false;
+        self.config.tps_period = None;
+        self
+    }
+
+    /// Run the scenario at one transaction every `period`, for rates below 1 TPS.
+    ///
+    /// This is useful for rare, cron-like work mixed in with other load, such as in a
+    /// [Suite](crate::Suite). The concurrency is held fixed (see
+    /// [concurrency](ConfigurableScenario::concurrency) to change it), since the scenario is
+    /// always limited by its rate rather than by its concurrency. The measured `actual_tps` is
+    /// fractional, while the reported `goal_tps` is rounded up to 1.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         // One transaction every 5 seconds
+    ///         .tps_every(Duration::from_secs(5))
+    ///         .duration(Duration::from_secs(300))
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the provided period is zero
+    fn tps_every(mut self, period: Duration) -> Self {
+        if period.is_zero() {
+            panic!("TPS period must be non-zero");
+        }
+        self = self.tps(1);
+        self.config.tps_period = Some(period);
+        self.config.sampler.controller = ControllerKind::Fixed;
+        self
+    }
+
+    /// Back off the TPS set with [tps](ConfigurableScenario::tps) while the error rate is above
+    /// `error_rate`, to avoid overwhelming a struggling service.
+    ///
+    /// The goal TPS is halved each time a sample's error rate exceeds the threshold, and
+    /// recovers by 5% of the requested TPS with each stable sample below it
+    /// (additive-increase/multiplicative-decrease). A service which can't sustain the requested
+    /// TPS is therefore held just around its capacity, rather than flooded with errors.
+    /// [RunStatistics](balter_core::RunStatistics) reports both the requested TPS and the goal
+    /// it was backed off to.
+    ///
+    /// Has no effect without [tps](ConfigurableScenario::tps).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(10_000)
+    ///         // Back off whenever more than 5% of transactions fail
+    ///         .aimd(0.05)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the error_rate is not between 0 and 1.
+    fn aimd(mut self, error_rate: f64) -> Self {
+        if !(0. ..=1.).contains(&error_rate) {
+            panic!(
+                "Specified error rate must be between 0 and 1. Value provided was {error_rate}."
+            );
+        }
+        self.config.aimd = Some(error_rate);
+        self
+    }
+
+    /// Cap the TPS of a single transaction, by the name of its `#[transaction]` function,
+    /// independently of the rest of the Scenario. Can be called once per transaction.
+    ///
+    /// The goal TPS (from [tps](ConfigurableScenario::tps) or any other mode) still counts every
+    /// transaction, capped or not, and is paced by the Scenario-wide rate limiter. Each call to
+    /// a capped transaction additionally waits for its own limiter, so it never exceeds its cap
+    /// however high the goal. Time spent waiting on a cap holds up the task running the
+    /// Scenario, so Balter raises the concurrency to make up the rest of the goal. A goal which
+    /// could only be reached by exceeding a cap is never reached, e.g. when every run of the
+    /// Scenario calls a capped transaction.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(1_000)
+    ///         // Writes are capped at 100 TPS, while reads make up the rest of the goal
+    ///         .transaction_tps("write_user", 100)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    ///     for _ in 0..9 {
+    ///         let _ = read_user().await;
+    ///     }
+    ///     let _ = write_user().await;
+    /// }
+    ///
+    /// #[transaction]
+    /// async fn read_user() -> Result<(), std::io::Error> {
+    ///     Ok(())
+    /// }
+    ///
+    /// #[transaction]
+    /// async fn write_user() -> Result<(), std::io::Error> {
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the provided TPS is zero
+    fn transaction_tps(mut self, transaction: &str, tps: u32) -> Self {
+        let tps = NonZeroU32::new(tps).expect("Transaction TPS must be non-zero");
+        self.config
+            .transaction_tps
+            .insert(transaction.to_string(), tps);
+        self
+    }
+
+    /// Run the scenario at the specified TPS in total across all peers (requires `rt` feature).
+    ///
+    /// With [tps](ConfigurableScenario::tps) the goal is for this server alone. Here the goal is
+    /// split evenly between this server and every peer it knows of through the
+    /// [BalterRuntime](crate::BalterRuntime) when the scenario starts, in the same way as
+    /// [peers](ConfigurableScenario::peers) (which takes precedence if set). If a peer stops
+    /// responding its share is handed off to a free peer. Peers which join after the scenario
+    /// has started are not given a share.
+    ///
+    /// Each server then treats its share as its own goal TPS, so any work it hands off when it
+    /// cannot reach its share alone comes out of that share, and the total stays the same.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     tokio::spawn(async {
+    ///         BalterRuntime::new().with_args().run().await;
+    ///     });
+    ///
+    ///     my_scenario()
+    ///         .global_tps(30_000)
+    ///         .duration(Duration::from_secs(300))
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the provided TPS is zero
+    #[cfg(feature = "rt")]
+    fn global_tps(mut self, tps: u32) -> Self {
+        self = self.tps(tps);
+        self.config.global_tps = true;
+        self
+    }
+
+    /// Run the scenario, linearly ramping the TPS from `start` to `end` over the duration of the
+    /// scenario. Ramping down (`end < start`) is also supported.
+    ///
+    /// NOTE: This method requires a `.duration()` to be set (unless limited by `.iterations()`);
+    /// without one the scenario does not run, and stops with [StopReason::MissingDuration]. The
+    /// goal TPS is recomputed on each sample, so very short durations will only see a couple of
+    /// steps along the ramp.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         // Ramp from 100 TPS to 5K TPS over 5 minutes
+    ///         .ramp_tps(100, 5_000)
+    ///         .duration(Duration::from_secs(300))
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if either of the provided TPS values are zero
+    fn ramp_tps(mut self, start: u32, end: u32) -> Self {
+        let start = NonZeroU32::new(start)
+            .unwrap_or_else(|| panic!("Starting TPS provided must be non-zero. Given: {start}"));
+        let end = NonZeroU32::new(end)
+            .unwrap_or_else(|| panic!("Ending TPS provided must be non-zero. Given: {end}"));
+        self.config.ramp = Some(RampConfig::new(start, end));
+        self
+    }
+
+    /// Run the scenario through a sequence of load stages.
+    ///
+    /// Each [Stage] linearly ramps from the previous stage's target TPS to its own over the
+    /// stage's duration, with the first stage holding its target. The duration of the scenario
+    /// is set to the total of the stage durations, and concurrency learned in earlier stages is
+    /// carried over into later ones.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .stages(vec![
+    ///             // Hold at 100 TPS for 2 minutes
+    ///             Stage::new(100, Duration::from_secs(120)),
+    ///             // Ramp up to 1K TPS over 5 minutes
+    ///             Stage::new(1_000, Duration::from_secs(300)),
+    ///             // Hold at 1K TPS for 3 minutes
+    ///             Stage::new(1_000, Duration::from_secs(180)),
+    ///         ])
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if no stages are provided, or if any stage has a target TPS of zero
+    fn stages(mut self, stages: Vec<Stage>) -> Self {
+        if stages.is_empty() {
+            panic!("At least one stage must be provided.");
+        }
+
+        if let Some(stage) = stages.iter().find(|s| s.target_tps == 0) {
+            panic!("Stage target TPS must be non-zero. Given: {stage:?}");
+        }
+
+        self.config.duration = Some(stages.iter().map(|s| s.duration).sum());
+        self.config.stages = Some(stages);
+        self
+    }
+
+    /// Run the scenario increasing TPS until a custom error rate is reached.
+    ///
+    /// This searches directly over the goal TPS, enforcing each trial TPS with the rate limiter
+    /// and adjusting the concurrency to reach it. By default the TPS doubles until the error
+    /// rate is exceeded, and then bisects between the last good and the overloaded TPS (see
+    /// [search_strategy](ConfigurableScenario::search_strategy)). Each trial is logged at the
+    /// `info` level, so the search can be followed as it converges.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         // Scale scenario until 25% error rate
+    ///         .error_rate(0.25)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the error_rate is not between 0 and 1.
+    fn error_rate(mut self, error_rate: f64) -> Self {
+        if !(0. ..=1.).contains(&error_rate) {
+            panic!(
+                "Specified error rate must be between 0 and 1. Value provided was {error_rate}."
+            );
+        }
+        self.config.error_rate = Some(error_rate);
+        self
+    }
+
+    /// Set the strategy used to find the TPS matching the goal error rate set with
+    /// [error_rate](ConfigurableScenario::error_rate).
+    ///
+    /// Defaults to [SearchStrategy::Bisect].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .error_rate(0.25)
+    ///         .search_strategy(SearchStrategy::Step)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    fn search_strategy(mut self, search: SearchStrategy) -> Self {
+        self.config.search = search;
+        self
+    }
+
+    /// Run the scenario increasing TPS until the error rate starts to climb, to find the onset
+    /// of overload.
+    ///
+    /// Unlike [error_rate](ConfigurableScenario::error_rate), this does not target a fixed
+    /// error rate, which can push the service well past the point at which it degrades.
+    /// Instead the TPS is doubled until the error rate climbs above that measured at the
+    /// initial TPS, and the highest TPS before it climbed is then found by bisection. The
+    /// scenario holds at that TPS for the rest of the run, and it is reported as
+    /// [RunStatistics::onset_tps].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let stats = my_scenario()
+    ///         .onset()
+    ///         .duration(Duration::from_secs(300))
+    ///         .await;
+    ///
+    ///     if let Some(onset_tps) = stats.onset_tps {
+    ///         println!("Errors start climbing above {onset_tps} TPS");
+    ///     }
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    fn onset(mut self) -> Self {
+        self.config.onset = true;
+        self
+    }
+
+    /// Run the scenario up to the specified latency, given a quantile.
+    ///
+    /// Balter searches for the highest TPS which keeps the latency at the given quantile under
+    /// the threshold, reported as [RunStatistics::goal_tps]. The latency measured at that
+    /// quantile is reported as [RunStatistics::latency_at_quantile].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    /// use std::num::NonZeroU32;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         // Scale scenario until p95 latency is 200ms
+    ///         .latency(Duration::from_millis(200), 0.95)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the quantile is not between 0 and 1.
+    fn latency(mut self, latency: Duration, quantile: f64) -> Self {
+        if !(0. ..=1.).contains(&quantile) {
+            panic!("Specified quantile must be between 0 and 1. Value provided was {quantile}.");
+        }
+
+        self.config.latency = Some(LatencyConfig::new(latency, quantile));
+        self
+    }
+
+    /// Run the scenario for the given duration.
+    ///
+    /// NOTE: This method doesn't make much sense without one of the other
+    /// load-testing methods (`tps()`/`error_rate()`/`latency()`)
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    /// use std::num::NonZeroU32;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(10_000)
+    ///         .duration(Duration::from_secs(120))
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    fn duration(mut self, duration: Duration) -> Self {
+        self.config.duration = Some(duration);
+        self
+    }
+
+    /// Run the scenario a fixed number of times in total, across all concurrent tasks.
+    ///
+    /// Once every iteration has completed the scenario stops, and the returned [RunStatistics]
+    /// cover the whole run. If a duration is also set, the scenario stops at whichever comes
+    /// first.
+    ///
+    /// NOTE: Each call of the scenario function counts as a single iteration, so scenarios used
+    /// with this method should not loop internally.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(1_000)
+    ///         .iterations(50_000)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    fn iterations(mut self, iterations: u64) -> Self {
+        self.config.iterations = Some(iterations);
+        self
+    }
+
+    /// Run the scenario a single time, on a single task, returning the [RunStatistics] of that run.
+    ///
+    /// This is useful as a smoke test of a scenario before load testing with it, or for
+    /// measuring the latency of an individual run. Unless a TPS is also set, transactions are
+    /// limited to the default of 512 TPS.
+    ///
+    /// NOTE: Like [iterations](ConfigurableScenario::iterations), this method should not be used
+    /// with scenarios which loop internally, since they never complete.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let stats = my_scenario().once().await;
+    ///     assert_eq!(stats.errors.total(), 0);
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    fn once(mut self) -> Self {
+        self.config.iterations = Some(1);
+        self.config.max_tps.get_or_insert(balter_core::BASE_TPS);
+        self.config.sampler.interval = Some(balter_core::MIN_INTERVAL);
+        self.fixed_concurrency(1)
+    }
+
+    /// Never send more than the given number of transactions in total, across all concurrent
+    /// tasks.
+    ///
+    /// This is a safety net for running against production services, independent of the
+    /// duration, iterations or TPS of the scenario. Each attempt of a transaction (including
+    /// retries) counts towards the budget. Once it is used up, no further transactions are
+    /// sent and the scenario stops with [StopReason::Budget], returning [RunStatistics] for
+    /// the run so far.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(50_000)
+    ///         .duration(Duration::from_secs(600))
+    ///         .request_budget(1_000_000)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    fn request_budget(mut self, budget: u64) -> Self {
+        self.config.request_budget = Some(budget);
+        self
+    }
+
+    /// Run the scenario without sending any load, for checking its configuration.
+    ///
+    /// Each transaction still waits on the rate limiter and is counted as a success, but its body
+    /// is never run. This exercises the sampler, controllers and metrics as normal, which is
+    /// useful for checking the control loop and dashboards before pointing the scenario at a
+    /// real service. The rest of the scenario body (outside of transactions) still runs.
+    ///
+    /// NOTE: A skipped transaction returns the default value of its output (e.g. `Ok(())`
+    /// for a `Result<(), E>`). Transactions whose output has no default value, such as
+    /// `balter::http::request`, panic instead, which counts as an error.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(10_000)
+    ///         .duration(Duration::from_secs(60))
+    ///         .dry_run()
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    ///     let _ = my_transaction().await;
+    /// }
+    ///
+    /// #[transaction]
+    /// async fn my_transaction() -> Result<(), String> {
+    ///     Ok(())
+    /// }
+    /// ```
+    fn dry_run(mut self) -> Self {
+        self.config.dry_run = true;
+        self
+    }
+
+    /// Apply a hint for how to run the Scenario
+    ///
+    /// By default Balter attempts to autoscale all parameters to find the optimal values for
+    /// various scenarios. However, this process can be slow due to the control loop processes
+    /// underneath (and the requirements to be adaptable to all sorts of timing
+    /// characteristics).
+    ///
+    /// This method allows providing hints to Balter to speed up finding optimal
+    /// parameters. See [Hint] for more information.
+    fn hint(mut self, hint: Hint) -> Self {
+        match hint {
+            Hint::Concurrency(concurrency) => {
+                self.config.hints.concurrency = concurrency;
+            }
+        }
+        self
+    }
+
+    /// Retry failed transactions according to the given [RetryPolicy].
+    ///
+    /// Only transactions marked with `#[transaction(retry)]` are retried. Each attempt waits on
+    /// the rate limiter, so retries count towards the TPS of the Scenario, and only the final
+    /// attempt is counted as a success or error.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(1_000)
+    ///         .retry(RetryPolicy::new(3, Duration::from_millis(50)))
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    ///     let _ = my_transaction().await;
+    /// }
+    ///
+    /// #[transaction(retry)]
+    /// async fn my_transaction() -> Result<(), ()> {
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `max_attempts` is zero.
+    fn retry(mut self, policy: RetryPolicy) -> Self {
+        if policy.max_attempts == 0 {
+            panic!("Retry policy must allow at least one attempt");
+        }
+        self.config.retry = Some(policy);
+        self
+    }
+
+    /// Set the controller used to adjust concurrency to reach the goal TPS.
+    ///
+    /// The default [ControllerKind::Ratio] reaches the goal TPS quickly, but can overshoot the
+    /// required concurrency for services where throughput does not scale linearly with the
+    /// number of concurrent requests. [ControllerKind::Pid] converges more gradually.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(10_000)
+    ///         .controller(ControllerKind::Pid(PidGains::default()))
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    fn controller(mut self, controller: ControllerKind) -> Self {
+        self.config.sampler.controller = controller;
+        self
+    }
+
+    /// Set the number of transactions the rate limiter allows through at once.
+    ///
+    /// By default transactions are spaced evenly to match the goal TPS, which produces
+    /// unnaturally smooth load. A larger burst lets up to `burst` transactions through at once
+    /// (while still averaging the goal TPS), which better mimics spiky real-world clients and
+    /// avoids the rate limiter becoming a bottleneck at high concurrency. Defaults to 1.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(10_000)
+    ///         .burst(100)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the provided burst is zero
+    fn burst(mut self, burst: u32) -> Self {
+        self.config.sampler.burst = NonZeroU32::new(burst).expect("Burst must be non-zero.");
+        self
+    }
+
+    /// Set how transactions are spaced out to match the goal TPS. Defaults to
+    /// [Arrival::Constant].
+    ///
+    /// With [Arrival::Poisson], the gaps between transactions are exponentially distributed,
+    /// like the traffic of many independent clients. The TPS still averages the goal, but
+    /// transactions occasionally arrive close together, which queues up at the service in a way
+    /// evenly spaced transactions never do.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(1_000)
+    ///         .arrival(Arrival::Poisson)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    fn arrival(mut self, arrival: Arrival) -> Self {
+        self.config.sampler.arrival = arrival;
+        self
+    }
+
+    /// Set what the goal TPS counts. Defaults to [TpsTarget::Transactions], where `.tps(100)`
+    /// sends 100 transactions per second however many the Scenario makes per run.
+    ///
+    /// With [TpsTarget::Scenarios], `.tps(100)` instead starts 100 runs of the Scenario per
+    /// second, and the transactions within each run are sent without waiting on the rate
+    /// limiter. This suits Scenarios modelling a user journey, where the load is naturally
+    /// measured in journeys. Both throughputs are reported in [RunStatistics] as
+    /// `transaction_tps` and `scenario_tps`, while `actual_tps` follows the target.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let stats = my_scenario()
+    ///         .tps(100)
+    ///         .tps_target(TpsTarget::Scenarios)
+    ///         .await;
+    ///     println!("{} journeys/s, {} TPS", stats.scenario_tps, stats.transaction_tps);
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    fn tps_target(mut self, target: TpsTarget) -> Self {
+        self.config.sampler.tps_target = target;
+        self
+    }
+
+    /// Set the maximum number of tasks Balter adds or removes each time it adjusts the
+    /// concurrency. Defaults to 100.
+    ///
+    /// Scenarios which require very high concurrency may want a larger step to ramp up faster,
+    /// while a smaller step avoids sudden jumps in load on delicate services. See also
+    /// [concurrency_step_ratio](ConfigurableScenario::concurrency_step_ratio).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(100_000)
+    ///         .concurrency_step(1_000)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the provided step is zero
+    fn concurrency_step(mut self, step: usize) -> Self {
+        if step == 0 {
+            panic!("Concurrency step must be non-zero");
+        }
+        self.config.sampler.concurrency_step = ConcurrencyStep::Absolute(step);
+        self
+    }
+
+    /// Limit each adjustment of the concurrency to the given fraction of the current
+    /// concurrency, rather than a fixed number of tasks.
+    ///
+    /// For instance, a ratio of 0.25 never changes the concurrency by more than 25% at a time.
+    /// This scales with the Scenario, ramping quickly at high concurrency while staying gentle
+    /// at low concurrency. Overrides
+    /// [concurrency_step](ConfigurableScenario::concurrency_step).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(100_000)
+    ///         .concurrency_step_ratio(0.25)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the provided ratio is not positive and finite
+    fn concurrency_step_ratio(mut self, ratio: f64) -> Self {
+        if !ratio.is_finite() || ratio <= 0. {
+            panic!("Concurrency step ratio must be positive and finite. Given: {ratio}");
+        }
+        self.config.sampler.concurrency_step = ConcurrencyStep::Proportional(ratio);
+        self
+    }
+
+    /// Set the maximum number of concurrent tasks Balter runs the scenario on.
+    ///
+    /// When the service under test is the bottleneck, Balter would otherwise keep spawning
+    /// tasks chasing an unreachable goal TPS, which can exhaust memory or file descriptors on
+    /// the load generator. If the goal TPS cannot be reached within this concurrency, the run
+    /// is marked as [RunStatistics::tps_limited] and [RunStatistics::concurrency_limited].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(100_000)
+    ///         .max_concurrency(500)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the provided maximum is zero
+    fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        if max_concurrency == 0 {
+            panic!("Maximum concurrency must be non-zero");
+        }
+        self.config.sampler.max_concurrency = Some(max_concurrency);
+        self
+    }
+
+    /// Cut the concurrency in half as soon as the p99 latency jumps beyond `multiple` times its
+    /// baseline.
+    ///
+    /// Balter otherwise only adjusts the concurrency once per sample window, and keeps adding
+    /// tasks to hold the goal TPS as latency climbs, which piles more load onto a service during
+    /// a brownout. The baseline is the lowest p99 latency measured over a sample window. Each
+    /// sample over the threshold halves the concurrency again, and Balter scales back up once
+    /// the latency recovers. Has no effect with
+    /// [fixed_concurrency](ConfigurableScenario::fixed_concurrency).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .error_rate(0.03)
+    ///         .latency_backoff(3.)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the multiple is not greater than 1
+    fn latency_backoff(mut self, multiple: f64) -> Self {
+        if multiple.is_nan() || multiple <= 1. {
+            panic!("Latency backoff multiple must be greater than 1. Given: {multiple}");
+        }
+        self.config.sampler.latency_backoff = Some(multiple);
+        self
+    }
+
+    /// Set the concurrency the scenario starts at.
+    ///
+    /// Balter otherwise starts from a low concurrency and ramps up until it reaches the goal TPS,
+    /// which can take a while for slow transactions. If you already know roughly the concurrency
+    /// needed, starting there skips most of the ramp. This is only a starting point, and the
+    /// concurrency is still adjusted from there; see
+    /// [max_concurrency](ConfigurableScenario::max_concurrency) to cap it, or
+    /// [fixed_concurrency](ConfigurableScenario::fixed_concurrency) to keep it fixed. Equivalent
+    /// to `.hint(Hint::Concurrency(concurrency))`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(10_000)
+    ///         .concurrency(200)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the provided concurrency is zero
+    fn concurrency(self, concurrency: usize) -> Self {
+        if concurrency == 0 {
+            panic!("Concurrency must be non-zero");
+        }
+        self.hint(Hint::Concurrency(concurrency))
+    }
+
+    /// Run the scenario at a fixed concurrency, rather than adjusting the concurrency to reach
+    /// the goal TPS.
+    ///
+    /// The scenario is still sampled, and the measured TPS, error rate and latency are reported
+    /// in [RunStatistics] as usual. Combined with [tps](ConfigurableScenario::tps) this measures
+    /// how the service behaves at a given concurrency and TPS limit. If the concurrency is too
+    /// low to reach the goal TPS the scenario runs below it, with
+    /// [RunStatistics::stable] set to `false`. Equivalent to
+    /// [concurrency](ConfigurableScenario::concurrency) with [ControllerKind::Fixed].
+    ///
+    /// The concurrency holds even if the scenario panics: panics are counted as errors, and any
+    /// task which dies anyway is replaced at the next sample.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let stats = my_scenario()
+    ///         .tps(1_000)
+    ///         .fixed_concurrency(50)
+    ///         .duration(Duration::from_secs(30))
+    ///         .await;
+    ///     println!("p99 latency: {:?}", stats.latency_p99);
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the provided concurrency is zero
+    fn fixed_concurrency(mut self, concurrency: usize) -> Self {
+        if concurrency == 0 {
+            panic!("Concurrency must be non-zero");
+        }
+        self.config.hints.concurrency = concurrency;
+        self.config.sampler.controller = ControllerKind::Fixed;
+        self
+    }
+
+    /// Ramp up from the starting concurrency, rather than estimating the concurrency needed from
+    /// the first sample.
+    ///
+    /// By default Balter takes one sample at the starting concurrency, then jumps straight to the
+    /// concurrency needed to sustain the goal TPS at the measured latency (by Little's Law,
+    /// `concurrency = TPS * latency`), up to the
+    /// [max_concurrency](ConfigurableScenario::max_concurrency). This shortens the ramp for high
+    /// goal TPS, but can overshoot for services whose latency climbs steeply with load.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(10_000)
+    ///         .skip_warmup()
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    fn skip_warmup(mut self) -> Self {
+        self.config.sampler.skip_warmup = true;
+        self
+    }
+
+    /// Abort the scenario once the error rate exceeds `error_rate` for `consecutive_samples`
+    /// samples in a row.
+    ///
+    /// This acts as a circuit breaker, to avoid hammering a clearly broken service for the full
+    /// duration of a run. Unlike [error_rate](ConfigurableScenario::error_rate), which searches
+    /// for the TPS at which a given error rate occurs, this stops the run entirely. Aborted runs
+    /// are marked with [RunStatistics::aborted] and [StopReason::ErrorRate].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(1_000)
+    ///         .duration(Duration::from_secs(3_600))
+    ///         // Abort if more than half of all transactions fail for 3 samples in a row
+    ///         .abort_on_error_rate(0.5, 3)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `error_rate` is not between 0 and 1, or if
+    /// `consecutive_samples` is zero.
+    fn abort_on_error_rate(mut self, error_rate: f64, consecutive_samples: usize) -> Self {
+        if !(0. ..=1.).contains(&error_rate) {
+            panic!("Error rate must be between 0 and 1. Given: {error_rate}");
+        }
+        if consecutive_samples == 0 {
+            panic!("Number of consecutive samples must be non-zero");
+        }
+        self.config.abort_error_rate = Some(ErrorRateAbort {
+            error_rate,
+            consecutive_samples,
+        });
+        self
+    }
+
+    /// Seed the RNG available to the scenario through [balter::rng()](crate::rng()).
+    ///
+    /// Each task running the scenario derives its own seed from the one provided, so that
+    /// generated data is reproducible across runs. Note that the interleaving of tasks, and so
+    /// the overall order of requests, still depends on timing.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use rand::Rng;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(1_000)
+    ///         .seed(42)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    ///     let user_id: u64 = balter::rng().gen_range(0..10_000);
+    /// }
+    /// ```
+    fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    /// Stop the scenario once it has panicked the given number of times.
+    ///
+    /// By default a panic in the scenario is logged and counted as an error, and the scenario is
+    /// run again so that the concurrency is maintained. This is useful for surfacing bugs in the
+    /// scenario itself without them being hidden in the error rate.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(1_000)
+    ///         .duration(Duration::from_secs(300))
+    ///         .max_panics(10)
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    fn max_panics(mut self, max_panics: u64) -> Self {
+        self.config.sampler.max_panics = Some(max_panics);
+        self
+    }
+
+    /// Compute the returned [RunStatistics] over the entire run.
+    ///
+    /// By default the TPS, error rate and latency quantiles are computed only over the sample
+    /// windows in which Balter had stabilized on the goal TPS, which excludes the initial
+    /// ramp-up. This method includes every sample instead.
+    fn whole_run_statistics(mut self) -> Self {
+        self.config.whole_run_statistics = true;
+        self
+    }
+
+    /// Run the scenario on a dedicated multi-threaded tokio runtime, rather than the runtime it
+    /// is awaited from.
+    ///
+    /// This isolates the load generator from anything else running in the process, and limits
+    /// the CPU it can use to the given number of worker threads. This is useful when load testing
+    /// a service running on the same machine, where an unbounded load generator would steal CPU
+    /// from the service under test.
+    ///
+    /// The runtime is built on a thread of its own when the scenario starts, and shut down once
+    /// it completes. The outer runtime (e.g. from `#[tokio::main]`) only waits for the
+    /// [RunStatistics], so it can be kept small, such as with
+    /// `#[tokio::main(flavor = "current_thread")]`. Tokio IO resources such as sockets are bound
+    /// to the runtime they were created on, so create them within the scenario (or its
+    /// [setup](ConfigurableScenario::setup)) rather than on the outer runtime.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(10_000)
+    ///         .runtime(RuntimeConfig::new(2))
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the number of worker threads is zero
+    fn runtime(mut self, runtime: RuntimeConfig) -> Self {
+        if runtime.worker_threads == 0 {
+            panic!("Runtime must have at least one worker thread");
+        }
+        self.config.runtime = Some(runtime);
+        self
+    }
+
+    /// Set how long to wait for running scenarios to finish when the scenario completes.
+    ///
+    /// Once the duration has elapsed, Balter stops starting new runs of the scenario and waits
+    /// for in-flight runs to finish. Any which take longer than this timeout (for instance
+    /// scenarios which loop internally) are aborted. Defaults to 1 second.
+    fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.config.sampler.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Set the number of samples Balter collects before deciding whether a measurement is
+    /// stable.
+    ///
+    /// Larger windows smooth out noise for fast, jittery scenarios at the cost of reacting more
+    /// slowly. Defaults to 5.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(10_000)
+    ///         .sample_window(20)
+    ///         .await;
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the window is less than 2.
+    fn sample_window(mut self, window: usize) -> Self {
+        if window < 2 {
+            panic!("Sample window must be at least 2");
+        }
+        self.config.sampler.window = window;
+        self
+    }
+
+    /// Set the number of samples the error rate is measured over when searching for the TPS
+    /// at a given [error_rate](ConfigurableScenario::error_rate).
+    ///
+    /// The error rate tends to be noisier than the TPS, and by default only the most recent
+    /// sample is used, so a single noisy sample can make Balter back off. A longer window
+    /// smooths this out at the cost of reacting more slowly. This is independent of the
+    /// [sample_window](ConfigurableScenario::sample_window) used to decide whether the TPS is
+    /// stable. Defaults to 1.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .error_rate(0.05)
+    ///         .error_window(10)
+    ///         .await;
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the window is zero.
+    fn error_window(mut self, window: usize) -> Self {
+        if window == 0 {
+            panic!("Error window must be non-zero");
+        }
+        self.config.sampler.error_window = window;
+        self
+    }
+
+    /// Set how far below the goal TPS the measured TPS can be while still counting as reaching
+    /// it, as a fraction of the goal TPS.
+    ///
+    /// Balter keeps adjusting the concurrency until the measured TPS reaches the goal. For
+    /// scenarios with a lot of variance in their TPS, a wider tolerance avoids endlessly
+    /// adjusting the concurrency around the goal. Defaults to 0.02.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(1_000)
+    ///         .tps_tolerance(0.15)
+    ///         .await;
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the tolerance is not between 0 and 1.
+    fn tps_tolerance(mut self, tolerance: f64) -> Self {
+        if !(0. ..1.).contains(&tolerance) {
+            panic!("TPS tolerance must be between 0 and 1");
+        }
+        self.config.sampler.tps_tolerance = tolerance;
+        self
+    }
+
+    /// Set the number of samples to discard before each sample window.
+    ///
+    /// Measurements taken right after Balter adjusts the concurrency or TPS limit tend to be
+    /// noisy; skipping them can help Balter stabilize faster. Defaults to 0.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(10_000)
+    ///         .skip_samples(2)
+    ///         .await;
+    /// }
+    /// ```
+    fn skip_samples(mut self, skip: usize) -> Self {
+        self.config.sampler.skip = skip;
+        self
+    }
+
+    /// Set the interval over which each sample is measured.
+    ///
+    /// By default Balter samples every second, or every 5 seconds for scenarios below 150 TPS.
+    /// At very low TPS short intervals only see a handful of transactions each, which makes the
+    /// measured TPS noisy; a longer interval smooths this out. Intervals below 10ms are raised
+    /// to 10ms.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(20)
+    ///         .sample_interval(Duration::from_secs(2))
+    ///         .await;
+    /// }
+    /// ```
+    fn sample_interval(mut self, interval: Duration) -> Self {
+        self.config.sampler.interval = Some(interval);
+        self
+    }
+
+    /// Set the bounds within which Balter adapts the sample interval.
+    ///
+    /// Balter lengthens the sample interval when measurements are noisy, and shortens it when
+    /// they are consistent. The interval is also kept to at least 10 times the median
+    /// transaction latency, since transactions still in flight at the end of a sample are
+    /// counted in the next one. By default the interval stays between 1 and 5 seconds (extended to
+    /// include any interval set with
+    /// [sample_interval](ConfigurableScenario::sample_interval)). Setting both bounds to the
+    /// same value disables adaptation.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(10_000)
+    ///         .sample_interval(Duration::from_millis(200))
+    ///         .sample_interval_bounds(Duration::from_millis(100), Duration::from_secs(2))
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `min` is greater than `max`, or if `max` is greater than 10
+    /// seconds.
+    fn sample_interval_bounds(mut self, min: Duration, max: Duration) -> Self {
+        if min > max {
+            panic!("Minimum sample interval must not be greater than the maximum. Given: {min:?} > {max:?}");
+        }
+        if max > balter_core::MAX_INTERVAL {
+            panic!(
+                "Maximum sample interval must be at most {:?}. Given: {max:?}",
+                balter_core::MAX_INTERVAL
+            );
+        }
+        self.config.sampler.interval_bounds = Some((min.max(balter_core::MIN_INTERVAL), max));
+        self
+    }
+
+    /// Stop the Scenario once the provided condition is met.
+    ///
+    /// The condition is checked against the most recent [SampleSet] each time Balter samples
+    /// the Scenario. If a duration is also set, it acts as an upper bound on how long the
+    /// Scenario runs for. [RunStatistics::stop_reason] records which of the two ended the run.
+    ///
+    /// NOTE: The condition is called from Balter's sampling loop, so it should return quickly.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .ramp_tps(100, 10_000)
+    ///         .duration(Duration::from_secs(600))
+    ///         .until(|samples| samples.latency(0.99) > Duration::from_millis(500))
+    ///         .await;
+    /// }
+    /// ```
+    fn until(mut self, condition: impl Fn(&SampleSet) -> bool + Send + 'static) -> Self {
+        self.hooks.until = Some(Box::new(condition));
+        self
+    }
+
+    /// Control the TPS of the Scenario with a custom [Controller].
+    ///
+    /// The Controller is consulted each time Balter samples the Scenario, alongside any
+    /// controllers from `.tps()`, `.error_rate()`, `.latency()`, etc. The Scenario runs at the
+    /// lowest TPS any of them allows, and stops with [StopReason::Controller] if any of them
+    /// returns [ControllerOutcome::Stop].
+    ///
+    /// NOTE: Custom Controllers are local to this instance, and are not distributed to peers.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use balter::SampleSet;
+    /// use std::num::NonZeroU32;
+    /// use std::time::Duration;
+    ///
+    /// /// Double the TPS each time the Scenario stabilizes, until latency degrades.
+    /// struct Doubling(NonZeroU32);
+    ///
+    /// impl Controller for Doubling {
+    ///     fn initial_tps(&self) -> NonZeroU32 {
+    ///         self.0
+    ///     }
+    ///
+    ///     fn analyze(&mut self, samples: &SampleSet, stable: bool) -> ControllerOutcome {
+    ///         if samples.latency(0.99) > Duration::from_millis(200) {
+    ///             return ControllerOutcome::Stop;
+    ///         }
+    ///         if stable {
+    ///             self.0 = self.0.saturating_mul(NonZeroU32::new(2).unwrap());
+    ///         }
+    ///         ControllerOutcome::Limit(self.0)
+    ///     }
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps_controller(Doubling(NonZeroU32::new(100).unwrap()))
+    ///         .duration(Duration::from_secs(600))
+    ///         .await;
+    /// }
+    /// ```
+    fn tps_controller(mut self, controller: impl Controller + 'static) -> Self {
+        self.hooks.controller = Some(Box::new(controller));
+        self
+    }
+
+    /// Call the provided function each time Balter samples the Scenario.
+    ///
+    /// The callback receives the most recent [SampleSet] along with a [LiveStats] snapshot of
+    /// the current concurrency, goal TPS, and measured TPS and error rate, which is useful for
+    /// reporting progress during long runs.
+    ///
+    /// NOTE: The callback is called from Balter's sampling loop, which is time-sensitive. It
+    /// should return quickly; any slow work (e.g. rendering or I/O) should be handed off to
+    /// another task, for instance through a channel.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let (tx, mut rx) = tokio::sync::watch::channel(LiveStats::default());
+    ///     tokio::spawn(async move {
+    ///         while rx.changed().await.is_ok() {
+    ///             let stats = *rx.borrow();
+    ///             println!("{:.0} TPS at {} concurrency", stats.actual_tps, stats.concurrency);
+    ///         }
+    ///     });
+    ///
+    ///     my_scenario()
+    ///         .tps(10_000)
+    ///         .duration(Duration::from_secs(300))
+    ///         .on_sample(move |_samples, stats| {
+    ///             let _ = tx.send(*stats);
+    ///         })
+    ///         .await;
+    /// }
+    /// ```
+    fn on_sample(mut self, callback: impl Fn(&SampleSet, &LiveStats) + Send + 'static) -> Self {
+        self.hooks.on_sample = Some(Box::new(callback));
+        self
+    }
+
+    /// Call the provided function each time the Scenario becomes stable, or stops being stable.
+    ///
+    /// The Scenario is stable once the measured TPS has settled at the goal TPS, which
+    /// [LiveStats::stable] reports for the sample in which this changed. Balter also logs each
+    /// change at the `info` level, along with the concurrency, measured TPS and error rate.
+    /// This is useful for correlating when a long run settled with external dashboards.
+    ///
+    /// NOTE: As with [on_sample](ConfigurableScenario::on_sample), the callback is called from
+    /// Balter's sampling loop and should return quickly.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(10_000)
+    ///         .duration(Duration::from_secs(300))
+    ///         .on_stability_change(|stats| {
+    ///             if stats.stable {
+    ///                 println!("Stable at {:?} with {} concurrency", stats.elapsed, stats.concurrency);
+    ///             }
+    ///         })
+    ///         .await;
+    /// }
+    /// ```
+    fn on_stability_change(mut self, callback: impl Fn(&LiveStats) + Send + 'static) -> Self {
+        self.hooks.on_stability_change = Some(Box::new(callback));
+        self
+    }
+
+    /// Record each sample to a CSV file at the provided path, for offline analysis.
+    ///
+    /// Each row holds the elapsed time, concurrency, goal and measured TPS, success and error
+    /// counts, error rate, latency quantiles and wall-clock timestamp for one sample. Times are
+    /// in seconds, with timestamps counted from the Unix epoch. The file is created (or
+    /// truncated) immediately.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(10_000)
+    ///         .duration(Duration::from_secs(300))
+    ///         .record_csv("samples.csv")
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the file cannot be created
+    fn record_csv(mut self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let recorder = CsvRecorder::create(path)
+            .unwrap_or_else(|err| panic!("Unable to create CSV file at {}: {err}", path.display()));
+        self.hooks.csv = Some(recorder);
+        self
+    }
+
+    /// Run the scenario with the provided arguments, which are fetched with
+    /// [balter::args()](crate::args()).
+    ///
+    /// This is useful for running the same scenario against several sets of parameters, such
+    /// as different payload sizes, with separate [RunStatistics] for each. The arguments are
+    /// serialized, so that they are handed off to peers along with the rest of the
+    /// configuration when the scenario is distributed.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     for payload_size in [1_024, 65_536, 1_048_576] {
+    ///         let stats = my_scenario()
+    ///             .tps(1_000)
+    ///             .duration(Duration::from_secs(60))
+    ///             .args(payload_size)
+    ///             .await;
+    ///         println!("{payload_size} bytes: {:?}", stats.latency_p99);
+    ///     }
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    ///     let payload_size = balter::args::<usize>();
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the arguments cannot be serialized
+    fn args<A: Serialize>(mut self, args: A) -> Self {
+        let bytes = bincode::serialize(&args)
+            .unwrap_or_else(|err| panic!("Unable to serialize the Scenario args: {err}"));
+        self.config.args = Some(bytes);
+        self
+    }
+
+    /// Share state with every run of the scenario, which is fetched with
+    /// [balter::state()](crate::state()).
+    ///
+    /// This is useful for sharing something which is expensive to set up, such as an HTTP client
+    /// and its connection pool, without building it on every run. The state is shared as-is
+    /// rather than cloned, so any mutation needs its own synchronization. State of several
+    /// different types can be shared by calling this method once for each.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::sync::Arc;
+    ///
+    /// struct Client {
+    ///     // e.g. a connection pool
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(1_000)
+    ///         .with_state(Arc::new(Client {}))
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    ///     let client = balter::state::<Client>();
+    /// }
+    /// ```
+    fn with_state<S: Send + Sync + 'static>(mut self, state: Arc<S>) -> Self {
+        self.hooks.state.insert(state);
+        self
+    }
+
+    /// Give each concurrent task of the scenario its own state, which is fetched with
+    /// [balter::state()](crate::state()).
+    ///
+    /// `factory` is called with the worker ID of each task as it is spawned, which is unique
+    /// among the tasks running at the same time (from `0` up to the concurrency). This is useful
+    /// for resources which can't be shared, such as a dedicated connection. As the concurrency
+    /// changes, the state is built for new tasks and dropped along with removed ones, so a worker
+    /// ID can be reused. Per-worker state takes precedence over state of the same type provided
+    /// with [with_state](ConfigurableScenario::with_state).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    ///
+    /// struct Worker {
+    ///     id: usize,
+    ///     sequence: AtomicU64,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     my_scenario()
+    ///         .tps(1_000)
+    ///         .per_worker(|id| Worker { id, sequence: AtomicU64::new(0) })
+    ///         .await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    ///     let worker = balter::state::<Worker>();
+    ///     let sequence = worker.sequence.fetch_add(1, Ordering::Relaxed);
+    /// }
+    /// ```
+    fn per_worker<W: Send + Sync + 'static>(
+        mut self,
+        factory: impl Fn(usize) -> W + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.state.insert_worker(factory);
+        self
+    }
+
+    /// Run `setup` once before the scenario starts, providing its output as shared state.
+    ///
+    /// Useful for one-time work such as creating a test user or fetching an auth token. The
+    /// setup is not counted towards the TPS or the [duration](ConfigurableScenario::duration)
+    /// of the scenario. Its output is available with [state](crate::state) in the same way as
+    /// state provided with [with_state](ConfigurableScenario::with_state).
     ///
     /// # Example
     /// ```no_run
     /// use balter::prelude::*;
     /// use std::time::Duration;
     ///
+    /// struct Token(String);
+    ///
     /// #[tokio::main]
     /// async fn main() {
     ///     my_scenario()
-    ///         // Scale scenario until 5K TPS
-    ///         .tps(5_000)
+    ///         .tps(1_000)
+    ///         .duration(Duration::from_secs(300))
+    ///         .setup(async { Token(login().await) })
+    ///         .teardown(async { logout().await })
     ///         .await;
     /// }
     ///
     /// #[scenario]
     /// async fn my_scenario() {
+    ///     let token = balter::state::<Token>();
     /// }
-    /// ```
     ///
-    /// # Panics
+    /// async fn login() -> String {
+    ///     "token".to_string()
+    /// }
     ///
-    /// This function will panic if the provided TPS is zero
-    fn tps(mut self, tps: u32) -> Self {
-        self.config.max_tps =
-            Some(NonZeroU32::new(tps).expect("TPS provided must be non-zero. Given: {tps}"));
+    /// async fn logout() {
+    /// }
+    /// ```
+    fn setup<S: Send + Sync + 'static>(
+        mut self,
+        setup: impl Future<Output = S> + Send + 'static,
+    ) -> Self {
+        self.hooks.setup = Some(Box::new(move |mut state| {
+            Box::pin(async move {
+                state.insert(Arc::new(setup.await));
+                state
+            })
+        }));
         self
     }
 
-    /// Run the scenario increasing TPS until a custom error rate is reached.
+    /// Run `teardown` once after the scenario ends.
+    ///
+    /// The teardown runs after all tasks running the scenario have shut down, however the
+    /// scenario ended, including when it is aborted. If the scenario's runner is dropped or
+    /// panics before the scenario ends, the teardown is spawned onto the tokio runtime instead.
+    /// The teardown is not counted towards the TPS or the duration of the scenario. See
+    /// [setup](ConfigurableScenario::setup) for an example.
+    fn teardown(mut self, teardown: impl Future<Output = ()> + Send + 'static) -> Self {
+        self.hooks.teardown = Some(Box::pin(teardown));
+        self
+    }
+
+    /// Route the scenario's metrics to `recorder`, rather than the global recorder.
+    ///
+    /// This covers Balter's own metrics as well as those emitted with
+    /// [balter::metrics](crate::metrics) from the scenario's tasks, which keeps the metrics of
+    /// several scenarios run in the same process apart from each other. Metrics emitted from
+    /// tasks the scenario spawns itself still go to the global recorder.
     ///
     /// # Example
     /// ```no_run
     /// use balter::prelude::*;
     /// use std::time::Duration;
+    /// # fn tenant_recorder(tenant: &str) -> metrics::NoopRecorder { metrics::NoopRecorder }
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     my_scenario()
-    ///         // Scale scenario until 25% error rate
-    ///         .error_rate(0.25)
-    ///         .await;
+    ///     for tenant in ["alpha", "beta"] {
+    ///         my_scenario()
+    ///             .tps(1_000)
+    ///             .duration(Duration::from_secs(60))
+    ///             .metrics_recorder(tenant_recorder(tenant))
+    ///             .await;
+    ///     }
     /// }
     ///
     /// #[scenario]
     /// async fn my_scenario() {
     /// }
     /// ```
-    ///
-    /// # Panics
-    ///
-    /// This function will panic if the error_rate is not between 0 and 1.
-    fn error_rate(mut self, error_rate: f64) -> Self {
-        if !(0. ..=1.).contains(&error_rate) {
-            panic!(
-                "Specified error rate must be between 0 and 1. Value provided was {error_rate}."
-            );
-        }
-        self.config.error_rate = Some(error_rate);
+    fn metrics_recorder(
+        mut self,
+        recorder: impl ::metrics::Recorder + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.recorder = Some(Arc::new(recorder));
         self
     }
 
-    /// Run the scenario up to the specified latency, given a quantile.
+    /// Attach the label `key="value"` to every metric the scenario emits, e.g. to tell apart
+    /// the metrics of different environments, regions or builds. Can be called repeatedly to
+    /// add several labels, with a later value for the same key replacing the earlier one.
+    ///
+    /// This covers Balter's own metrics as well as those emitted with
+    /// [balter::metrics](crate::metrics) from the scenario's tasks. The labels are part of the
+    /// scenario's configuration, so they are also attached to the metrics of peers which take
+    /// on its work.
     ///
     /// # Example
     /// ```no_run
     /// use balter::prelude::*;
     /// use std::time::Duration;
-    /// use std::num::NonZeroU32;
     ///
     /// #[tokio::main]
     /// async fn main() {
     ///     my_scenario()
-    ///         // Scale scenario until p95 latency is 200ms
-    ///         .latency(Duration::from_millis(200), 0.95)
+    ///         .tps(1_000)
+    ///         .duration(Duration::from_secs(60))
+    ///         .label("environment", "staging")
+    ///         .label("region", "eu-west-1")
     ///         .await;
     /// }
     ///
@@ -156,32 +2261,42 @@ where
     ///
     /// # Panics
     ///
-    /// This function will panic if the quantile is not between 0 and 1.
-    fn latency(mut self, latency: Duration, quantile: f64) -> Self {
-        if !(0. ..=1.).contains(&quantile) {
-            panic!("Specified quantile must be between 0 and 1. Value provided was {quantile}.");
+    /// This function will panic if the key is one of the labels Balter sets itself (`scenario`,
+    /// `transaction`, `server`, `status` or `kind`)
+    fn label(mut self, key: &str, value: &str) -> Self {
+        if RESERVED_LABELS.contains(&key) {
+            panic!("Label `{key}` is reserved by Balter");
         }
-
-        self.config.latency = Some(LatencyConfig::new(latency, quantile));
+        self.config
+            .labels
+            .insert(key.to_string(), value.to_string());
         self
     }
 
-    /// Run the scenario for the given duration.
+    /// Split the goal TPS evenly across the provided peers when the scenario starts (requires
+    /// `rt` feature).
     ///
-    /// NOTE: This method doesn't make much sense without one of the other
-    /// load-testing methods (`tps()`/`error_rate()`/`latency()`)
+    /// This instance and each peer run an equal slice of the TPS set with
+    /// [tps](ConfigurableScenario::tps), with any remainder spread one TPS at a time over the
+    /// first slices. Peers must be running a [BalterRuntime](crate::BalterRuntime)
+    /// which knows of the scenario. Work handed off to peers is health-checked in the same way
+    /// as work redistributed while the scenario is running.
     ///
     /// # Example
     /// ```no_run
     /// use balter::prelude::*;
     /// use std::time::Duration;
-    /// use std::num::NonZeroU32;
     ///
     /// #[tokio::main]
     /// async fn main() {
+    ///     tokio::spawn(async {
+    ///         BalterRuntime::new().with_args().run().await;
+    ///     });
+    ///
     ///     my_scenario()
-    ///         .tps(10_000)
-    ///         .duration(Duration::from_secs(120))
+    ///         .tps(30_000)
+    ///         .duration(Duration::from_secs(300))
+    ///         .peers(&["10.0.0.2:7621".parse().unwrap(), "10.0.0.3:7621".parse().unwrap()])
     ///         .await;
     /// }
     ///
@@ -189,27 +2304,62 @@ where
     /// async fn my_scenario() {
     /// }
     /// ```
-    fn duration(mut self, duration: Duration) -> Self {
-        self.config.duration = Some(duration);
+    #[cfg(feature = "rt")]
+    fn peers(mut self, peers: &[SocketAddr]) -> Self {
+        self.hooks.peers = peers.to_vec();
         self
     }
 
-    /// Apply a hint for how to run the Scenario
+    /// Run the scenario on a separate tokio task, returning a [ScenarioHandle] which can be
+    /// used to stop or pause it, watch its [LiveStats], or override its goal TPS.
     ///
-    /// By default Balter attempts to autoscale all parameters to find the optimal values for
-    /// various scenarios. However, this process can be slow due to the control loop processes
-    /// underneath (and the requirements to be adaptable to all sorts of timing
-    /// characteristics).
+    /// Must be called from within a tokio runtime.
     ///
-    /// This method allows providing hints to Balter to speed up finding optimal
-    /// parameters. See [Hint] for more information.
-    fn hint(mut self, hint: Hint) -> Self {
-        match hint {
-            Hint::Concurrency(concurrency) => {
-                self.config.hints.concurrency = concurrency;
-            }
+    /// # Example
+    /// ```no_run
+    /// use balter::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let handle = my_scenario()
+    ///         .tps(1_000)
+    ///         .duration(Duration::from_secs(3_600))
+    ///         .spawn();
+    ///
+    ///     tokio::signal::ctrl_c().await.unwrap();
+    ///     handle.stop();
+    ///     let stats = handle.stats().await;
+    /// }
+    ///
+    /// #[scenario]
+    /// async fn my_scenario() {
+    /// }
+    /// ```
+    fn spawn(mut self) -> ScenarioHandle {
+        let stop = Arc::new(AtomicBool::new(false));
+        self.hooks.stop = Some(stop.clone());
+        let (live_tx, live) = watch::channel(LiveStats::default());
+        let (commands, commands_rx) = mpsc::unbounded_channel();
+        let (paused, paused_rx) = watch::channel(false);
+        self.hooks.control = Some(ScenarioControl {
+            live: live_tx,
+            commands: commands_rx,
+            paused: paused_rx,
+        });
+        ScenarioHandle {
+            stop,
+            task: tokio::spawn(self),
+            live,
+            commands,
+            paused,
         }
-        self
+    }
+
+    /// Name of the scenario, which is used in metric labels and to look it up in distributed
+    /// runs.
+    fn name(&self) -> &str {
+        &self.config.name
     }
 }
 
@@ -218,10 +2368,10 @@ mod runtime {
     use super::*;
     use balter_runtime::DistributedScenario;
 
-    impl<T, F> DistributedScenario for Scenario<T>
+    impl<T> DistributedScenario for Scenario<T>
     where
-        T: Fn() -> F + Send + 'static + Clone + Sync,
-        F: Future<Output = ()> + Send,
+        T: Clone + Send + 'static,
+        Self: Future<Output = RunStatistics>,
     {
         #[allow(unused)]
         fn set_config(
@@ -232,18 +2382,129 @@ mod runtime {
                 func: self.func.clone(),
                 runner_fut: None,
                 config,
+                hooks: RunHooks::default(),
             })
         }
     }
 }
 
-#[instrument(name="scenario", skip_all, fields(name=config.name))]
-pub(crate) async fn run_scenario<T, F>(scenario: T, config: ScenarioConfig) -> RunStatistics
+/// Run a [SingleThreaded] scenario to completion on a dedicated thread, since its tasks can't
+/// be moved between the threads of the caller's runtime.
+async fn run_single_threaded<T, F>(
+    scenario: SingleThreaded<T>,
+    config: ScenarioConfig,
+    hooks: RunHooks,
+) -> RunStatistics
 where
     T: Fn() -> F + Send + Sync + 'static + Clone,
-    F: Future<Output = ()> + Send,
+    F: Future<Output = ()> + 'static,
 {
-    if config.is_unconfigured() {
+    if config.runtime.is_some() {
+        warn!(
+            "Ignoring the runtime configured for {}, since single-threaded scenarios always run on a runtime of their own.",
+            config.name
+        );
+    }
+
+    let span = tracing::Span::current();
+    run_on_thread(config, hooks, move |config, hooks| {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Unable to build single-threaded runtime");
+        tokio::task::LocalSet::new().block_on(
+            &runtime,
+            run_scenario(scenario, config, hooks).instrument(span),
+        )
+    })
+    .await
+}
+
+/// Run a scenario on the runtime configured with [runtime](ConfigurableScenario::runtime) if
+/// there is one, or on the caller's runtime otherwise.
+async fn run_scenario_on_runtime<T: SpawnWorker>(
+    scenario: T,
+    config: ScenarioConfig,
+    hooks: RunHooks,
+) -> RunStatistics {
+    let Some(runtime) = config.runtime else {
+        return run_scenario(scenario, config, hooks).await;
+    };
+
+    let span = tracing::Span::current();
+    run_on_thread(config, hooks, move |config, hooks| {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(runtime.worker_threads)
+            .thread_name(format!("balter-{}-worker", config.name))
+            .enable_all()
+            .build()
+            .expect("Unable to build scenario runtime");
+        runtime.block_on(run_scenario(scenario, config, hooks).instrument(span))
+    })
+    .await
+}
+
+/// Run a scenario to completion on a dedicated thread, which drives the runtime built by `run`.
+async fn run_on_thread(
+    config: ScenarioConfig,
+    mut hooks: RunHooks,
+    run: impl FnOnce(ScenarioConfig, RunHooks) -> RunStatistics + Send + 'static,
+) -> RunStatistics {
+    // NOTE: The thread keeps running if this future is dropped, so we signal it to stop instead.
+    let stop = hooks
+        .stop
+        .get_or_insert_with(|| Arc::new(AtomicBool::new(false)))
+        .clone();
+    let _guard = StopOnDrop(stop);
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    std::thread::Builder::new()
+        .name(format!("balter-{}", config.name))
+        .spawn(move || {
+            let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| run(config, hooks)));
+            let _ = tx.send(res);
+        })
+        .expect("Unable to spawn scenario thread");
+
+    match rx.await {
+        Ok(Ok(stats)) => stats,
+        Ok(Err(panic)) => std::panic::resume_unwind(panic),
+        Err(_) => panic!("Scenario thread exited without finishing"),
+    }
+}
+
+struct StopOnDrop(Arc<AtomicBool>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+#[instrument(name="scenario", skip_all, fields(name=config.name))]
+pub(crate) async fn run_scenario<T: SpawnWorker>(
+    scenario: T,
+    config: ScenarioConfig,
+    mut hooks: RunHooks,
+) -> RunStatistics {
+    let recorder = hooks.recorder.take();
+    let labels = (!config.labels.is_empty()).then(|| {
+        config
+            .labels
+            .iter()
+            .map(|(key, value)| ::metrics::Label::new(key.clone(), value.clone()))
+            .collect()
+    });
+    let run = with_labels(labels, run_scenario_inner(scenario, config, hooks));
+    with_recorder(recorder, run).await
+}
+
+async fn run_scenario_inner<T: SpawnWorker>(
+    scenario: T,
+    config: ScenarioConfig,
+    mut hooks: RunHooks,
+) -> RunStatistics {
+    if config.is_unconfigured() && hooks.controller.is_none() {
         debug!(
             "Not load testing {} with config {:?}, because it has no work to do.",
             config.name, &config
@@ -251,77 +2512,351 @@ where
         return RunStatistics::default();
     }
 
+    if let Some(method) = config.missing_duration() {
+        error!(
+            "Not load testing {}, because `.{method}()` requires a `.duration()` to be set.",
+            config.name
+        );
+        return RunStatistics {
+            stop_reason: StopReason::MissingDuration,
+            aborted: true,
+            ..Default::default()
+        };
+    }
+
+    #[cfg(feature = "rt")]
+    let config = {
+        let mut peers = std::mem::take(&mut hooks.peers);
+        if peers.is_empty() && config.global_tps {
+            peers = balter_runtime::runtime::active_peers();
+        }
+
+        if peers.is_empty() {
+            config
+        } else {
+            shard_work(config, &peers).await
+        }
+    };
+
     info!("Running {} with config {:?}", config.name, &config);
+    if config.dry_run {
+        warn!(
+            "Dry run of {}: transactions are skipped, and no load is sent.",
+            config.name
+        );
+    }
+
+    if let Some(args) = &config.args {
+        hooks.state.set_args(args.clone());
+    }
+
+    let teardown = Teardown(hooks.teardown.take());
+    if let Some(setup) = hooks.setup.take() {
+        debug!("Running setup for {}", config.name);
+        hooks.state = setup(std::mem::take(&mut hooks.state)).await;
+    }
 
     let start = Instant::now();
 
-    let mut controllers = CompositeController::new(&config);
+    let mut controllers = CompositeController::new(&config, hooks.controller.take());
     //let mut sampler = ConcurrentSampler::new(&config.name, scenario, controllers.initial_tps());
     let mut sampler = Sampler::new(
-        &config.name,
         scenario,
         controllers.initial_tps(),
-        config.concurrency(),
+        &config,
+        std::mem::take(&mut hooks.state),
     )
     .await;
 
+    if let Some(duration) = config.duration {
+        sampler.set_deadline(start + duration);
+    }
+    if let Some(control) = &hooks.control {
+        sampler.set_pause(control.paused.clone());
+    }
+
+    let mut error_samples = 0;
+    let mut was_stable = false;
+    let mut override_tps = None;
+
     // NOTE: This loop is time-sensitive. Any long awaits or blocking will throw off measurements
-    let final_sample = loop {
+    let (final_stable, final_sample, stop_reason) = loop {
         let (stable, samples) = sampler.sample().await;
 
+        let stats = LiveStats {
+            elapsed: start.elapsed(),
+            concurrency: sampler.concurrency(),
+            goal_tps: sampler.tps_limit().get(),
+            actual_tps: samples.tps,
+            error_rate: samples.error_rate,
+            stable,
+        };
+        if let Some(on_sample) = &hooks.on_sample {
+            on_sample(sampler.samples(), &stats);
+        }
+        if let Some(csv) = &mut hooks.csv {
+            csv.record(&stats, &samples);
+        }
+        if let Some(control) = &mut hooks.control {
+            control.live.send_replace(stats);
+            while let Ok(command) = control.commands.try_recv() {
+                match command {
+                    ScenarioCommand::GoalTps(tps) => {
+                        info!("Overriding goal TPS of {} to {tps}", config.name);
+                        override_tps = Some(tps);
+                    }
+                    ScenarioCommand::Concurrency(concurrency) => {
+                        info!("Overriding concurrency of {} to {concurrency}", config.name);
+                        sampler.override_concurrency(concurrency);
+                    }
+                }
+            }
+        }
+
+        if stable != was_stable {
+            if stable {
+                info!(
+                    elapsed = ?stats.elapsed,
+                    concurrency = stats.concurrency,
+                    goal_tps = stats.goal_tps,
+                    actual_tps = stats.actual_tps,
+                    error_rate = stats.error_rate,
+                    "{} stabilized at {:.0} TPS with a concurrency of {}",
+                    config.name,
+                    stats.actual_tps,
+                    stats.concurrency
+                );
+            } else {
+                info!(
+                    elapsed = ?stats.elapsed,
+                    concurrency = stats.concurrency,
+                    goal_tps = stats.goal_tps,
+                    actual_tps = stats.actual_tps,
+                    error_rate = stats.error_rate,
+                    "{} destabilized at {:.0} TPS with a concurrency of {}",
+                    config.name,
+                    stats.actual_tps,
+                    stats.concurrency
+                );
+            }
+            if let Some(on_stability_change) = &hooks.on_stability_change {
+                on_stability_change(&stats);
+            }
+            was_stable = stable;
+        }
+
         // NOTE: We have our break-out inside this branch so that our final sampler_stats are
         // accurate.
         if let Some(duration) = config.duration {
-            if start.elapsed() > duration {
-                break samples;
+            if start.elapsed() >= duration {
+                break (stable, samples, StopReason::Duration);
+            }
+        }
+
+        if sampler.iterations_complete() {
+            break (stable, samples, StopReason::Iterations);
+        }
+
+        if sampler.budget_exhausted() {
+            warn!(
+                "Request budget of {} transactions used up; stopping {}",
+                config.request_budget.unwrap_or_default(),
+                config.name
+            );
+            break (stable, samples, StopReason::Budget);
+        }
+
+        if let Some(stop) = &hooks.stop {
+            if stop.load(Ordering::Relaxed) {
+                info!("Stopping {}", config.name);
+                break (stable, samples, StopReason::Stopped);
+            }
+        }
+
+        if sampler.paused() {
+            continue;
+        }
+
+        if sampler.no_transactions() {
+            error!(
+                "{} is running without recording any transactions; stopping the scenario. \
+                Transactions must be called with #[transaction] to be measured, or use \
+                .tps_target(TpsTarget::Scenarios) to count runs of the scenario instead.",
+                config.name
+            );
+            break (stable, samples, StopReason::NoTransactions);
+        }
+
+        if let Some(max_panics) = config.sampler.max_panics {
+            if sampler.panics() >= max_panics {
+                error!(
+                    "{} panicked {} times; stopping the scenario",
+                    config.name,
+                    sampler.panics()
+                );
+                break (stable, samples, StopReason::Panics);
+            }
+        }
+
+        if let Some(abort) = &config.abort_error_rate {
+            if samples.error_rate > abort.error_rate {
+                error_samples += 1;
+            } else {
+                error_samples = 0;
+            }
+
+            if error_samples >= abort.consecutive_samples {
+                error!(
+                    "Error rate of {:.3} exceeded {:.3} for {error_samples} samples; aborting {}",
+                    samples.error_rate, abort.error_rate, config.name
+                );
+                break (stable, samples, StopReason::ErrorRate);
             }
         }
 
-        let new_goal_tps = controllers.limit(&samples, stable);
+        if let Some(until) = &hooks.until {
+            if until(sampler.samples()) {
+                info!("Stop condition met for {}", config.name);
+                break (stable, samples, StopReason::Until);
+            }
+        }
 
-        if new_goal_tps < sampler.tps_limit() || stable {
-            sampler.set_tps_limit(new_goal_tps);
+        match (controllers.analyze(sampler.samples(), stable), override_tps) {
+            (ControllerOutcome::Limit(_), Some(tps)) => {
+                sampler.override_tps_limit(tps);
+            }
+            (ControllerOutcome::Limit(new_goal_tps), None) => {
+                if new_goal_tps < sampler.tps_limit() || stable {
+                    sampler.set_tps_limit(new_goal_tps);
+                }
+            }
+            (ControllerOutcome::Stop, _) => {
+                info!("Controller stopped {}", config.name);
+                break (stable, samples, StopReason::Controller);
+            }
         }
     };
 
-    let sampler_stats = sampler.shutdown();
+    let sampler_stats = sampler.shutdown().await;
+    let elapsed = start.elapsed();
+
+    if let Some(csv) = &mut hooks.csv {
+        csv.flush();
+    }
+
+    teardown.run().await;
 
     #[cfg(feature = "rt")]
     signal_completion().await;
 
-    info!("Scenario complete");
+    info!(elapsed = ?elapsed, "Scenario complete");
+
+    // NOTE: Statistics are computed over the windows in which the sampler was stable, unless
+    // requested otherwise (or if the sampler never stabilized). Runs with a fixed number of
+    // iterations report over the whole batch of work.
+    let measurement = if config.whole_run_statistics || config.iterations.is_some() {
+        sampler_stats.run_measurement
+    } else {
+        sampler_stats
+            .stable_measurement
+            .or(sampler_stats.run_measurement)
+    }
+    .unwrap_or(final_sample);
 
     RunStatistics {
         concurrency: sampler_stats.concurrency,
         goal_tps: sampler_stats.tps_limit.get(),
-        actual_tps: final_sample.tps,
-        latency_p50: final_sample.latency(0.5),
-        latency_p90: final_sample.latency(0.9),
-        latency_p95: final_sample.latency(0.95),
-        latency_p99: final_sample.latency(0.99),
-        error_rate: final_sample.error_rate,
+        requested_tps: config.max_tps.map(NonZeroU32::get),
+        actual_tps: measurement.tps,
+        transaction_tps: measurement.transaction_tps,
+        scenario_tps: measurement.scenario_tps,
+        latency_p50: measurement.latency(0.5),
+        latency_p90: measurement.latency(0.9),
+        latency_p95: measurement.latency(0.95),
+        latency_p99: measurement.latency(0.99),
+        latency_max: measurement.latency(1.),
+        latency_at_quantile: config
+            .latency
+            .map(|latency| measurement.latency(latency.quantile)),
+        latency_histogram: measurement.latency_histogram().clone(),
+        error_rate: measurement.error_rate,
+        onset_tps: controllers.onset_tps(),
         tps_limited: sampler_stats.tps_limited,
+        concurrency_limited: sampler_stats.concurrency_limited,
+        stable: final_stable,
+        stage: config.stage_at(elapsed),
+        stop_reason,
+        aborted: stop_reason.is_abort(),
+        elapsed,
+        panics: sampler_stats.panics,
+        scenario_errors: sampler_stats.scenario_errors,
+        errors: sampler_stats.errors,
+        status_codes: sampler_stats.status_codes,
+        concurrency_summary: sampler_stats.concurrency_summary,
     }
 }
 
-#[allow(unused)]
+/// Hand off an even slice of the goal TPS to each peer, returning the config for the slice this
+/// instance runs.
 #[cfg(feature = "rt")]
-async fn distribute_work(_config: &ScenarioConfig, _elapsed: Duration, _self_tps: f64) {
-    /*
-    let mut new_config = config.clone();
-    // TODO: This does not take into account transmission time. Logic will have
-    // to be far fancier to properly time-sync various peers on a single
-    // scenario.
-    new_config.duration = config.duration - elapsed;
+async fn shard_work(mut config: ScenarioConfig, peers: &[SocketAddr]) -> ScenarioConfig {
+    let Some(goal_tps) = config.max_tps else {
+        warn!(
+            "Not sharding {} across peers, because it has no goal TPS.",
+            config.name
+        );
+        return config;
+    };
+
+    let mut shards = shard_tps(goal_tps, peers.len() + 1).into_iter();
+    // NOTE: There is always at least one shard, since the goal TPS is non-zero.
+    config.max_tps = shards.next();
+    // NOTE: Each shard is the goal for a single server, so peers must not shard it again.
+    config.global_tps = false;
 
-    let new_tps = new_config.goal_tps().unwrap() - self_tps as u32;
-    new_config.set_goal_tps(new_tps);
+    // NOTE: Transaction caps apply to each server, so they are split up along with the goal.
+    // Caps too small to split still allow 1 TPS on every server.
+    let caps: Vec<_> = config
+        .transaction_tps
+        .iter()
+        .map(|(name, cap)| (name.clone(), shard_tps(*cap, peers.len() + 1)))
+        .collect();
+    let shard_caps = |i: usize| {
+        caps.iter()
+            .map(|(name, caps)| {
+                let cap = caps.get(i).copied().unwrap_or(NonZeroU32::MIN);
+                (name.clone(), cap)
+            })
+            .collect()
+    };
+    config.transaction_tps = shard_caps(0);
 
     let (ref tx, _) = *BALTER_OUT;
-    // TODO: Handle the error case.
-    let _ = tx.send(RuntimeMessage::Help(new_config)).await;
-    */
-    todo!()
+    for (i, (peer, tps)) in peers.iter().zip(shards).enumerate() {
+        let mut shard = config.clone();
+        shard.max_tps = Some(tps);
+        shard.transaction_tps = shard_caps(i + 1);
+        if tx
+            .send(RuntimeMessage::Shard(*peer, Box::new(shard)))
+            .await
+            .is_err()
+        {
+            error!("Unable to hand off {tps} TPS of {} to {peer}", config.name);
+        }
+    }
+
+    config
+}
+
+/// Split the goal TPS into `count` slices differing by at most one TPS. Slices which would be
+/// zero are dropped.
+#[cfg(feature = "rt")]
+fn shard_tps(goal_tps: NonZeroU32, count: usize) -> Vec<NonZeroU32> {
+    let count = count.max(1) as u32;
+    let (base, remainder) = (goal_tps.get() / count, goal_tps.get() % count);
+    (0..count)
+        .filter_map(|i| NonZeroU32::new(base + u32::from(i < remainder)))
+        .collect()
 }
 
 #[cfg(feature = "rt")]
@@ -329,7 +2864,317 @@ async fn signal_completion() {
     // TODO: We should send which scenario was actually completed so that the runtime can be
     // intelligent about figuring out if load was alleviated or not.
 
+    // NOTE: Without a runtime nothing receives the message, and once the channel filled up
+    // every later Scenario would hang here.
+    if balter_runtime::runtime::server_id().is_none() {
+        return;
+    }
+
     let (ref tx, _) = *BALTER_OUT;
     // TODO: Handle the error case.
     let _ = tx.send(RuntimeMessage::Finished).await;
 }
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn test_from_fn() {
+        use super::*;
+
+        let stats = Scenario::from_fn("test_from_fn", || async { Err::<(), _>("failed") })
+            .once()
+            .await;
+        assert_eq!(stats.scenario_errors, 1);
+    }
+
+    #[tokio::test]
+    async fn test_drop() {
+        use super::*;
+        use std::sync::atomic::AtomicU64;
+
+        let count = Arc::new(AtomicU64::new(0));
+        let scenario = Scenario::new("test_drop", {
+            let count = count.clone();
+            move || {
+                let count = count.clone();
+                async move {
+                    count.fetch_add(1, Ordering::Relaxed);
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                }
+            }
+        })
+        .tps(1_000);
+
+        // NOTE: The Scenario is dropped partway through the run once the timeout elapses
+        let res = tokio::time::timeout(Duration::from_millis(500), scenario).await;
+        assert!(res.is_err());
+        assert!(count.load(Ordering::Relaxed) > 0);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let stopped_at = count.load(Ordering::Relaxed);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(count.load(Ordering::Relaxed), stopped_at);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_handle_control() {
+        use super::*;
+
+        use crate::testing::{Latency, MockScenario};
+
+        let handle = MockScenario::new(Latency::Fixed(Duration::from_millis(1)))
+            .scenario("test_handle_control")
+            .tps(500)
+            .duration(Duration::from_secs(30))
+            .spawn();
+
+        let mut live = handle.live_stats();
+        live.changed().await.unwrap();
+        assert_eq!(live.borrow().goal_tps, 500);
+
+        handle.set_goal_tps(200);
+        tokio::time::timeout(
+            Duration::from_secs(30),
+            live.wait_for(|stats| stats.goal_tps == 200),
+        )
+        .await
+        .expect("goal TPS was not overridden")
+        .unwrap();
+
+        handle.stop();
+        let stats = handle.stats().await;
+        assert_eq!(stats.stop_reason, StopReason::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_runtime() {
+        use super::*;
+        use crate::testing::{Latency, MockScenario};
+        use std::collections::HashSet;
+        use std::sync::Mutex;
+
+        let mock = MockScenario::new(Latency::Fixed(Duration::from_millis(1)));
+        let threads = Arc::new(Mutex::new(HashSet::new()));
+        let stats = Scenario::from_fn("test_runtime", {
+            let threads = threads.clone();
+            move || {
+                let name = std::thread::current().name().map(str::to_string);
+                threads.lock().unwrap().insert(name);
+                let mock = mock.clone();
+                async move { mock.transaction().await }
+            }
+        })
+        .tps(500)
+        .iterations(200)
+        .runtime(RuntimeConfig::new(2))
+        .await;
+        assert!(stats.actual_tps > 0.);
+
+        let threads = threads.lock().unwrap();
+        assert_eq!(
+            *threads,
+            HashSet::from([Some("balter-test_runtime-worker".to_string())])
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pause() {
+        use super::*;
+
+        use crate::testing::{Latency, MockScenario};
+
+        let handle = MockScenario::new(Latency::Fixed(Duration::from_millis(1)))
+            .scenario("test_pause")
+            .tps(500)
+            .sample_interval(Duration::from_millis(200))
+            .duration(Duration::from_secs(60))
+            .spawn();
+
+        let mut live = handle.live_stats();
+        let stats = *live.wait_for(|stats| stats.stable).await.unwrap();
+        assert!(stats.actual_tps > 450., "{}", stats.actual_tps);
+
+        handle.pause();
+        assert!(handle.is_paused());
+        let paused = *live.wait_for(|stats| stats.actual_tps < 1.).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert!(live.borrow().actual_tps < 1.);
+
+        // NOTE: The tasks and goal are kept while paused, so there's no ramp up once resumed
+        handle.resume();
+        let resumed = tokio::time::timeout(
+            Duration::from_secs(3),
+            live.wait_for(|stats| stats.actual_tps > 450.),
+        )
+        .await
+        .expect("TPS did not recover after resuming")
+        .map(|stats| *stats)
+        .unwrap();
+        assert_eq!(resumed.concurrency, paused.concurrency);
+        assert_eq!(resumed.goal_tps, 500);
+
+        handle.stop();
+        handle.stats().await;
+    }
+
+    #[tokio::test]
+    async fn test_no_transactions() {
+        use super::*;
+
+        let stats = Scenario::from_fn("test_no_transactions", || async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        })
+        .tps(1_000)
+        .duration(Duration::from_secs(60))
+        .await;
+
+        assert_eq!(stats.stop_reason, StopReason::NoTransactions);
+        assert!(stats.aborted);
+        assert!(
+            stats.elapsed < Duration::from_secs(10),
+            "{:?}",
+            stats.elapsed
+        );
+        assert!(stats.concurrency_summary.max <= balter_core::BASE_CONCURRENCY);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_tps() {
+        use super::*;
+        use crate::testing::{Latency, MockScenario};
+        use crate::transaction::transaction_hook;
+        use balter_core::{ErrorKind, TransactionLabels};
+        use std::sync::atomic::AtomicU64;
+
+        const LABELS: TransactionLabels = TransactionLabels {
+            name: "write",
+            success: "balter_transaction_write_success",
+            error: "balter_transaction_write_error",
+            latency: "balter_transaction_write_latency",
+            retries: "balter_transaction_write_retries",
+        };
+
+        let mock = MockScenario::new(Latency::Fixed(Duration::from_millis(1)));
+        let writes = Arc::new(AtomicU64::new(0));
+        let stats = Scenario::from_fn("test_transaction_tps", {
+            let writes = writes.clone();
+            move || {
+                let (mock, writes) = (mock.clone(), writes.clone());
+                async move {
+                    let _ = mock.transaction().await;
+                    let _: Result<(), std::io::Error> = transaction_hook(
+                        LABELS,
+                        async {
+                            writes.fetch_add(1, Ordering::Relaxed);
+                            Ok(())
+                        },
+                        |_| ErrorKind::Other,
+                        || None,
+                    )
+                    .await;
+                }
+            }
+        })
+        .tps(200)
+        .transaction_tps("write", 20)
+        .duration(Duration::from_secs(5))
+        .await;
+
+        // NOTE: Each run makes one capped write, so the writes also hold back the reads
+        let writes = writes.load(Ordering::Relaxed) as f64 / stats.elapsed.as_secs_f64();
+        assert!((writes - 20.).abs() < 3., "{writes}");
+        assert!(stats.actual_tps < 50., "{}", stats.actual_tps);
+    }
+
+    #[tokio::test]
+    async fn test_tps_target() {
+        use super::*;
+
+        use crate::testing::{Latency, MockScenario};
+
+        let mock = MockScenario::new(Latency::Fixed(Duration::from_millis(1)));
+        let stats = Scenario::from_fn("test_tps_target", move || {
+            let mock = mock.clone();
+            async move {
+                for _ in 0..3 {
+                    let _ = mock.transaction().await;
+                }
+            }
+        })
+        .tps(100)
+        .tps_target(TpsTarget::Scenarios)
+        .duration(Duration::from_secs(5))
+        .await;
+
+        assert!(
+            (stats.scenario_tps - 100.).abs() < 10.,
+            "{}",
+            stats.scenario_tps
+        );
+        assert_eq!(stats.actual_tps, stats.scenario_tps);
+        assert!(
+            (stats.transaction_tps - 300.).abs() < 30.,
+            "{}",
+            stats.transaction_tps
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_merge_quantiles() {
+        use super::*;
+
+        use crate::testing::{Latency, MockScenario};
+
+        let mock = MockScenario::new(Latency::SkewNormal {
+            location: Duration::from_millis(5),
+            scale: Duration::from_millis(2),
+            shape: 5.,
+        });
+        let stats = Scenario::from_fn("test_merge_quantiles", move || {
+            let mock = mock.clone();
+            async move {
+                let _ = mock.transaction().await;
+            }
+        })
+        .tps(200)
+        .duration(Duration::from_secs(3))
+        .await;
+        assert!(!stats.latency_histogram.is_empty());
+
+        // NOTE: Merging only combines histograms, so the quantiles of a single run must already
+        // be those of its histogram for the merge to agree with it.
+        let mut merged = stats.clone();
+        merged.merge(&RunStatistics::default());
+        for (merged, run) in [
+            (merged.latency_p50, stats.latency_p50),
+            (merged.latency_p90, stats.latency_p90),
+            (merged.latency_p95, stats.latency_p95),
+            (merged.latency_p99, stats.latency_p99),
+            (merged.latency_max, stats.latency_max),
+        ] {
+            assert_eq!(merged, run);
+        }
+    }
+
+    #[cfg(feature = "rt")]
+    #[test]
+    fn test_shard_tps() {
+        use super::*;
+
+        let tps = |goal, count| -> Vec<u32> {
+            shard_tps(NonZeroU32::new(goal).unwrap(), count)
+                .into_iter()
+                .map(NonZeroU32::get)
+                .collect()
+        };
+
+        assert_eq!(tps(1_000, 1), vec![1_000]);
+        assert_eq!(tps(1_000, 4), vec![250; 4]);
+        assert_eq!(tps(1_000, 3), vec![334, 333, 333]);
+        assert_eq!(tps(2, 4), vec![1, 1]);
+
+        let shards = tps(10_007, 7);
+        assert_eq!(shards.iter().sum::<u32>(), 10_007);
+        assert!(shards.iter().max().unwrap() - shards.iter().min().unwrap() <= 1);
+    }
+}
@@ -0,0 +1,259 @@
+//! Shared state within a Scenario.
+use serde::de::DeserializeOwned;
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+tokio::task_local! {
+    /// State provided with [with_state](crate::scenario::ConfigurableScenario::with_state),
+    /// shared by every task spawned by the sampler.
+    pub(crate) static TASK_STATE: StateMap;
+}
+
+/// Builds the state of a single task, given its worker ID
+type WorkerFn = Arc<dyn Fn(usize, &mut StateMap) + Send + Sync>;
+
+/// Shared state of a Scenario, keyed by type. Cloning is cheap, so that each task can hold its
+/// own handle.
+#[derive(Clone, Default)]
+pub(crate) struct StateMap {
+    states: Arc<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+    workers: Arc<Vec<WorkerFn>>,
+    args: Option<Arc<Args>>,
+}
+
+/// Arguments provided with [args](crate::scenario::ConfigurableScenario::args), which are
+/// deserialized on first use and shared by every task from then on.
+struct Args {
+    bytes: Vec<u8>,
+    value: OnceLock<Arc<dyn Any + Send + Sync>>,
+}
+
+impl StateMap {
+    pub fn insert<S: Send + Sync + 'static>(&mut self, state: Arc<S>) {
+        Arc::make_mut(&mut self.states).insert(TypeId::of::<S>(), state);
+    }
+
+    /// Provide the serialized arguments of the Scenario, fetched with [args](crate::args()).
+    pub fn set_args(&mut self, bytes: Vec<u8>) {
+        self.args = Some(Arc::new(Args {
+            bytes,
+            value: OnceLock::new(),
+        }));
+    }
+
+    /// Build state of type `W` separately for each task, with [for_worker](Self::for_worker).
+    pub fn insert_worker<W: Send + Sync + 'static>(
+        &mut self,
+        factory: impl Fn(usize) -> W + Send + Sync + 'static,
+    ) {
+        Arc::make_mut(&mut self.workers).push(Arc::new(move |id, state: &mut StateMap| {
+            state.insert(Arc::new(factory(id)))
+        }));
+    }
+
+    /// State of the task with the given worker ID, where per-worker state takes precedence over
+    /// shared state of the same type.
+    pub fn for_worker(&self, id: usize) -> StateMap {
+        let mut state = StateMap {
+            states: self.states.clone(),
+            workers: Arc::default(),
+            args: self.args.clone(),
+        };
+        for worker in self.workers.iter() {
+            worker(id, &mut state);
+        }
+        state
+    }
+
+    fn get<S: Send + Sync + 'static>(&self) -> Option<Arc<S>> {
+        let state = self.states.get(&TypeId::of::<S>())?.clone();
+        state.downcast().ok()
+    }
+
+    fn get_args<A: DeserializeOwned + Clone + Send + Sync + 'static>(&self) -> Option<A> {
+        let args = self.args.as_ref()?;
+        let value = args.value.get_or_init(|| {
+            let value: A = bincode::deserialize(&args.bytes).unwrap_or_else(|err| {
+                panic!(
+                    "Unable to deserialize the Scenario args as {}: {err}",
+                    type_name::<A>()
+                )
+            });
+            Arc::new(value)
+        });
+        value.downcast_ref().cloned()
+    }
+}
+
+/// Get the state of type `S` shared by the current Scenario, which is provided with
+/// [with_state](crate::scenario::ConfigurableScenario::with_state), or the current task's own
+/// state provided with [per_worker](crate::scenario::ConfigurableScenario::per_worker).
+///
+/// NOTE: The state is only available on the tasks Balter runs the Scenario on, and not on any
+/// tasks the Scenario spawns itself. Fetch the state beforehand and move it into those tasks
+/// instead.
+///
+/// # Example
+/// ```no_run
+/// use balter::prelude::*;
+///
+/// struct Client {
+///     // e.g. a connection pool
+/// }
+///
+/// #[scenario]
+/// async fn my_scenario() {
+///     let client = balter::state::<Client>();
+///     let _ = my_transaction(&client).await;
+/// }
+///
+/// #[transaction]
+/// async fn my_transaction(client: &Client) -> Result<(), String> {
+///     Ok(())
+/// }
+/// ```
+///
+/// # Panics
+///
+/// This function will panic if no state of type `S` was provided to the Scenario, or if it is
+/// called outside of a running Scenario.
+pub fn state<S: Send + Sync + 'static>() -> Arc<S> {
+    TASK_STATE
+        .try_with(StateMap::get)
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| {
+            panic!(
+                "No state of type {} was provided to the Scenario",
+                type_name::<S>()
+            )
+        })
+}
+
+/// Get the arguments of the current Scenario, which are provided with
+/// [args](crate::scenario::ConfigurableScenario::args).
+///
+/// The arguments are deserialized the first time they are fetched, and cloned on each call
+/// after that.
+///
+/// NOTE: As with [state], the arguments are only available on the tasks Balter runs the
+/// Scenario on.
+///
+/// # Example
+/// ```no_run
+/// use balter::prelude::*;
+///
+/// #[scenario]
+/// async fn my_scenario() {
+///     let payload_size = balter::args::<usize>();
+///     let _ = upload(vec![0; payload_size]).await;
+/// }
+///
+/// #[transaction]
+/// async fn upload(payload: Vec<u8>) -> Result<(), String> {
+///     Ok(())
+/// }
+/// ```
+///
+/// # Panics
+///
+/// This function will panic if no arguments were provided to the Scenario, if they were
+/// provided as a different type than `A`, or if it is called outside of a running Scenario.
+pub fn args<A: DeserializeOwned + Clone + Send + Sync + 'static>() -> A {
+    TASK_STATE
+        .try_with(StateMap::get_args)
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| {
+            panic!(
+                "No args of type {} were provided to the Scenario",
+                type_name::<A>()
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_state() {
+        let mut states = StateMap::default();
+        states.insert(Arc::new(42u64));
+        states.insert(Arc::new("shared".to_string()));
+
+        let (num, name) = TASK_STATE
+            .scope(states.clone(), async {
+                (state::<u64>(), state::<String>())
+            })
+            .await;
+        assert_eq!(*num, 42);
+        assert_eq!(*name, "shared");
+
+        // Each handle shares the same state
+        let num_2 = TASK_STATE.scope(states, async { state::<u64>() }).await;
+        assert!(Arc::ptr_eq(&num, &num_2));
+    }
+
+    #[tokio::test]
+    async fn test_worker_state() {
+        let mut states = StateMap::default();
+        states.insert(Arc::new(42u64));
+        states.insert(Arc::new(0usize));
+        states.insert_worker(|id| id);
+
+        let (num, id) = TASK_STATE
+            .scope(states.for_worker(3), async {
+                (state::<u64>(), state::<usize>())
+            })
+            .await;
+        assert_eq!(*num, 42);
+        assert_eq!(*id, 3);
+
+        // Shared state is still shared, but each worker has its own state
+        let (num_2, id_2) = TASK_STATE
+            .scope(states.for_worker(4), async {
+                (state::<u64>(), state::<usize>())
+            })
+            .await;
+        assert!(Arc::ptr_eq(&num, &num_2));
+        assert_eq!(*id_2, 4);
+    }
+
+    #[tokio::test]
+    async fn test_args() {
+        let mut states = StateMap::default();
+        states.set_args(bincode::serialize(&(1_024usize, "payload".to_string())).unwrap());
+
+        let (size, name) = TASK_STATE
+            .scope(states.for_worker(0), async { args::<(usize, String)>() })
+            .await;
+        assert_eq!(size, 1_024);
+        assert_eq!(name, "payload");
+
+        // The deserialized args are shared between workers
+        let (size_2, _) = TASK_STATE
+            .scope(states.for_worker(1), async { args::<(usize, String)>() })
+            .await;
+        assert_eq!(size_2, 1_024);
+        let args = states.args.as_ref().unwrap();
+        assert!(args.value.get().is_some());
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "No args of type u32")]
+    async fn test_missing_args() {
+        TASK_STATE
+            .scope(StateMap::default(), async { args::<u32>() })
+            .await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "No state of type u32")]
+    async fn test_missing_state() {
+        let mut states = StateMap::default();
+        states.insert(Arc::new(42u64));
+        TASK_STATE.scope(states, async { state::<u32>() }).await;
+    }
+}
@@ -0,0 +1,119 @@
+//! Running several Scenarios together
+use crate::scenario::{ConfigurableScenario, ScenarioHandle};
+use balter_core::RunStatistics;
+use std::collections::BTreeMap;
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+use std::time::Duration;
+
+type SpawnFn = Box<dyn FnOnce(Option<Duration>) -> ScenarioHandle + Send>;
+
+/// Set of Scenarios which are run at the same time, to model mixed traffic across endpoints.
+///
+/// Each Scenario keeps its own goals, controller and metric labels, and the Suite runs them
+/// concurrently until all of them complete. Awaiting the Suite returns the [RunStatistics] of
+/// each Scenario, keyed by its name.
+///
+/// # Example
+/// ```no_run
+/// use balter::prelude::*;
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let stats = Suite::new()
+///         .scenario(checkout().tps(100))
+///         .scenario(browse().tps(500))
+///         .duration(Duration::from_secs(300))
+///         .await;
+///
+///     assert!(stats["checkout"].actual_tps > 95.);
+///     assert!(stats["browse"].actual_tps > 475.);
+/// }
+///
+/// #[scenario]
+/// async fn checkout() {
+/// }
+///
+/// #[scenario]
+/// async fn browse() {
+/// }
+/// ```
+#[derive(Default)]
+pub struct Suite {
+    scenarios: Vec<(String, SpawnFn)>,
+    duration: Option<Duration>,
+}
+
+impl Suite {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a Scenario to the Suite.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the Suite already has a Scenario with the same name.
+    pub fn scenario<S>(mut self, scenario: S) -> Self
+    where
+        S: ConfigurableScenario<RunStatistics> + Send + 'static,
+    {
+        let name = scenario.name().to_string();
+        if self.scenarios.iter().any(|(existing, _)| *existing == name) {
+            panic!("Suite already has a scenario named {name}");
+        }
+
+        self.scenarios.push((
+            name,
+            Box::new(move |duration| match duration {
+                Some(duration) => scenario.duration(duration).spawn(),
+                None => scenario.spawn(),
+            }),
+        ));
+        self
+    }
+
+    /// Run every Scenario in the Suite for the given duration, overriding any duration set on
+    /// the Scenarios themselves.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+}
+
+impl IntoFuture for Suite {
+    type Output = BTreeMap<String, RunStatistics>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        let duration = self.duration;
+        let handles: Vec<_> = self
+            .scenarios
+            .into_iter()
+            .map(|(name, spawn)| (name, spawn(duration)))
+            .collect();
+
+        Box::pin(async move {
+            let mut stats = BTreeMap::new();
+            for (name, handle) in handles {
+                stats.insert(name, handle.stats().await);
+            }
+            stats
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+
+    #[test]
+    #[should_panic(expected = "Suite already has a scenario named checkout")]
+    fn test_duplicate_scenario() {
+        let _ = Suite::new()
+            .scenario(Scenario::new("checkout", || async {}).tps(100))
+            .scenario(Scenario::new("checkout", || async {}).tps(500));
+    }
+}
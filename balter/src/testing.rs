@@ -0,0 +1,188 @@
+//! Mock Scenarios for testing code built on Balter, such as custom [Controller](crate::Controller)s,
+//! without a service to load test.
+//!
+//! # Example
+//! ```no_run
+//! use balter::prelude::*;
+//! use balter::testing::{Latency, MockScenario};
+//! use std::time::Duration;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let mock = MockScenario::new(Latency::Normal {
+//!         mean: Duration::from_millis(10),
+//!         std: Duration::from_millis(2),
+//!     })
+//!     .error_rate(0.01);
+//!
+//!     let stats = mock
+//!         .scenario("mock")
+//!         .tps(1_000)
+//!         .duration(Duration::from_secs(10))
+//!         .await;
+//!     assert!(stats.error_rate < 0.05);
+//! }
+//! ```
+use crate::scenario::{Scenario, ScenarioFn};
+use crate::transaction::transaction_hook;
+use balter_core::{ErrorKind, TransactionLabels};
+use rand::Rng;
+use rand_distr::{Distribution, Normal, SkewNormal};
+use std::time::Duration;
+
+const LABELS: TransactionLabels = TransactionLabels {
+    name: "mock",
+    success: "balter_transaction_mock_success",
+    error: "balter_transaction_mock_error",
+    latency: "balter_transaction_mock_latency",
+    retries: "balter_transaction_mock_retries",
+};
+
+/// Distribution of the latency of mock transactions. Sampled latencies are clamped at zero.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Latency {
+    /// Every transaction takes exactly this long
+    Fixed(Duration),
+    /// Normally distributed latency
+    Normal { mean: Duration, std: Duration },
+    /// Skew-normal distributed latency. A positive `shape` gives a long tail of slow
+    /// transactions, as is typical of real services.
+    SkewNormal {
+        location: Duration,
+        scale: Duration,
+        shape: f64,
+    },
+}
+
+impl Latency {
+    /// Sample the latency of a single transaction
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the `shape` of [Latency::SkewNormal] is not finite
+    pub fn sample(&self, rng: &mut impl Rng) -> Duration {
+        let secs = match *self {
+            Latency::Fixed(latency) => return latency,
+            Latency::Normal { mean, std } => Normal::new(mean.as_secs_f64(), std.as_secs_f64())
+                .expect("Invalid normal distribution")
+                .sample(rng),
+            Latency::SkewNormal {
+                location,
+                scale,
+                shape,
+            } => SkewNormal::new(location.as_secs_f64(), scale.as_secs_f64(), shape)
+                .expect("Invalid skew-normal distribution")
+                .sample(rng),
+        };
+        Duration::from_secs_f64(secs.max(0.))
+    }
+}
+
+/// Error returned by failed mock transactions. Counted as [ErrorKind::Other].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Mock transaction failed")]
+pub struct MockError;
+
+/// Scenario which sleeps for a random latency in place of calling a service, failing at a given
+/// error rate. Each call is counted as a transaction named `mock`.
+#[derive(Clone, Debug)]
+pub struct MockScenario {
+    latency: Latency,
+    error_rate: f64,
+}
+
+impl MockScenario {
+    pub fn new(latency: Latency) -> Self {
+        Self {
+            latency,
+            error_rate: 0.,
+        }
+    }
+
+    /// Fail transactions at random with the given probability.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the error rate is not between 0 and 1
+    pub fn error_rate(mut self, error_rate: f64) -> Self {
+        if !(0. ..=1.).contains(&error_rate) {
+            panic!("Error rate must be between 0 and 1. Given: {error_rate}");
+        }
+        self.error_rate = error_rate;
+        self
+    }
+
+    /// Run a single mock transaction
+    pub async fn transaction(&self) -> Result<(), MockError> {
+        transaction_hook(
+            LABELS,
+            async {
+                let (latency, failed) = {
+                    let mut rng = crate::rng();
+                    (self.latency.sample(&mut rng), rng.gen_bool(self.error_rate))
+                };
+                tokio::time::sleep(latency).await;
+                if failed {
+                    Err(MockError)
+                } else {
+                    Ok(())
+                }
+            },
+            |_| ErrorKind::Other,
+            || Some(Ok(())),
+        )
+        .await
+    }
+
+    /// Build a Scenario which runs one mock transaction per call. Failed transactions are only
+    /// counted as transaction errors, not [scenario errors](balter_core::RunStatistics::scenario_errors).
+    pub fn scenario(&self, name: &str) -> Scenario<ScenarioFn> {
+        let mock = self.clone();
+        Scenario::from_fn(name, move || {
+            let mock = mock.clone();
+            async move {
+                let _ = mock.transaction().await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_latency() {
+        let mut rng = crate::rng();
+        let fixed = Latency::Fixed(Duration::from_millis(5));
+        assert_eq!(fixed.sample(&mut rng), Duration::from_millis(5));
+
+        // NOTE: Half of the samples fall below zero, and are clamped
+        let normal = Latency::Normal {
+            mean: Duration::ZERO,
+            std: Duration::from_millis(1),
+        };
+        let zeros = (0..1_000)
+            .filter(|_| normal.sample(&mut rng).is_zero())
+            .count();
+        assert!((400..600).contains(&zeros), "{zeros}");
+    }
+
+    #[tokio::test]
+    async fn test_mock_scenario() {
+        let stats = MockScenario::new(Latency::Fixed(Duration::from_millis(1)))
+            .error_rate(0.5)
+            .scenario("test_mock_scenario")
+            .tps(1_000)
+            .iterations(1_000)
+            .await;
+
+        assert!(
+            (0.3..0.7).contains(&stats.error_rate),
+            "{}",
+            stats.error_rate
+        );
+        assert_eq!(stats.scenario_errors, 0);
+    }
+}
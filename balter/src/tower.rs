@@ -0,0 +1,134 @@
+//! Load testing [tower Services](tower_service::Service) (requires `tower` feature)
+use crate::scenario::Scenario;
+use crate::transaction::transaction_hook;
+use balter_core::{ErrorKind, TransactionLabels};
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use tower_service::Service;
+
+const LABELS: TransactionLabels = TransactionLabels {
+    name: "tower_service",
+    success: "balter_transaction_tower_service_success",
+    error: "balter_transaction_tower_service_error",
+    latency: "balter_transaction_tower_service_latency",
+    retries: "balter_transaction_tower_service_retries",
+};
+
+/// Future of a single run of a [service_scenario]
+pub type ServiceFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Create a Scenario which calls the Service with a fresh request from `req_factory` on each run.
+///
+/// Each run is a single Transaction, which waits for the Service to be ready and then calls it.
+/// Errors from either are counted as [ErrorKind::Other], with metrics labeled
+/// `transaction="tower_service"`. Responses are dropped.
+///
+/// NOTE: The Service is cloned for each call (as is usual for tower Services), so any state
+/// which should be shared between calls, such as a connection, must be shared by its clones.
+///
+/// # Example
+/// ```no_run
+/// use balter::prelude::*;
+/// use std::convert::Infallible;
+/// use std::future::{ready, Ready};
+/// use std::task::{Context, Poll};
+///
+/// #[derive(Clone)]
+/// struct Echo;
+///
+/// impl tower_service::Service<String> for Echo {
+///     type Response = String;
+///     type Error = Infallible;
+///     type Future = Ready<Result<String, Infallible>>;
+///
+///     fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+///         Poll::Ready(Ok(()))
+///     }
+///
+///     fn call(&mut self, req: String) -> Self::Future {
+///         ready(Ok(req))
+///     }
+/// }
+///
+/// # async fn run() {
+/// balter::tower::service_scenario("echo", Echo, || "hello".to_string())
+///     .tps(1_000)
+///     .await;
+/// # }
+/// ```
+pub fn service_scenario<S, Req, R>(
+    name: &str,
+    svc: S,
+    req_factory: R,
+) -> Scenario<impl Fn() -> ServiceFuture + Send + Sync + Clone + 'static>
+where
+    S: Service<Req> + Clone + Send + Sync + 'static,
+    S::Future: Send,
+    S::Error: Send,
+    Req: Send + 'static,
+    R: Fn() -> Req + Send + Sync + Clone + 'static,
+{
+    Scenario::new(name, move || -> ServiceFuture {
+        let mut svc = svc.clone();
+        let req = req_factory();
+        Box::pin(async move {
+            let _ = transaction_hook(
+                LABELS,
+                async move {
+                    poll_fn(|cx| svc.poll_ready(cx)).await?;
+                    svc.call(req).await.map(drop)
+                },
+                |_| ErrorKind::Other,
+                || Some(Ok(())),
+            )
+            .await;
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::ConfigurableScenario;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    /// Service which fails every other call
+    #[derive(Clone, Default)]
+    struct Flaky(Arc<AtomicU64>);
+
+    impl Service<u64> for Flaky {
+        type Response = ();
+        type Error = String;
+        type Future = std::future::Ready<Result<(), String>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), String>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: u64) -> Self::Future {
+            let calls = self.0.fetch_add(req, Ordering::Relaxed);
+            std::future::ready(if calls.is_multiple_of(2) {
+                Ok(())
+            } else {
+                Err("Err".to_string())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_service_scenario() {
+        let svc = Flaky::default();
+        let stats = service_scenario("flaky", svc.clone(), || 1)
+            .tps(100)
+            .fixed_concurrency(1)
+            .iterations(20)
+            .duration(Duration::from_secs(10))
+            .await;
+
+        assert_eq!(svc.0.load(Ordering::Relaxed), 20);
+        assert_eq!(stats.errors.other, 10);
+    }
+}
@@ -1,66 +1,457 @@
+use crate::metric_keys::{scenario_metric, transaction_metric};
+use crate::sampler::Pacer;
 use arc_swap::ArcSwap;
-use balter_core::TransactionLabels;
-use governor::DefaultDirectRateLimiter;
+use balter_core::{ErrorKind, RetryPolicy, TpsTarget, TransactionLabels};
+use metrics::Label;
 use metrics_util::AtomicBucket;
+use std::collections::HashMap;
 use std::time::{Duration, Instant};
 use std::{
+    fmt::Display,
     future::Future,
+    marker::PhantomData,
     sync::{
         atomic::{AtomicU64, Ordering},
         Arc,
     },
 };
 
+/// Number of possible HTTP status codes, which are always three digits
+pub(crate) const STATUS_CODES: usize = 1_000;
+
 /// Transaction hook used by the `#[transaction]` macro. Not intended to be used manually.
-pub async fn transaction_hook<T, R, E>(labels: TransactionLabels, func: T) -> T::Output
+///
+/// `classify` determines the [ErrorKind] of a failed transaction, and `dry_run` provides the
+/// output of the transaction in a dry run, if it has one.
+pub async fn transaction_hook<T, R, E, C, D>(
+    labels: TransactionLabels,
+    func: T,
+    classify: C,
+    dry_run: D,
+) -> T::Output
 where
     T: Future<Output = Result<R, E>>,
+    C: FnOnce(&E) -> ErrorKind,
+    D: FnOnce() -> Option<T::Output>,
 {
     // TODO: Remove clone
     if let Ok(hook) = TRANSACTION_HOOK.try_with(|v| v.clone()) {
-        {
-            let limiter = hook.limiter.load();
-            limiter.until_ready().await;
-        }
+        let (res, elapsed) = hook.attempt(labels, func, dry_run).await;
+        hook.record(labels, res.as_ref().err().map(classify), elapsed);
+        res
+    } else {
+        tracing::error!("No hook available.");
+        func.await
+    }
+}
 
-        let start = Instant::now();
-        let res = func.await;
-        let elapsed = start.elapsed();
+/// Count a response with the given HTTP status code towards the current Scenario, for
+/// [RunStatistics::status_codes](balter_core::RunStatistics::status_codes).
+#[cfg(feature = "http")]
+pub(crate) fn record_status(labels: TransactionLabels, legacy_name: &'static str, status: u16) {
+    let _ = TRANSACTION_HOOK.try_with(|hook| hook.record_status(labels, legacy_name, status));
+}
 
-        // TODO: Unfortunately we're duplicating all data collection here, which isn't ideal.
-        // It makes more sense to move the metric logging out of the individual
-        // transaction_hooks, and to log it in the sampler.
-        hook.latency.push(elapsed);
-        if cfg!(feature = "metrics") {
-            metrics::histogram!(labels.latency).record(elapsed.as_secs_f64());
+/// Transaction hook used by the `#[transaction]` macro for functions which don't return a
+/// `Result`. Not intended to be used manually.
+///
+/// Infallible transactions are always counted as a success.
+pub async fn transaction_infallible_hook<T, D>(
+    labels: TransactionLabels,
+    func: T,
+    dry_run: D,
+) -> T::Output
+where
+    T: Future,
+    D: FnOnce() -> Option<T::Output>,
+{
+    // TODO: Remove clone
+    if let Ok(hook) = TRANSACTION_HOOK.try_with(|v| v.clone()) {
+        let (res, elapsed) = hook.attempt(labels, func, dry_run).await;
+        hook.record(labels, None, elapsed);
+        res
+    } else {
+        tracing::error!("No hook available.");
+        func.await
+    }
+}
+
+/// Transaction hook used by the `#[transaction(retry)]` macro. Not intended to be used manually.
+///
+/// Runs a single attempt of the transaction, where `attempt` starts at `1`. Returns `None` if the
+/// attempt failed and should be retried according to the Scenario's [RetryPolicy].
+pub async fn transaction_retry_hook<T, R, E, C, D>(
+    labels: TransactionLabels,
+    attempt: u32,
+    func: T,
+    classify: C,
+    dry_run: D,
+) -> Option<T::Output>
+where
+    T: Future<Output = Result<R, E>>,
+    C: FnOnce(&E) -> ErrorKind,
+    D: FnOnce() -> Option<T::Output>,
+{
+    // TODO: Remove clone
+    if let Ok(hook) = TRANSACTION_HOOK.try_with(|v| v.clone()) {
+        let policy = hook.retry;
+        if let Some(policy) = policy {
+            let backoff = policy.backoff(attempt);
+            if !backoff.is_zero() {
+                tokio::time::sleep(backoff).await;
+            }
         }
 
-        if res.is_ok() {
-            hook.success.fetch_add(1, Ordering::Relaxed);
+        // NOTE: Each attempt re-acquires a permit from the rate limiter, so retries count towards
+        // the TPS limit.
+        let (res, elapsed) = hook.attempt(labels, func, dry_run).await;
 
+        let retry = res.is_err() && policy.is_some_and(|policy| attempt < policy.max_attempts);
+        if retry {
             if cfg!(feature = "metrics") {
-                metrics::counter!(labels.success).increment(1);
+                let (name, metric_labels) = transaction_metric(
+                    "balter_transaction_retries",
+                    labels.retries,
+                    &labels,
+                    &hook.scenario,
+                );
+                metrics::counter!(name, metric_labels).increment(1);
             }
+            None
         } else {
-            hook.error.fetch_add(1, Ordering::Relaxed);
-            if cfg!(feature = "metrics") {
-                metrics::counter!(labels.error).increment(1);
-            }
+            hook.record(labels, res.as_ref().err().map(classify), elapsed);
+            Some(res)
         }
-
-        res
     } else {
         tracing::error!("No hook available.");
-        func.await
+        Some(func.await)
+    }
+}
+
+/// Errors which can report their [ErrorKind].
+///
+/// Transactions whose error type implements this trait have their errors counted by kind in
+/// [RunStatistics::errors](balter_core::RunStatistics::errors), and labeled with
+/// `kind="{kind}"` in metrics. Errors from any other type are counted as [ErrorKind::Other].
+///
+/// # Example
+/// ```no_run
+/// use balter::prelude::*;
+///
+/// enum MyError {
+///     Timeout,
+///     Status(u16),
+/// }
+///
+/// impl TransactionError for MyError {
+///     fn kind(&self) -> ErrorKind {
+///         match self {
+///             MyError::Timeout => ErrorKind::Timeout,
+///             MyError::Status(500..) => ErrorKind::Server,
+///             MyError::Status(_) => ErrorKind::Client,
+///         }
+///     }
+/// }
+///
+/// #[transaction]
+/// async fn my_transaction() -> Result<(), MyError> {
+///     Err(MyError::Timeout)
+/// }
+/// ```
+pub trait TransactionError {
+    fn kind(&self) -> ErrorKind;
+}
+
+impl TransactionError for std::io::Error {
+    fn kind(&self) -> ErrorKind {
+        use std::io::ErrorKind as IoKind;
+        match std::io::Error::kind(self) {
+            IoKind::TimedOut => ErrorKind::Timeout,
+            IoKind::ConnectionRefused
+            | IoKind::ConnectionReset
+            | IoKind::ConnectionAborted
+            | IoKind::NotConnected
+            | IoKind::BrokenPipe => ErrorKind::Connection,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+impl TransactionError for tokio::time::error::Elapsed {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Timeout
+    }
+}
+
+// NOTE: The `#[transaction]` macro classifies errors using autoref specialization, since error
+// types are not required to implement [TransactionError]: `(&&Classify(err)).error_kind()`
+// resolves to [ClassifySpecific] if the error implements the trait, and [ClassifyFallback]
+// otherwise.
+#[doc(hidden)]
+pub struct Classify<'a, E>(pub &'a E);
+
+/// Fixes the output type of the transaction body, so that its error type is known when the
+/// error is classified.
+#[doc(hidden)]
+pub fn with_output<O, F: Future<Output = O>>(func: F) -> F {
+    func
+}
+
+#[doc(hidden)]
+pub trait ClassifySpecific {
+    fn error_kind(&self) -> ErrorKind;
+}
+
+impl<E: TransactionError> ClassifySpecific for &Classify<'_, E> {
+    fn error_kind(&self) -> ErrorKind {
+        self.0.kind()
+    }
+}
+
+#[doc(hidden)]
+pub trait ClassifyFallback {
+    fn error_kind(&self) -> ErrorKind;
+}
+
+impl<E> ClassifyFallback for Classify<'_, E> {
+    fn error_kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+// NOTE: In a dry run the transaction body is skipped, so the `#[transaction]` macro provides a
+// stand-in output using the same autoref specialization as [Classify]:
+// `(&&DryRun::<O>::new()).dry_run_output()` is a successful default output if there is one, and
+// `None` otherwise.
+#[doc(hidden)]
+pub struct DryRun<O>(PhantomData<O>);
+
+impl<O> DryRun<O> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait DryRunResult<O> {
+    fn dry_run_output(&self) -> Option<O>;
+}
+
+impl<R: Default, E> DryRunResult<Result<R, E>> for &DryRun<Result<R, E>> {
+    fn dry_run_output(&self) -> Option<Result<R, E>> {
+        Some(Ok(R::default()))
+    }
+}
+
+#[doc(hidden)]
+pub trait DryRunDefault<O> {
+    fn dry_run_output(&self) -> Option<O>;
+}
+
+impl<O: Default> DryRunDefault<O> for &DryRun<O> {
+    fn dry_run_output(&self) -> Option<O> {
+        Some(O::default())
+    }
+}
+
+#[doc(hidden)]
+pub trait DryRunFallback<O> {
+    fn dry_run_output(&self) -> Option<O>;
+}
+
+impl<O> DryRunFallback<O> for DryRun<O> {
+    fn dry_run_output(&self) -> Option<O> {
+        None
+    }
+}
+
+/// Scenario hook used by the `#[scenario]` macro. Not intended to be used manually.
+///
+/// Records a scenario which returned an error, separately from transaction errors.
+pub fn scenario_hook<R: ScenarioResult>(res: R) {
+    let Err(err) = res.into_result() else {
+        return;
+    };
+
+    if let Ok(hook) = TRANSACTION_HOOK.try_with(|v| v.clone()) {
+        tracing::error!("Scenario {} returned an error: {err}", hook.scenario);
+        hook.scenario_errors.fetch_add(1, Ordering::Relaxed);
+        if cfg!(feature = "metrics") {
+            let (name, labels) = scenario_metric("balter_scenario_errors", &hook.scenario);
+            metrics::counter!(name, labels).increment(1);
+        }
+    } else {
+        tracing::error!("Scenario returned an error: {err}");
+    }
+}
+
+/// Return types supported for functions marked with the `#[scenario]` macro.
+pub trait ScenarioResult {
+    fn into_result(self) -> Result<(), String>;
+}
+
+impl ScenarioResult for () {
+    fn into_result(self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl<E: Display> ScenarioResult for Result<(), E> {
+    fn into_result(self) -> Result<(), String> {
+        self.map_err(|err| err.to_string())
     }
 }
 
 #[derive(Clone)]
 pub(crate) struct TransactionData {
-    pub limiter: Arc<ArcSwap<DefaultDirectRateLimiter>>,
+    /// Name of the scenario running the transaction, used to label transaction metrics
+    pub scenario: Arc<str>,
+    pub limiter: Arc<ArcSwap<Pacer>>,
     pub success: Arc<AtomicU64>,
     pub error: Arc<AtomicU64>,
     pub latency: Arc<AtomicBucket<Duration>>,
+    pub retry: Option<RetryPolicy>,
+    /// Number of runs of the scenario which returned an error
+    pub scenario_errors: Arc<AtomicU64>,
+    /// Number of runs of the scenario completed since the last sample
+    pub scenarios: Arc<AtomicU64>,
+    /// Whether the rate limiter paces transactions, or runs of the scenario
+    pub tps_target: TpsTarget,
+    /// Total transaction errors of each kind, indexed by [ErrorKind::index]
+    pub error_kinds: Arc<[AtomicU64; ErrorKind::ALL.len()]>,
+    /// Number of permits acquired from the rate limiter
+    pub acquired: Arc<AtomicU64>,
+    /// Number of permits which weren't immediately available, and had to wait on the rate limiter
+    pub throttled: Arc<AtomicU64>,
+    /// Rate limiters capping the TPS of individual transactions, by transaction name
+    pub transaction_limiters: Arc<HashMap<String, Pacer>>,
+    /// Number of transactions which had to wait on their transaction's cap
+    pub capped: Arc<AtomicU64>,
+    /// Whether to skip the transaction body, as set with `.dry_run()`
+    pub dry_run: bool,
+    /// Maximum number of transactions to send, and the number sent so far
+    pub budget: Option<(u64, Arc<AtomicU64>)>,
+    /// Number of responses with each HTTP status code, indexed by the code
+    #[cfg_attr(not(feature = "http"), allow(dead_code))]
+    pub status_codes: Arc<[AtomicU64; STATUS_CODES]>,
+}
+
+impl TransactionData {
+    async fn attempt<T, D>(
+        &self,
+        labels: TransactionLabels,
+        func: T,
+        dry_run: D,
+    ) -> (T::Output, Duration)
+    where
+        T: Future,
+        D: FnOnce() -> Option<T::Output>,
+    {
+        if let Some((limit, sent)) = &self.budget {
+            // NOTE: Once the budget is used up, the transaction is never sent and the task waits
+            // here until the scenario shuts down and aborts it.
+            if sent.fetch_add(1, Ordering::Relaxed) >= *limit {
+                std::future::pending::<()>().await;
+            }
+        }
+
+        // NOTE: The transaction's own cap is waited on first, so that a permit from the scenario
+        // limiter isn't held (and wasted) while waiting on it.
+        if let Some(limiter) = self.transaction_limiters.get(labels.name) {
+            limiter.until_ready(&self.capped).await;
+        }
+        if self.tps_target == TpsTarget::Transactions {
+            self.acquire().await;
+        }
+
+        let start = Instant::now();
+        let res = if self.dry_run {
+            dry_run().unwrap_or_else(|| {
+                panic!(
+                    "Unable to dry run transaction {}, because its output has no Default value",
+                    labels.name
+                )
+            })
+        } else {
+            func.await
+        };
+        (res, start.elapsed())
+    }
+
+    /// Wait on the rate limiter for a permit to send a transaction, or to start a run of the
+    /// scenario
+    pub async fn acquire(&self) {
+        self.acquired.fetch_add(1, Ordering::Relaxed);
+        self.limiter.load().until_ready(&self.throttled).await;
+    }
+
+    #[cfg(feature = "http")]
+    fn record_status(&self, labels: TransactionLabels, legacy_name: &'static str, status: u16) {
+        if let Some(count) = self.status_codes.get(usize::from(status)) {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+        if cfg!(feature = "metrics") {
+            let (name, mut metric_labels) = transaction_metric(
+                "balter_transaction_status",
+                legacy_name,
+                &labels,
+                &self.scenario,
+            );
+            metric_labels.push(Label::new("status", status.to_string()));
+            metrics::counter!(name, metric_labels).increment(1);
+        }
+    }
+
+    /// Count a failed transaction towards the error rate, and towards the errors of its kind.
+    /// Every failure goes through here, so that the two never disagree.
+    pub fn record_failure(&self, kind: ErrorKind) {
+        self.error.fetch_add(1, Ordering::Relaxed);
+        self.error_kinds[kind.index()].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record(&self, labels: TransactionLabels, error: Option<ErrorKind>, elapsed: Duration) {
+        // TODO: Unfortunately we're duplicating all data collection here, which isn't ideal.
+        // It makes more sense to move the metric logging out of the individual
+        // transaction_hooks, and to log it in the sampler.
+        self.latency.push(elapsed);
+        if cfg!(feature = "metrics") {
+            let (name, metric_labels) = transaction_metric(
+                "balter_transaction_latency",
+                labels.latency,
+                &labels,
+                &self.scenario,
+            );
+            crate::metrics::record_exemplar(&name, &metric_labels, elapsed.as_secs_f64());
+            metrics::histogram!(name, metric_labels).record(elapsed.as_secs_f64());
+        }
+
+        if let Some(kind) = error {
+            self.record_failure(kind);
+            if cfg!(feature = "metrics") {
+                let (name, mut metric_labels) = transaction_metric(
+                    "balter_transaction_error",
+                    labels.error,
+                    &labels,
+                    &self.scenario,
+                );
+                metric_labels.push(Label::new("kind", kind.as_str()));
+                metrics::counter!(name, metric_labels).increment(1);
+            }
+        } else {
+            self.success.fetch_add(1, Ordering::Relaxed);
+
+            if cfg!(feature = "metrics") {
+                let (name, metric_labels) = transaction_metric(
+                    "balter_transaction_success",
+                    labels.success,
+                    &labels,
+                    &self.scenario,
+                );
+                metrics::counter!(name, metric_labels).increment(1);
+            }
+        }
+    }
 }
 
 tokio::task_local! {
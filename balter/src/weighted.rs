@@ -0,0 +1,179 @@
+//! Weighted selection of transactions within a Scenario.
+//!
+//! See the [weighted!](crate::weighted!) macro.
+use rand::Rng;
+
+/// Weighted random selection of an index in constant time, using Vose's alias method.
+#[derive(Clone, Debug)]
+pub struct AliasTable {
+    prob: Vec<f64>,
+    alias: Vec<usize>,
+}
+
+impl AliasTable {
+    /// Build an alias table for the given weights. Weights do not need to sum to 1.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if no weights are provided, if any weight is negative or not
+    /// finite, or if all weights are zero.
+    pub fn new(weights: &[f64]) -> Self {
+        if weights.is_empty() {
+            panic!("At least one weight must be provided");
+        }
+        if let Some(weight) = weights.iter().find(|w| !w.is_finite() || **w < 0.) {
+            panic!("Weights must be non-negative and finite. Given: {weight}");
+        }
+        let total: f64 = weights.iter().sum();
+        if total <= 0. {
+            panic!("At least one weight must be non-zero");
+        }
+
+        let n = weights.len();
+        let mut prob: Vec<f64> = weights.iter().map(|w| w * n as f64 / total).collect();
+        let mut alias = vec![0; n];
+
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) = (0..n).partition(|&i| prob[i] < 1.);
+        while let (Some(s), Some(l)) = (small.pop(), large.pop()) {
+            alias[s] = l;
+            prob[l] -= 1. - prob[s];
+            if prob[l] < 1. {
+                small.push(l);
+            } else {
+                large.push(l);
+            }
+        }
+
+        // NOTE: Anything left over is due to floating point error, and should always be picked.
+        for i in small.into_iter().chain(large) {
+            prob[i] = 1.;
+        }
+
+        Self { prob, alias }
+    }
+
+    /// Number of weights in the table
+    pub fn len(&self) -> usize {
+        self.prob.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prob.is_empty()
+    }
+
+    /// Pick an index using the given RNG.
+    pub fn sample_with<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        let i = rng.gen_range(0..self.prob.len());
+        if rng.gen::<f64>() < self.prob[i] {
+            i
+        } else {
+            self.alias[i]
+        }
+    }
+
+    /// Pick an index using the RNG of the current Balter task (see [crate::rng]).
+    pub fn sample(&self) -> usize {
+        self.sample_with(&mut crate::rng())
+    }
+}
+
+/// Pick between transactions according to their weights.
+///
+/// Returns a closure which, each time it is called, randomly picks one of the provided
+/// transactions (async functions taking no arguments) and returns its future. The output of the
+/// transaction is discarded.
+///
+/// Each picked transaction is a single transaction as far as Balter is concerned: it waits on the
+/// rate limiter once, and counts once towards the TPS and error rate. The configured TPS is
+/// therefore split between the transactions according to their weights.
+///
+/// # Example
+/// ```no_run
+/// use balter::prelude::*;
+///
+/// #[scenario]
+/// async fn my_scenario() {
+///     let mix = weighted!([(0.7, read), (0.25, write), (0.05, delete)]);
+///     loop {
+///         mix().await;
+///     }
+/// }
+///
+/// #[transaction]
+/// async fn read() -> Result<(), ()> {
+///     Ok(())
+/// }
+///
+/// #[transaction]
+/// async fn write() -> Result<(), ()> {
+///     Ok(())
+/// }
+///
+/// #[transaction]
+/// async fn delete() -> Result<(), ()> {
+///     Ok(())
+/// }
+/// ```
+///
+/// # Panics
+///
+/// Panics under the same conditions as [AliasTable::new].
+#[macro_export]
+macro_rules! weighted {
+    ([$(($weight:expr, $transaction:expr)),+ $(,)?]) => {{
+        type WeightedFuture = ::core::pin::Pin<Box<dyn ::core::future::Future<Output = ()> + Send>>;
+
+        let table = $crate::weighted::AliasTable::new(&[$($weight as f64),+]);
+        let transactions: Vec<fn() -> WeightedFuture> = vec![$(
+            || Box::pin(async {
+                let _ = $transaction().await;
+            })
+        ),+];
+
+        move || -> WeightedFuture { transactions[table.sample()]() }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    #[test]
+    fn test_alias_table() {
+        let table = AliasTable::new(&[0.7, 0.25, 0.05, 0.]);
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        let mut counts = [0; 4];
+        for _ in 0..100_000 {
+            counts[table.sample_with(&mut rng)] += 1;
+        }
+
+        assert!((counts[0] as f64 / 100_000. - 0.7).abs() < 0.01);
+        assert!((counts[1] as f64 / 100_000. - 0.25).abs() < 0.01);
+        assert!((counts[2] as f64 / 100_000. - 0.05).abs() < 0.01);
+        assert_eq!(counts[3], 0);
+    }
+
+    #[tokio::test]
+    async fn test_weighted() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static A: AtomicU64 = AtomicU64::new(0);
+        static B: AtomicU64 = AtomicU64::new(0);
+
+        async fn a() {
+            A.fetch_add(1, Ordering::Relaxed);
+        }
+        async fn b() {
+            B.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mix = weighted!([(3, a), (1, b)]);
+        for _ in 0..10_000 {
+            mix().await;
+        }
+
+        let ratio = A.load(Ordering::Relaxed) as f64 / 10_000.;
+        assert!((ratio - 0.75).abs() < 0.03);
+    }
+}
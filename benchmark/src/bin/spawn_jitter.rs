@@ -0,0 +1,59 @@
+//! Measures how late each sample lands while the concurrency climbs into the thousands.
+//!
+//! Spawning a large number of tasks in one go used to hold up the sampling loop, which showed up
+//! as samples running well past their interval. Run this against two revisions to compare:
+//!
+//! ```sh
+//! cargo run --release --bin spawn_jitter
+//! ```
+use balter::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::sleep;
+
+const INTERVAL: Duration = Duration::from_millis(200);
+
+#[tokio::main]
+async fn main() {
+    let samples = Arc::new(Mutex::new(vec![]));
+
+    let stats = scenario_slow()
+        // NOTE: Each task runs at 10 TPS, so this needs a concurrency of at least 5,000.
+        .tps(50_000)
+        .concurrency_step(2_500)
+        .sample_window(2)
+        .sample_interval(INTERVAL)
+        .sample_interval_bounds(INTERVAL, INTERVAL)
+        .duration(Duration::from_secs(30))
+        .on_sample({
+            let samples = samples.clone();
+            move |samples_set, stats| {
+                let elapsed = samples_set.iter().map(|sample| sample.elapsed).max();
+                if let Some(elapsed) = elapsed {
+                    samples.lock().unwrap().push((elapsed, stats.concurrency));
+                }
+            }
+        })
+        .await;
+
+    let samples = samples.lock().unwrap();
+    let mut max_jitter = Duration::ZERO;
+    for (elapsed, concurrency) in samples.iter() {
+        let jitter = elapsed.saturating_sub(INTERVAL);
+        println!("concurrency={concurrency:>5} jitter={jitter:?}");
+        max_jitter = max_jitter.max(jitter);
+    }
+
+    println!("max_jitter={max_jitter:?} concurrency={}", stats.concurrency);
+}
+
+#[scenario]
+async fn scenario_slow() {
+    let _ = transaction_slow().await;
+}
+
+#[transaction]
+async fn transaction_slow() -> Result<(), ()> {
+    sleep(Duration::from_millis(100)).await;
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use axum::{
     debug_handler,
     extract::{Json, Path},
-    http::StatusCode,
+    http::{HeaderName, StatusCode},
     routing::get,
     Router,
 };
@@ -9,7 +9,8 @@ use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
 use lazy_static::lazy_static;
 #[allow(unused)]
 use metrics::{counter, gauge, histogram};
-use rand_distr::{Distribution, SkewNormal};
+use rand::Rng;
+use rand_distr::{Distribution, Normal, SkewNormal};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::SocketAddr;
@@ -20,7 +21,7 @@ use std::{
         atomic::{AtomicBool, AtomicU64, Ordering},
         Arc, RwLock as ARwLock,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 #[allow(unused)]
 use tracing::{debug, error, instrument};
@@ -41,13 +42,23 @@ pub async fn run(addr: SocketAddr) {
         .route(
             "/limited/:max_tps/delay/ms/:delay_ms/server/:server_id",
             get(limited),
-        );
+        )
+        .route(
+            "/ramping_capacity/:start_tps/:end_tps/after/:secs/scenario/:scenario_name",
+            get(ramping_capacity),
+        )
+        .route(
+            "/latency_spike/:delay_ms/:spike_ms/from/:start/to/:end/scenario/:scenario_name",
+            get(latency_spike),
+        )
+        .route("/error_rate/:pct", get(error_rate))
+        .route("/latency_dist/:mean_ms/:stddev_ms", get(latency_dist));
 
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
     axum::serve(listener, app).await.unwrap();
 }
 
-/** New Handler **/
+/* New Handler */
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
@@ -206,7 +217,7 @@ async fn tps_updater_task() {
     }
 }
 
-/** Old Handlers **/
+/* Old Handlers */
 
 #[debug_handler]
 pub async fn delay(Path(delay_ms): Path<u64>) {
@@ -278,13 +289,138 @@ pub async fn limited(
     Ok(())
 }
 
-/** Utils **/
+/// Header with the capacity (in TPS) the `/ramping_capacity` route is scheduled to have.
+pub const CAPACITY_HEADER: &str = "x-mock-capacity";
+
+struct RampingCapacity {
+    start: Instant,
+    start_limiter: DefaultDirectRateLimiter,
+    end_limiter: DefaultDirectRateLimiter,
+}
+
+lazy_static! {
+    static ref RAMPING_MAP: Arc<ARwLock<HashMap<String, Arc<RampingCapacity>>>> =
+        Arc::new(ARwLock::new(HashMap::new()));
+}
+
+/// Like the `/max` route, but the max TPS changes from `start_tps` to `end_tps` once `secs`
+/// have passed since the first request for the scenario.
+#[debug_handler]
+pub async fn ramping_capacity(
+    Path((start_tps, end_tps, secs, scenario_name)): Path<(u32, u32, u64, String)>,
+) -> (StatusCode, [(HeaderName, String); 1]) {
+    counter!("mock-server.tps").increment(1);
+    TPS_MEASURE.fetch_add(1, Ordering::Relaxed);
+
+    let read = RAMPING_MAP.read().unwrap().get(&scenario_name).cloned();
+    let ramping = if let Some(ramping) = read {
+        ramping
+    } else {
+        RAMPING_MAP
+            .write()
+            .unwrap()
+            .entry(scenario_name)
+            .or_insert_with(|| {
+                Arc::new(RampingCapacity {
+                    start: Instant::now(),
+                    start_limiter: rate_limiter(start_tps),
+                    end_limiter: rate_limiter(end_tps),
+                })
+            })
+            .clone()
+    };
+
+    let (capacity, limiter) = if ramping.start.elapsed() < Duration::from_secs(secs) {
+        (start_tps, &ramping.start_limiter)
+    } else {
+        (end_tps, &ramping.end_limiter)
+    };
+
+    let status = match limiter.check() {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (
+        status,
+        [(
+            HeaderName::from_static(CAPACITY_HEADER),
+            capacity.to_string(),
+        )],
+    )
+}
+
+lazy_static! {
+    static ref SPIKE_MAP: Arc<ARwLock<HashMap<String, Instant>>> =
+        Arc::new(ARwLock::new(HashMap::new()));
+}
+
+/// Delay for `delay_ms`, except between `start` and `end` seconds since the first request for
+/// the scenario, when the delay spikes to `spike_ms`.
+#[debug_handler]
+pub async fn latency_spike(
+    Path((delay_ms, spike_ms, start, end, scenario_name)): Path<(u64, u64, u64, u64, String)>,
+) {
+    counter!("mock-server.tps").increment(1);
+    TPS_MEASURE.fetch_add(1, Ordering::Relaxed);
+
+    let read = SPIKE_MAP.read().unwrap().get(&scenario_name).copied();
+    let first = match read {
+        Some(first) => first,
+        None => *SPIKE_MAP
+            .write()
+            .unwrap()
+            .entry(scenario_name)
+            .or_insert_with(Instant::now),
+    };
+
+    let elapsed = first.elapsed();
+    let delay_ms = if elapsed >= Duration::from_secs(start) && elapsed < Duration::from_secs(end) {
+        spike_ms
+    } else {
+        delay_ms
+    };
+
+    let latency = Duration::from_millis(delay_ms);
+    tokio::time::sleep(latency).await;
+    histogram!("mock-server.latency").record(latency.as_secs_f64());
+}
+
+/// Return a 500 for `pct` percent of requests, chosen at random.
+#[debug_handler]
+pub async fn error_rate(Path(pct): Path<f64>) -> Result<(), StatusCode> {
+    counter!("mock-server.tps").increment(1);
+    TPS_MEASURE.fetch_add(1, Ordering::Relaxed);
+
+    if rand::thread_rng().gen_bool((pct / 100.).clamp(0., 1.)) {
+        counter!("mock-server.error").increment(1);
+        Err(StatusCode::INTERNAL_SERVER_ERROR)
+    } else {
+        Ok(())
+    }
+}
+
+/// Delay for a latency sampled from a normal distribution, truncated at zero.
+#[debug_handler]
+pub async fn latency_dist(Path((mean_ms, stddev_ms)): Path<(f64, f64)>) -> Result<(), StatusCode> {
+    counter!("mock-server.tps").increment(1);
+    TPS_MEASURE.fetch_add(1, Ordering::Relaxed);
+
+    let normal = Normal::new(mean_ms, stddev_ms).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let latency_ms: f64 = normal.sample(&mut rand::thread_rng());
+    let latency = Duration::from_secs_f64(latency_ms.max(0.) / 1000.);
+
+    tokio::time::sleep(latency).await;
+    histogram!("mock-server.latency").record(latency.as_secs_f64());
+    Ok(())
+}
+
+/* Utils */
 
 pub fn rate_limiter(tps: u32) -> DefaultDirectRateLimiter {
     RateLimiter::direct(Quota::per_second(NonZeroU32::new(tps).unwrap()))
 }
 
-/** TPS Printer **/
+/* TPS Printer */
 
 static TPS_MEASURE: AtomicU64 = AtomicU64::new(0);
 
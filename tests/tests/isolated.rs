@@ -8,6 +8,8 @@ mod tests {
     use balter::prelude::*;
     use mock_service::prelude::*;
     use reqwest::Client;
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
     use std::sync::OnceLock;
     use std::time::Duration;
 
@@ -30,6 +32,431 @@ mod tests {
         assert!(stats.concurrency >= 10);
     }
 
+    #[tokio::test]
+    async fn single_instance_fixed_concurrency() {
+        init().await;
+
+        let stats = scenario_1ms_delay()
+            .tps(1_000)
+            .fixed_concurrency(4)
+            .duration(Duration::from_secs(10))
+            .await;
+
+        assert_eq!(stats.concurrency, 4);
+        assert_eq!(stats.goal_tps, 1_000);
+        assert!(stats.actual_tps > 950.);
+        assert!(stats.latency_p50 >= Duration::from_millis(1));
+    }
+
+    #[tokio::test]
+    async fn single_instance_until() {
+        init().await;
+
+        let stats = scenario_1ms_delay()
+            .tps(1_000)
+            .duration(Duration::from_secs(120))
+            .until(|samples| samples.elapsed() > Duration::from_secs(10))
+            .await;
+
+        assert_eq!(stats.stop_reason, StopReason::Until);
+        assert!(stats.actual_tps > 950.);
+    }
+
+    #[tokio::test]
+    async fn single_instance_stop() {
+        init().await;
+
+        let handle = scenario_1ms_delay()
+            .tps(1_000)
+            .duration(Duration::from_secs(120))
+            .spawn();
+
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert!(!handle.is_finished());
+        handle.stop();
+        let stats = handle.stats().await;
+
+        assert_eq!(stats.stop_reason, StopReason::Stopped);
+        assert!(stats.elapsed < Duration::from_secs(20));
+        assert!(stats.actual_tps > 950.);
+    }
+
+    #[tokio::test]
+    async fn single_instance_on_sample() {
+        init().await;
+
+        let (tx, rx) = tokio::sync::watch::channel(LiveStats::default());
+        let stats = scenario_1ms_delay()
+            .tps(1_000)
+            .duration(Duration::from_secs(30))
+            .on_sample(move |_, stats| {
+                let _ = tx.send(*stats);
+            })
+            .await;
+
+        let live = *rx.borrow();
+        assert_eq!(live.goal_tps, stats.goal_tps);
+        assert!(live.elapsed >= Duration::from_secs(30));
+        assert!(live.actual_tps > 950.);
+    }
+
+    #[tokio::test]
+    async fn single_instance_on_stability_change() {
+        init().await;
+
+        let first = std::sync::Arc::new(OnceLock::new());
+        let stats = scenario_1ms_delay()
+            .tps(1_000)
+            .duration(Duration::from_secs(30))
+            .on_stability_change({
+                let first = first.clone();
+                move |stats| {
+                    if stats.stable {
+                        let _ = first.set(*stats);
+                    }
+                }
+            })
+            .await;
+
+        let first = first.get().expect("scenario never stabilized");
+        assert!(stats.stable);
+        assert!(first.stable);
+        assert_eq!(first.goal_tps, 1_000);
+        assert!(first.elapsed < stats.elapsed);
+    }
+
+    #[tokio::test]
+    async fn single_instance_request_budget() {
+        init().await;
+
+        let stats = scenario_1ms_delay()
+            .tps(1_000)
+            .duration(Duration::from_secs(120))
+            .request_budget(5_000)
+            .await;
+
+        assert_eq!(stats.stop_reason, StopReason::Budget);
+        assert!(stats.elapsed < Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn single_instance_scenario_errors() {
+        let stats = scenario_setup_error()
+            .tps(100)
+            .iterations(50)
+            .duration(Duration::from_secs(60))
+            .await;
+
+        assert_eq!(stats.stop_reason, StopReason::Iterations);
+        assert_eq!(stats.scenario_errors, 50);
+    }
+
+    #[scenario]
+    async fn scenario_setup_error() -> Result<(), String> {
+        Err("setup failed".to_string())
+    }
+
+    #[tokio::test]
+    async fn single_instance_named_scenario() {
+        let stats = scenario_renamed()
+            .tps(100)
+            .iterations(20)
+            .duration(Duration::from_secs(60))
+            .await;
+
+        assert_eq!(stats.stop_reason, StopReason::Iterations);
+        assert_eq!(stats.scenario_errors, 0);
+    }
+
+    #[scenario(name = "renamed_scenario")]
+    async fn scenario_renamed() {}
+
+    #[tokio::test]
+    async fn single_instance_error_kinds() {
+        let stats = scenario_error_kinds()
+            .tps(100)
+            .iterations(20)
+            .duration(Duration::from_secs(60))
+            .await;
+
+        assert_eq!(stats.errors.timeout, 20);
+        assert_eq!(stats.errors.other, 20);
+        assert_eq!(stats.errors.total(), 40);
+    }
+
+    #[scenario]
+    async fn scenario_error_kinds() {
+        let _ = transaction_timeout().await;
+        let _ = transaction_unclassified().await;
+    }
+
+    #[transaction]
+    async fn transaction_timeout() -> Result<(), std::io::Error> {
+        Err(std::io::ErrorKind::TimedOut.into())
+    }
+
+    #[transaction]
+    async fn transaction_unclassified() -> Result<(), String> {
+        Err("unclassified".to_string())
+    }
+
+    #[tokio::test]
+    async fn single_instance_http_request() {
+        init().await;
+
+        let stats = scenario_http_ok()
+            .tps(100)
+            .iterations(50)
+            .duration(Duration::from_secs(60))
+            .await;
+        assert_eq!(stats.stop_reason, StopReason::Iterations);
+        assert_eq!(stats.errors.total(), 0);
+        assert_eq!(stats.status_codes, BTreeMap::from([(200, 50)]));
+
+        let stats = scenario_http_not_found()
+            .tps(100)
+            .iterations(50)
+            .duration(Duration::from_secs(60))
+            .await;
+        assert_eq!(stats.errors.client, 50);
+        assert_eq!(stats.errors.total(), 50);
+        assert_eq!(stats.status_codes, BTreeMap::from([(404, 50)]));
+
+        // Only the first request each second is let through
+        let stats = scenario_http_limited()
+            .tps(100)
+            .iterations(50)
+            .duration(Duration::from_secs(60))
+            .await;
+        assert!(stats.errors.server > 40);
+        assert_eq!(stats.errors.total(), stats.errors.server);
+        assert_eq!(stats.status_codes.values().sum::<u64>(), 50);
+        assert_eq!(stats.status_codes[&200], 50 - stats.errors.server);
+    }
+
+    #[scenario]
+    async fn scenario_http_ok() {
+        http_get("http://0.0.0.0:3002/delay/ms/1").await;
+    }
+
+    #[scenario]
+    async fn scenario_http_not_found() {
+        http_get("http://0.0.0.0:3002/missing").await;
+    }
+
+    #[scenario]
+    async fn scenario_http_limited() {
+        http_get("http://0.0.0.0:3002/max/1/delay/ms/1/scenario/http").await;
+    }
+
+    async fn http_get(url: &str) {
+        let client = CLIENT.get_or_init(Client::new);
+        let req = client.get(url).build().unwrap();
+        let _ = balter::http::request(client, req).await;
+    }
+
+    #[tokio::test]
+    async fn single_instance_infallible_transaction() {
+        let stats = scenario_infallible()
+            .tps(100)
+            .iterations(20)
+            .duration(Duration::from_secs(60))
+            .await;
+
+        assert_eq!(stats.stop_reason, StopReason::Iterations);
+        assert_eq!(stats.error_rate, 0.);
+        assert_eq!(stats.errors.total(), 0);
+    }
+
+    #[scenario]
+    async fn scenario_infallible() {
+        assert_eq!(transaction_infallible(2).await, 4);
+        transaction_unit().await;
+    }
+
+    #[transaction]
+    async fn transaction_infallible(val: u64) -> u64 {
+        val * 2
+    }
+
+    #[transaction]
+    async fn transaction_unit() {}
+
+    #[tokio::test]
+    async fn single_instance_suite() {
+        init().await;
+
+        let stats = Suite::new()
+            .scenario(scenario_1ms_single().tps(200))
+            .scenario(scenario_1ms_delay().tps(500))
+            .duration(Duration::from_secs(10))
+            .await;
+
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats["scenario_1ms_single"].goal_tps, 200);
+        assert!(stats["scenario_1ms_single"].actual_tps > 190.);
+        assert_eq!(stats["scenario_1ms_delay"].goal_tps, 500);
+        assert!(stats["scenario_1ms_delay"].actual_tps > 475.);
+    }
+
+    #[tokio::test]
+    async fn single_instance_once() {
+        init().await;
+
+        let stats = scenario_1ms_single().once().await;
+
+        assert_eq!(stats.stop_reason, StopReason::Iterations);
+        assert_eq!(stats.concurrency, 1);
+        assert_eq!(stats.error_rate, 0.);
+        assert!(stats.latency_p50 >= Duration::from_millis(1));
+        assert!(stats.elapsed < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn single_instance_missing_duration() {
+        init().await;
+
+        let stats = scenario_1ms_single().ramp_tps(100, 1_000).await;
+
+        assert_eq!(stats.stop_reason, StopReason::MissingDuration);
+        assert!(stats.aborted);
+        assert_eq!(stats.elapsed, Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn single_instance_iterations() {
+        init().await;
+
+        let stats = scenario_1ms_single()
+            .tps(1_000)
+            .iterations(10_000)
+            .duration(Duration::from_secs(120))
+            .await;
+
+        assert_eq!(stats.stop_reason, StopReason::Iterations);
+        assert!(stats.elapsed >= Duration::from_secs(9));
+        assert!(stats.actual_tps > 900.);
+    }
+
+    #[tokio::test]
+    async fn single_instance_args() {
+        for multiplier in [1u64, 3] {
+            let stats = scenario_args()
+                .tps(100)
+                .iterations(20)
+                .duration(Duration::from_secs(60))
+                .args(multiplier)
+                .await;
+
+            assert_eq!(stats.stop_reason, StopReason::Iterations);
+        }
+
+        assert_eq!(ARGS_TOTAL.load(Ordering::Relaxed), 20 + 20 * 3);
+    }
+
+    static ARGS_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+    #[scenario]
+    async fn scenario_args() {
+        let multiplier = balter::args::<u64>();
+        let val = transaction_infallible(multiplier).await;
+        ARGS_TOTAL.fetch_add(val / 2, Ordering::Relaxed);
+    }
+
+    #[tokio::test]
+    async fn single_instance_shared_state() {
+        init().await;
+
+        let stats = scenario_1ms_shared_client()
+            .tps(1_000)
+            .iterations(10_000)
+            .duration(Duration::from_secs(120))
+            .with_state(std::sync::Arc::new(Client::new()))
+            .await;
+
+        // NOTE: Fetching missing state panics
+        assert_eq!(stats.stop_reason, StopReason::Iterations);
+        assert_eq!(stats.panics, 0);
+        assert!(stats.elapsed >= Duration::from_secs(9));
+    }
+
+    #[tokio::test]
+    async fn single_instance_single_threaded() {
+        init().await;
+
+        let stats = scenario_1ms_single_threaded()
+            .tps(500)
+            .duration(Duration::from_secs(10))
+            .await;
+
+        assert_eq!(stats.goal_tps, 500);
+        assert_eq!(stats.panics, 0);
+        assert!(stats.actual_tps > 450.);
+    }
+
+    #[scenario(single_threaded)]
+    async fn scenario_1ms_single_threaded() {
+        // NOTE: An Rc held across an await makes the scenario !Send
+        let client = std::rc::Rc::new(Client::new());
+        loop {
+            let _ = transaction_1ms(&client).await;
+        }
+    }
+
+    #[scenario]
+    async fn scenario_1ms_shared_client() {
+        let client = balter::state::<Client>();
+        let _ = transaction_1ms(&client).await;
+    }
+
+    #[tokio::test]
+    async fn single_instance_setup_teardown() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static TEARDOWNS: AtomicU64 = AtomicU64::new(0);
+
+        let stats = scenario_setup_token()
+            .tps(100)
+            .iterations(20)
+            .duration(Duration::from_secs(60))
+            .setup(async { SetupToken(42) })
+            .teardown(async {
+                TEARDOWNS.fetch_add(1, Ordering::Relaxed);
+            })
+            .await;
+
+        // NOTE: The scenario panics if the token is missing or wrong
+        assert_eq!(stats.stop_reason, StopReason::Iterations);
+        assert_eq!(stats.panics, 0);
+        assert_eq!(TEARDOWNS.load(Ordering::Relaxed), 1);
+
+        // Every transaction fails, so the scenario is aborted
+        let stats = scenario_error_kinds()
+            .tps(100)
+            .duration(Duration::from_secs(60))
+            .sample_interval(Duration::from_millis(200))
+            .abort_on_error_rate(0.5, 2)
+            .teardown(async {
+                TEARDOWNS.fetch_add(1, Ordering::Relaxed);
+            })
+            .await;
+
+        assert!(stats.aborted);
+        assert_eq!(TEARDOWNS.load(Ordering::Relaxed), 2);
+    }
+
+    struct SetupToken(u64);
+
+    #[scenario]
+    async fn scenario_setup_token() {
+        assert_eq!(balter::state::<SetupToken>().0, 42);
+    }
+
+    #[scenario]
+    async fn scenario_1ms_single() {
+        let client = CLIENT.get_or_init(Client::new);
+        let _ = transaction_1ms(client).await;
+    }
+
     #[scenario]
     async fn scenario_1ms_delay() {
         let client = Client::new();
@@ -108,6 +535,51 @@ mod tests {
         assert!(stats.concurrency >= 10);
     }
 
+    #[tokio::test]
+    async fn single_instance_generator_bound_tps() {
+        init().await;
+
+        let stats = scenario_1ms_serialized()
+            .tps(5_000)
+            .duration(Duration::from_secs(30))
+            .await;
+
+        // NOTE: Only one transaction runs at a time, so TPS plateaus below 1,000 no matter the
+        // concurrency, while the latency of the service stays flat.
+        assert!(dbg!(stats.goal_tps) < 1_000);
+        assert!(dbg!(stats.actual_tps) > 0.8 * stats.goal_tps as f64);
+        assert!(stats.latency_p50 < Duration::from_millis(10));
+    }
+
+    #[tokio::test]
+    async fn single_instance_injected_errors() {
+        init().await;
+
+        let stats = scenario_injected_errors()
+            .tps(500)
+            .duration(Duration::from_secs(10))
+            .await;
+
+        assert!(dbg!(stats.error_rate) > 0.07);
+        assert!(dbg!(stats.error_rate) < 0.13);
+        assert_eq!(stats.goal_tps, 500);
+    }
+
+    #[tokio::test]
+    async fn single_instance_latency_distribution() {
+        init().await;
+
+        let stats = scenario_latency_distribution()
+            .tps(500)
+            .duration(Duration::from_secs(10))
+            .await;
+
+        assert!(dbg!(stats.latency_p50) > Duration::from_millis(18));
+        assert!(dbg!(stats.latency_p50) < Duration::from_millis(25));
+        assert!(dbg!(stats.latency_p90) > stats.latency_p50 + Duration::from_millis(3));
+        assert_eq!(stats.goal_tps, 500);
+    }
+
     #[tokio::test]
     async fn single_instance_error_rate() {
         init().await;
@@ -124,6 +596,115 @@ mod tests {
         assert!(stats.concurrency >= 2);
     }
 
+    #[tokio::test]
+    async fn single_instance_error_rate_ramping_capacity() {
+        init().await;
+
+        let stats = scenario_ramping_capacity()
+            .error_rate(0.03)
+            .duration(Duration::from_secs(90))
+            .await;
+
+        // NOTE: The capacity drops from 1,200 to 600 TPS after 30s, which the goal TPS should
+        // follow back down.
+        let capacity = RAMPING_CAPACITY.load(Ordering::Relaxed);
+        assert_eq!(capacity, 600);
+        assert!(dbg!(stats.goal_tps) <= 660);
+        assert!(dbg!(stats.goal_tps) >= 420);
+    }
+
+    #[tokio::test]
+    async fn single_instance_latency_backoff() {
+        init().await;
+
+        let samples = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let stats = scenario_latency_spike()
+            .tps(2_000)
+            .duration(Duration::from_secs(60))
+            .latency_backoff(3.)
+            .on_sample({
+                let samples = samples.clone();
+                move |_, stats| samples.lock().unwrap().push(*stats)
+            })
+            .await;
+
+        // NOTE: Latency spikes from 5ms to 50ms between 30s and 45s, during which holding
+        // 2,000 TPS would take 10x the concurrency.
+        let samples = samples.lock().unwrap();
+        let before = samples
+            .iter()
+            .rev()
+            .find(|s| s.elapsed < Duration::from_secs(29))
+            .unwrap()
+            .concurrency;
+        let during = samples
+            .iter()
+            .filter(|s| s.elapsed > Duration::from_secs(31) && s.elapsed < Duration::from_secs(45))
+            .map(|s| s.concurrency)
+            .min()
+            .unwrap();
+        assert!(dbg!(during) < dbg!(before));
+        assert!(stats.actual_tps > 1_800.);
+    }
+
+    #[scenario]
+    async fn scenario_latency_spike() {
+        let _ =
+            transaction_get("http://0.0.0.0:3002/latency_spike/5/50/from/30/to/45/scenario/spike")
+                .await;
+    }
+
+    #[tokio::test]
+    async fn single_instance_abort_on_error_rate() {
+        init().await;
+
+        let stats = scenario_1ms_max_2000()
+            .tps(5_000)
+            .duration(Duration::from_secs(120))
+            .abort_on_error_rate(0.5, 3)
+            .await;
+
+        assert_eq!(stats.stop_reason, StopReason::ErrorRate);
+        assert!(stats.aborted);
+        assert!(stats.elapsed < Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn single_instance_tps_controller() {
+        use balter::SampleSet;
+        use std::num::NonZeroU32;
+
+        /// Steps up by 500 TPS each time the sampler stabilizes, stopping at 2,000 TPS.
+        struct StepUp(NonZeroU32);
+
+        impl Controller for StepUp {
+            fn initial_tps(&self) -> NonZeroU32 {
+                self.0
+            }
+
+            fn analyze(&mut self, _samples: &SampleSet, stable: bool) -> ControllerOutcome {
+                if stable {
+                    if self.0.get() >= 2_000 {
+                        return ControllerOutcome::Stop;
+                    }
+                    self.0 = self.0.saturating_add(500);
+                }
+                ControllerOutcome::Limit(self.0)
+            }
+        }
+
+        init().await;
+
+        let stats = scenario_1ms_delay()
+            .tps_controller(StepUp(NonZeroU32::new(500).unwrap()))
+            .duration(Duration::from_secs(120))
+            .await;
+
+        assert_eq!(stats.stop_reason, StopReason::Controller);
+        assert_eq!(stats.goal_tps, 2_000);
+        assert!(stats.stable);
+    }
+
     /* Scenario Helpers */
 
     static CLIENT: OnceLock<Client> = OnceLock::new();
@@ -143,6 +724,66 @@ mod tests {
         Ok(())
     }
 
+    #[scenario]
+    async fn scenario_1ms_serialized() {
+        static LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+        let _guard = LOCK.lock().await;
+        let _ = transaction_1ms_serialized().await;
+    }
+
+    #[transaction]
+    async fn transaction_1ms_serialized() -> Result<(), reqwest::Error> {
+        let client = CLIENT.get_or_init(Client::new);
+        client.get("http://0.0.0.0:3002/delay/ms/1").send().await?;
+        Ok(())
+    }
+
+    #[scenario]
+    async fn scenario_injected_errors() {
+        let _ = transaction_get("http://0.0.0.0:3002/error_rate/10").await;
+    }
+
+    #[scenario]
+    async fn scenario_latency_distribution() {
+        let _ = transaction_get("http://0.0.0.0:3002/latency_dist/20/5").await;
+    }
+
+    #[transaction]
+    async fn transaction_get(url: &str) -> Result<(), reqwest::Error> {
+        let client = CLIENT.get_or_init(Client::new);
+        client.get(url).send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    /// Capacity of the `/ramping_capacity` route as of the last response
+    static RAMPING_CAPACITY: AtomicU64 = AtomicU64::new(0);
+
+    #[scenario]
+    async fn scenario_ramping_capacity() {
+        let _ = transaction_ramping_capacity().await;
+    }
+
+    #[transaction]
+    async fn transaction_ramping_capacity() -> Result<(), reqwest::Error> {
+        let client = CLIENT.get_or_init(Client::new);
+        let res = client
+            .get("http://0.0.0.0:3002/ramping_capacity/1200/600/after/30/scenario/ramping")
+            .send()
+            .await?;
+
+        let capacity = res
+            .headers()
+            .get(mock_service::CAPACITY_HEADER)
+            .and_then(|capacity| capacity.to_str().ok()?.parse().ok());
+        if let Some(capacity) = capacity {
+            RAMPING_CAPACITY.store(capacity, Ordering::Relaxed);
+        }
+
+        res.error_for_status()?;
+        Ok(())
+    }
+
     #[scenario]
     async fn scenario_1ms_max_2000() {
         let _ = transaction_1ms_max_2000().await;
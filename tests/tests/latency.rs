@@ -23,6 +23,7 @@ mod tests {
 
         assert!(dbg!(stats.latency_p90) > Duration::from_millis(120));
         assert!(dbg!(stats.latency_p90) < Duration::from_millis(140));
+        assert_eq!(stats.latency_at_quantile, Some(stats.latency_p90));
     }
 
     static CLIENT: OnceLock<Client> = OnceLock::new();